@@ -1,5 +1,7 @@
 //package gamebot.host
 
+import android.graphics.BitmapFactory
+import android.util.Base64
 import android.view.ViewGroup
 import android.webkit.WebResourceRequest
 import android.webkit.WebView
@@ -12,7 +14,12 @@ import androidx.activity.compose.setContent
 import androidx.compose.foundation.layout.Column
 import androidx.compose.foundation.layout.Row
 import androidx.compose.foundation.layout.fillMaxSize
+import androidx.compose.foundation.layout.fillMaxWidth
+import androidx.compose.foundation.lazy.LazyColumn
+import androidx.compose.foundation.lazy.items
 import androidx.compose.material3.Button
+import androidx.compose.material3.HorizontalDivider
+import androidx.compose.material3.LinearProgressIndicator
 import androidx.compose.material3.TextField
 import androidx.compose.runtime.Composable
 import androidx.compose.runtime.CompositionLocalProvider
@@ -23,20 +30,34 @@ import androidx.compose.runtime.getValue
 import androidx.compose.runtime.mutableStateOf
 import androidx.compose.runtime.remember
 import androidx.compose.runtime.setValue
+import androidx.compose.ui.Alignment
 import androidx.compose.ui.Modifier
 import androidx.compose.ui.focus.onFocusChanged
+import androidx.compose.ui.graphics.asImageBitmap
 import androidx.compose.ui.platform.ComposeView
 import androidx.compose.ui.viewinterop.AndroidView
 import androidx.navigation.compose.composable
 import androidx.navigation.compose.rememberNavController
 import com.kevinnzou.web.rememberWebViewState
 import gamebot.host.d
+import gamebot.host.presentation.component.SectionSelect
+import gamebot.host.presentation.component.SectionSlider
+import gamebot.host.presentation.component.SectionSwitch
 import gamebot.host.presentation.component.SimpleNavHost
 import io.ktor.websocket.Frame
 import kotlinx.coroutines.channels.Channel
 import kotlinx.serialization.ExperimentalSerializationApi
 import kotlinx.serialization.SerialName
 import kotlinx.serialization.Serializable
+import kotlinx.serialization.json.JsonArray
+import kotlinx.serialization.json.JsonClassDiscriminator
+import kotlinx.serialization.json.JsonElement
+import kotlinx.serialization.json.JsonObject
+import kotlinx.serialization.json.JsonPrimitive
+import kotlinx.serialization.json.int
+import kotlinx.serialization.json.jsonArray
+import kotlinx.serialization.json.jsonObject
+import kotlin.math.roundToInt
 
 private class MyWebViewClient : WebViewClient() {
 
@@ -144,6 +165,144 @@ sealed interface Component {
         }
     }
 
+    @Serializable
+    @SerialName("Switch")
+    data class Switch(val label: String, val value: Boolean, val callbackId: Int = 0) : Component {
+        @Composable
+        override fun Render() {
+            val callback = LocalUIEvent.current
+            SectionSwitch(title = label, checked = value) {
+                callback(callbackId, CallbackValue.Bool(it))
+            }
+        }
+    }
+
+    @Serializable
+    @SerialName("Select")
+    data class Select(
+        val label: String,
+        val selected: Int,
+        val options: List<String>,
+        val callbackId: Int = 0
+    ) : Component {
+        @Composable
+        override fun Render() {
+            val callback = LocalUIEvent.current
+            SectionSelect(
+                title = label,
+                body = options.getOrElse(selected) { "" },
+                selection = options
+            ) {
+                callback(callbackId, CallbackValue.UInt(options.indexOf(it).toUInt()))
+            }
+        }
+    }
+
+    @Serializable
+    @SerialName("Section")
+    data class Section(val title: String, val content: List<Component> = emptyList()) : Component {
+        @Composable
+        override fun Render() {
+            gamebot.host.presentation.component.Section(title.ifEmpty { null }) {
+                content.forEach {
+                    it.Render()
+                }
+            }
+        }
+    }
+
+    @Serializable
+    @SerialName("Slider")
+    data class Slider(
+        val label: String,
+        val value: Double,
+        val min: Double,
+        val max: Double,
+        val step: Double = 0.0,
+        val callbackId: Int = 0
+    ) : Component {
+        @Composable
+        override fun Render() {
+            val callback = LocalUIEvent.current
+            // compose counts the stops between both ends
+            val steps = if (step > 0) ((max - min) / step).roundToInt() - 1 else 0
+            SectionSlider(
+                title = label,
+                value = value.toFloat(),
+                range = min.toFloat()..max.toFloat(),
+                steps = steps.coerceAtLeast(0),
+                format = {
+                    if (step > 0 && step % 1.0 == 0.0) it.roundToInt().toString() else "%.2f".format(it)
+                }
+            ) {
+                callback(callbackId, CallbackValue.Float(it.toDouble()))
+            }
+        }
+    }
+
+    @Serializable
+    @SerialName("Checkbox")
+    data class Checkbox(val label: String, val value: Boolean, val callbackId: Int = 0) : Component {
+        @Composable
+        override fun Render() {
+            val callback = LocalUIEvent.current
+            Row(verticalAlignment = Alignment.CenterVertically) {
+                androidx.compose.material3.Checkbox(checked = value, onCheckedChange = {
+                    callback(callbackId, CallbackValue.Bool(it))
+                })
+                androidx.compose.material3.Text(label)
+            }
+        }
+    }
+
+    @Serializable
+    @SerialName("LazyList")
+    data class LazyList(val content: List<Component> = emptyList()) : Component {
+        @Composable
+        override fun Render() {
+            LazyColumn {
+                items(content) {
+                    it.Render()
+                }
+            }
+        }
+    }
+
+    @Serializable
+    @SerialName("Divider")
+    data object Divider : Component {
+        @Composable
+        override fun Render() {
+            HorizontalDivider()
+        }
+    }
+
+    @Serializable
+    @SerialName("Image")
+    data class Image(val data: String) : Component {
+        @Composable
+        override fun Render() {
+            val bitmap = remember(data) {
+                val bytes = Base64.decode(data, Base64.DEFAULT)
+                BitmapFactory.decodeByteArray(bytes, 0, bytes.size)?.asImageBitmap()
+            } ?: return
+            androidx.compose.foundation.Image(bitmap, contentDescription = null)
+        }
+    }
+
+    @Serializable
+    @SerialName("Progress")
+    data class Progress(val value: Float? = null) : Component {
+        @Composable
+        override fun Render() {
+            if (value == null) {
+                LinearProgressIndicator(modifier = Modifier.fillMaxWidth())
+            } else {
+                LinearProgressIndicator(progress = { value }, modifier = Modifier.fillMaxWidth())
+            }
+        }
+    }
+
     @Serializable
     @SerialName("Text")
     data class Text(val content: String) : Component {
@@ -248,6 +407,71 @@ sealed interface Component {
 }
 
 
+// change to the json of the layout, see gamebot::ui::diff
+// path names fields of objects and indices of lists from the root
+@OptIn(ExperimentalSerializationApi::class)
+@Serializable
+@JsonClassDiscriminator("op")
+sealed class UIPatch {
+    abstract val path: List<JsonPrimitive>
+
+    @Serializable
+    @SerialName("Replace")
+    data class Replace(override val path: List<JsonPrimitive>, val node: JsonElement) : UIPatch()
+
+    @Serializable
+    @SerialName("Update")
+    data class Update(
+        override val path: List<JsonPrimitive>,
+        val props: JsonObject,
+        val removed: List<String> = emptyList()
+    ) : UIPatch()
+
+    @Serializable
+    @SerialName("Insert")
+    data class Insert(override val path: List<JsonPrimitive>, val index: Int, val node: JsonElement) :
+        UIPatch()
+
+    @Serializable
+    @SerialName("Remove")
+    data class Remove(override val path: List<JsonPrimitive>, val index: Int) : UIPatch()
+
+    @Serializable
+    @SerialName("Move")
+    data class Move(override val path: List<JsonPrimitive>, val from: Int, val to: Int) : UIPatch()
+}
+
+private fun JsonElement.update(
+    path: List<JsonPrimitive>,
+    f: (JsonElement) -> JsonElement
+): JsonElement {
+    if (path.isEmpty()) {
+        return f(this)
+    }
+    val seg = path.first()
+    val rest = path.drop(1)
+    return if (seg.isString) {
+        val map = jsonObject.toMutableMap()
+        map[seg.content] = map.getValue(seg.content).update(rest, f)
+        JsonObject(map)
+    } else {
+        val list = jsonArray.toMutableList()
+        list[seg.int] = list[seg.int].update(rest, f)
+        JsonArray(list)
+    }
+}
+
+fun UIPatch.applyTo(tree: JsonElement): JsonElement = tree.update(path) { target ->
+    when (this) {
+        is UIPatch.Replace -> node
+        is UIPatch.Update -> JsonObject(target.jsonObject + props - removed.toSet())
+        is UIPatch.Insert -> JsonArray(target.jsonArray.toMutableList().apply { add(index, node) })
+        is UIPatch.Remove -> JsonArray(target.jsonArray.toMutableList().apply { removeAt(index) })
+        is UIPatch.Move -> JsonArray(target.jsonArray.toMutableList().apply { add(to, removeAt(from)) })
+    }
+}
+
+
 //interface Callback {
 //    fun onEvent(eventId: Int, data: Any)
 //}
@@ -0,0 +1,96 @@
+package gamebot.host
+
+import RemoteService
+import android.app.KeyguardManager
+import android.content.Context
+import android.net.ConnectivityManager
+import android.os.Binder
+import android.os.PowerManager
+import kotlinx.serialization.SerialName
+import kotlinx.serialization.Serializable
+
+// battery, brightness and volume are read by the guest through shell commands,
+// these need framework calls that don't check the calling package
+
+@Serializable
+enum class NetworkType {
+    None,
+    Wifi,
+    Cellular,
+    Ethernet,
+    Other,
+}
+
+@Serializable
+data class NetworkInfo(
+    val connected: Boolean = false,
+    val type: NetworkType = NetworkType.None,
+    val metered: Boolean = false,
+) {
+    companion object {
+        @Suppress("DEPRECATION")
+        fun from(context: Context): NetworkInfo {
+            val manager =
+                context.getSystemService(Context.CONNECTIVITY_SERVICE) as ConnectivityManager
+            val info = manager.activeNetworkInfo ?: return NetworkInfo()
+            return NetworkInfo(
+                connected = info.isConnected,
+                type = when (info.type) {
+                    ConnectivityManager.TYPE_WIFI -> NetworkType.Wifi
+                    ConnectivityManager.TYPE_MOBILE -> NetworkType.Cellular
+                    ConnectivityManager.TYPE_ETHERNET -> NetworkType.Ethernet
+                    else -> NetworkType.Other
+                },
+                metered = manager.isActiveNetworkMetered,
+            )
+        }
+    }
+}
+
+@Serializable
+data class ScreenInfo(
+    val interactive: Boolean = false,
+    val locked: Boolean = false,
+) {
+    companion object {
+        fun from(context: Context): ScreenInfo {
+            val power = context.getSystemService(Context.POWER_SERVICE) as PowerManager
+            val keyguard = context.getSystemService(Context.KEYGUARD_SERVICE) as KeyguardManager
+            return ScreenInfo(
+                interactive = power.isInteractive,
+                locked = keyguard.isKeyguardLocked,
+            )
+        }
+    }
+}
+
+// sizes in the natural orientation, physical is what the panel has without
+// overrides
+@Serializable
+data class DisplayInfo(
+    val width: Int = 0,
+    val height: Int = 0,
+    val density: Int = 0,
+    @SerialName("physical_width")
+    val physicalWidth: Int = 0,
+    @SerialName("physical_height")
+    val physicalHeight: Int = 0,
+    @SerialName("physical_density")
+    val physicalDensity: Int = 0,
+) {
+    companion object {
+        fun from(remoteService: RemoteService): DisplayInfo {
+            Binder.clearCallingIdentity()
+            val size = remoteService.getOverrideDisplaySize()
+            val physical = remoteService.getPhysicalDisplaySize()
+            return DisplayInfo(
+                width = size.x,
+                height = size.y,
+                density = remoteService.getOverrideDisplayDensity(),
+                physicalWidth = physical.x,
+                physicalHeight = physical.y,
+                physicalDensity = remoteService.getPhysicalDisplayDensity(),
+            )
+        }
+    }
+}
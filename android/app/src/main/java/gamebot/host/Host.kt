@@ -17,11 +17,16 @@ import android.app.ActivityManager.RunningAppProcessInfo.IMPORTANCE_SERVICE
 import android.app.ActivityManager.RunningAppProcessInfo.IMPORTANCE_TOP_SLEEPING
 import android.app.ActivityManager.RunningAppProcessInfo.IMPORTANCE_TOP_SLEEPING_PRE_28
 import android.app.ActivityManager.RunningAppProcessInfo.IMPORTANCE_VISIBLE
+import android.app.ActivityOptions
+import android.app.Notification
 import android.content.pm.PackageManager.GET_ACTIVITIES
+import android.graphics.Point
 import android.os.Binder
+import android.os.Build
 import android.os.ParcelFileDescriptor
 import android.util.Log
 import android.view.KeyEvent
+import java.util.TimeZone
 import kotlinx.coroutines.CoroutineScope
 import kotlinx.coroutines.Dispatchers
 import kotlinx.coroutines.cancel
@@ -42,6 +47,27 @@ class Host(val remoteService: RemoteService, val localService: ILocalService, va
         }
     }
 
+    fun patchConfigUI(patch: ByteArray) {
+        sendLargeData(patch).use { pfd ->
+            localService.patchConfigUI(name, pfd)
+        }
+    }
+
+    fun updateOverlay(frame: ByteArray) {
+        sendLargeData(frame).use { pfd ->
+            localService.updateOverlay(name, pfd)
+        }
+    }
+
+    fun updateSchedule(tasks: ByteArray) {
+        sendLargeData(tasks).use { pfd ->
+            localService.updateSchedule(name, pfd)
+        }
+    }
+
+    // task name picked in the host, empty on timeout or once stopped
+    fun waitScheduleRun(timeout: Long): String = localService.waitScheduleRun(name, timeout)
+
     fun waitConfigUIEvent(): ByteArray {
         return localService.waitConfigUIEvent(name).use { pfd ->
             ParcelFileDescriptor.AutoCloseInputStream(pfd).readBytes()
@@ -73,6 +99,9 @@ class Host(val remoteService: RemoteService, val localService: ILocalService, va
     }
 
 
+    // git clone target of this guest, models and images live under asset/
+    fun guestDir(): String = RemoteService.remoteCache + "/guest/$name"
+
     fun onStart() {
         Log.e("gamebot", "onStart")
         scope = CoroutineScope(Dispatchers.Default )
@@ -82,6 +111,9 @@ class Host(val remoteService: RemoteService, val localService: ILocalService, va
         Log.e("gamebot", "onStop")
         scope.cancel()
         localService.clearConfigUI(name)
+        localService.clearOverlay(name)
+        localService.clearSchedule(name)
+        restoreDisplay()
     }
 
     fun click(x: Float, y: Float) {
@@ -235,6 +267,102 @@ class Host(val remoteService: RemoteService, val localService: ILocalService, va
         }?.toList() ?: emptyList<String>()
         return Json.encodeToString(data)
     }
-}
 
+    fun networkInfo(): String {
+        Binder.clearCallingIdentity()
+        return Json.encodeToString(NetworkInfo.from(remoteService.context))
+    }
+
+    fun screenInfo(): String {
+        Binder.clearCallingIdentity()
+        return Json.encodeToString(ScreenInfo.from(remoteService.context))
+    }
+
+    // what the display was before this guest changed it, put back on stop
+    private var displayBefore: DisplayInfo? = null
+
+    fun displayInfo(): String = Json.encodeToString(DisplayInfo.from(remoteService))
+
+    // the physical size or density clears the override instead of forcing it
+    @Synchronized
+    fun setDisplay(width: Int, height: Int, density: Int) {
+        Binder.clearCallingIdentity()
+        val now = DisplayInfo.from(remoteService)
+        if (displayBefore == null) {
+            displayBefore = now
+        }
+        if (width == now.physicalWidth && height == now.physicalHeight) {
+            remoteService.clearOverrideDisplaySize()
+        } else {
+            remoteService.setOverrideDisplaySize(Point(width, height))
+        }
+        if (density == now.physicalDensity) {
+            remoteService.clearOverrideDensity()
+        } else {
+            remoteService.setOverrideDensitySize(density)
+        }
+        displayBefore?.let {
+            if (it.width == width && it.height == height && it.density == density) {
+                displayBefore = null
+            }
+        }
+    }
+
+    private fun restoreDisplay() {
+        displayBefore?.let {
+            runCatching { setDisplay(it.width, it.height, it.density) }
+                .onFailure { e -> Log.e("gamebot", "restore display", e) }
+        }
+    }
+
+    // seconds, for gamebot::schedule to tell local time
+    fun utcOffset(millis: Long): Int = TimeZone.getDefault().getOffset(millis) / 1000
+
+    // Surface.ROTATION_*
+    fun rotation(): Int = remoteService.getRotation()
 
+    fun notificationList(): String {
+        val data = remoteService.activeNotifications().map { NotificationInfo.from(it) }
+        return Json.encodeToString(data)
+    }
+
+    // empty when done, otherwise the reason
+    fun clickNotification(key: String): String = runCatching {
+        val sbn = remoteService.activeNotifications().find { it.key == key }
+            ?: return "notification $key is gone"
+        val intent = sbn.notification.contentIntent
+            ?: return "notification $key has nothing to open"
+        // android 14 only lets the sender start activities from background
+        // when it says so
+        if (Build.VERSION.SDK_INT >= 34) {
+            val options = ActivityOptions.makeBasic().setPendingIntentBackgroundActivityStartMode(
+                ActivityOptions.MODE_BACKGROUND_ACTIVITY_START_ALLOWED
+            )
+            intent.send(null, 0, null, null, null, null, options.toBundle())
+        } else {
+            intent.send()
+        }
+        if (sbn.notification.flags and Notification.FLAG_AUTO_CANCEL != 0) {
+            runCatching { cancel(sbn.packageName, sbn.tag, sbn.id, sbn.user.identifier) }
+        }
+        ""
+    }.getOrElse { it.toString() }
+
+    // other apps' notifications can only be cancelled by root
+    fun dismissNotification(key: String): String = runCatching {
+        val sbn = remoteService.activeNotifications().find { it.key == key }
+            ?: return ""
+        cancel(sbn.packageName, sbn.tag, sbn.id, sbn.user.identifier)
+        ""
+    }.getOrElse { it.toString() }
+
+    private fun cancel(packageName: String, tag: String?, id: Int, userId: Int) {
+        if (Build.VERSION.SDK_INT < 30) {
+            throw UnsupportedOperationException("dismiss needs android 11")
+        }
+        Binder.clearCallingIdentity()
+        remoteService.notificationManager.cancelNotificationWithTag(
+            packageName, packageName, tag, id, userId
+        )
+    }
+}
@@ -10,6 +10,8 @@ package gamebot.host
 //import com.ketch.Ketch
 import Component
 import UIEvent
+import UIPatch
+import applyTo
 import android.app.ActivityManager
 import android.content.Context
 import android.content.Context.ACTIVITY_SERVICE
@@ -46,14 +48,21 @@ import androidx.savedstate.SavedStateRegistry
 import androidx.savedstate.SavedStateRegistryController
 import androidx.savedstate.SavedStateRegistryOwner
 import androidx.savedstate.setViewTreeSavedStateRegistryOwner
+import gamebot.host.overlay.GuestOverlay
 import kotlinx.coroutines.ExperimentalCoroutinesApi
 import kotlinx.coroutines.channels.BufferOverflow
 import kotlinx.coroutines.channels.Channel
 import kotlinx.coroutines.channels.ClosedReceiveChannelException
 import kotlinx.coroutines.runBlocking
+import kotlinx.coroutines.withTimeoutOrNull
 import kotlinx.serialization.ExperimentalSerializationApi
+import kotlinx.serialization.SerialName
+import kotlinx.serialization.Serializable
 import kotlinx.serialization.builtins.ListSerializer
 import kotlinx.serialization.json.Json
+import kotlinx.serialization.json.JsonElement
+import kotlinx.serialization.json.JsonNull
+import kotlinx.serialization.json.decodeFromJsonElement
 import kotlinx.serialization.json.decodeFromStream
 import kotlinx.serialization.json.encodeToStream
 import java.io.ByteArrayOutputStream
@@ -80,9 +89,36 @@ fun MemoryMonitor() {
 
 data class ConfigUI(
     val layout: MutableState<Component> = mutableStateOf(Component.Empty()),
-    val event: MutableState<Channel<UIEvent>> = mutableStateOf(Channel())
+    val event: MutableState<Channel<UIEvent>> = mutableStateOf(Channel()),
+    // json of layout, what patches from the guest apply to
+    var tree: JsonElement = JsonNull
 )
 
+// a gamebot::schedule task as the guest last published it, times are local
+// and as text, e.g. 2026-10-19 04:00
+@Serializable
+data class ScheduledTask(
+    val name: String,
+    val cron: String,
+    val due: Boolean = false,
+    val next: String? = null,
+    @SerialName("runs_today") val runsToday: Int = 0,
+    @SerialName("daily_limit") val dailyLimit: Int? = null,
+    @SerialName("first_day") val firstDay: String? = null,
+    @SerialName("last_day") val lastDay: String? = null,
+    @SerialName("last_run") val lastRun: String? = null,
+    @SerialName("last_error") val lastError: String? = null,
+)
+
+data class GuestSchedule(
+    val tasks: MutableState<List<ScheduledTask>> = mutableStateOf(emptyList()),
+    // task names picked in the host, taken by waitScheduleRun
+    val run: Channel<String> = Channel(8, BufferOverflow.DROP_LATEST),
+)
+
+// elements carry fields only the guest cares about, such as key
+private val uiJson = Json { ignoreUnknownKeys = true }
+
 
 class LocalService(
     val context: ComponentActivity,
@@ -100,6 +136,7 @@ class LocalService(
 //    var configUIEvent = mutableStateOf(Channel<UIEvent>())
 
     val configUIList = mutableMapOf<String, ConfigUI>()
+    val scheduleList = mutableMapOf<String, GuestSchedule>()
 
     override fun toast(text: String) {
         context.runOnUiThread {
@@ -389,8 +426,10 @@ class LocalService(
 //
 //        throw NotImplementedError()
 
+        val tree: JsonElement
         val component:Component = try {
-             Json.decodeFromStream(stream)
+            tree = uiJson.decodeFromStream(stream)
+            uiJson.decodeFromJsonElement(tree)
         } catch (e: Throwable) {
             // TODO: how to deal with ui fail: just throw
             d("updateConfigUI fail", e)
@@ -402,11 +441,28 @@ class LocalService(
         configUIList.getOrPut(name, {
             ConfigUI()
         }).apply {
+            this.tree = tree
             layout.value = component
             event.value = Channel(8, BufferOverflow.DROP_LATEST)
         }
     }
 
+    // keeps the event channel, events sent meanwhile still reach the guest
+    @OptIn(ExperimentalSerializationApi::class)
+    override fun patchConfigUI(name: String, pfd: ParcelFileDescriptor) {
+        val stream = ParcelFileDescriptor.AutoCloseInputStream(pfd)
+        val ui = configUIList[name] ?: return
+        try {
+            val patch: List<UIPatch> = uiJson.decodeFromStream(stream)
+            val tree = patch.fold(ui.tree) { tree, it -> it.applyTo(tree) }
+            ui.layout.value = uiJson.decodeFromJsonElement(tree)
+            ui.tree = tree
+        } catch (e: Throwable) {
+            d("patchConfigUI fail", e)
+            throw e
+        }
+    }
+
     override fun sendEmptyConfigUIEvent(name: String) {
         configUIList[name]?.apply {
             event.value.trySend(UIEvent.Empty)
@@ -449,6 +505,42 @@ class LocalService(
         configUIList.remove(name)
     }
 
+    @OptIn(ExperimentalSerializationApi::class)
+    override fun updateSchedule(name: String, pfd: ParcelFileDescriptor) {
+        val tasks: List<ScheduledTask> = ParcelFileDescriptor.AutoCloseInputStream(pfd).use {
+            uiJson.decodeFromStream(it)
+        }
+        scheduleList.getOrPut(name) { GuestSchedule() }.tasks.value = tasks
+    }
+
+    // the guest waits here between its own due tasks, so a run from the host
+    // starts right away
+    override fun waitScheduleRun(name: String, timeout: Long): String {
+        val channel = scheduleList[name]?.run ?: return ""
+        return runBlocking {
+            withTimeoutOrNull(timeout) { channel.receiveCatching().getOrNull() }
+        } ?: ""
+    }
+
+    fun runScheduled(name: String, task: String) {
+        scheduleList[name]?.run?.trySend(task)
+    }
+
+    override fun clearSchedule(name: String) {
+        scheduleList.remove(name)?.run?.close()
+    }
+
+    private val overlay = GuestOverlay(context)
+
+    override fun updateOverlay(name: String, pfd: ParcelFileDescriptor) {
+        val data = ParcelFileDescriptor.AutoCloseInputStream(pfd).use { it.readBytes() }
+        overlay.update(name, data)
+    }
+
+    override fun clearOverlay(name: String) {
+        overlay.clear(name)
+    }
+
     val windowManager = context.getSystemService(Context.WINDOW_SERVICE) as WindowManager
 
     fun addFloatingView(gravity: Int = Gravity.BOTTOM or Gravity.START): ComposeView {
@@ -483,6 +483,33 @@ class MainViewModel(
             }
         }
     }
+
+    // tasks of gamebot::schedule, run starts one whether it is due or not
+    @Composable
+    fun GuestSchedule(name: String) {
+        val schedule = localService.scheduleList[name] ?: return
+        for (task in schedule.tasks.value) {
+            Row(
+                Modifier
+                    .fillMaxWidth()
+                    .padding(horizontal = 16.dp),
+                verticalAlignment = Alignment.CenterVertically,
+            ) {
+                Column(Modifier.weight(1f)) {
+                    Text(task.name, style = MaterialTheme.typography.titleSmall)
+                    val limit = task.dailyLimit?.let { " ${task.runsToday}/$it today" } ?: ""
+                    val next = if (task.due) "due" else task.next?.let { "next $it" } ?: "done"
+                    Text("${task.cron}, $next$limit", style = MaterialTheme.typography.bodySmall)
+                    task.lastError?.let {
+                        Text(it, color = MaterialTheme.colorScheme.error, maxLines = 1)
+                    }
+                }
+                TextButton(onClick = { localService.runScheduled(name, task.name) }) {
+                    Text("run")
+                }
+            }
+        }
+    }
 }
 
 @OptIn(ExperimentalMaterial3Api::class)
@@ -705,6 +732,7 @@ fun GuestUI(navController: NavController, viewModel: MainViewModel, name: String
 //                        Text("started")
 //                    }
 
+                    viewModel.GuestSchedule(guest.name)
                     viewModel.GuestUI(guest.name)
                 }
 
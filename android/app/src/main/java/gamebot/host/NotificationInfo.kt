@@ -0,0 +1,39 @@
+package gamebot.host
+
+import android.app.Notification
+import android.service.notification.StatusBarNotification
+import kotlinx.serialization.SerialName
+import kotlinx.serialization.Serializable
+
+@Serializable
+data class NotificationInfo(
+    val key: String = "",
+    @SerialName("package")
+    val packageName: String = "",
+    val id: Int = 0,
+    val tag: String? = null,
+    val title: String = "",
+    val text: String = "",
+    // millis since epoch
+    @SerialName("post_time")
+    val postTime: Long = 0,
+    val ongoing: Boolean = false,
+) {
+    companion object {
+        fun from(sbn: StatusBarNotification): NotificationInfo {
+            val extras = sbn.notification.extras
+            return NotificationInfo(
+                key = sbn.key,
+                packageName = sbn.packageName,
+                id = sbn.id,
+                tag = sbn.tag,
+                title = extras.getCharSequence(Notification.EXTRA_TITLE)?.toString() ?: "",
+                // expanded text when there is one, it's the whole message
+                text = (extras.getCharSequence(Notification.EXTRA_BIG_TEXT)
+                    ?: extras.getCharSequence(Notification.EXTRA_TEXT))?.toString() ?: "",
+                postTime = sbn.postTime,
+                ongoing = sbn.isOngoing,
+            )
+        }
+    }
+}
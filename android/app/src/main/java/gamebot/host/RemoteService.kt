@@ -1,6 +1,7 @@
 //import kotlin.Pair
 //import kotlinx.coroutines.time.sample
 import android.app.ActivityManager
+import android.app.INotificationManager
 import android.app.UiAutomation
 import android.app.UiAutomationConnection
 import android.app.UiAutomationHidden
@@ -23,6 +24,7 @@ import android.os.HandlerThread
 import android.os.IBinder
 import android.os.ServiceManager
 import android.os.SystemClock
+import android.service.notification.StatusBarNotification
 import android.view.IWindowManager
 import android.view.InputDevice
 import android.view.MotionEvent
@@ -122,6 +124,7 @@ class RemoteService(val context: Context) : IRemoteService.Stub() {
     lateinit var packageManager: PackageManager
     lateinit var windowManager: IWindowManager
     lateinit var inputManager: IInputManager
+    lateinit var notificationManager: INotificationManager
 
     lateinit var uiAutomationHidden: UiAutomationHidden
     lateinit var uiAutomationConnection: UiAutomationConnection
@@ -435,6 +438,18 @@ class RemoteService(val context: Context) : IRemoteService.Stub() {
         }
     }
 
+    fun clearOverrideDisplaySize() {
+        windowManager.clearForcedDisplaySize(0)
+    }
+
+    fun clearOverrideDensity() {
+        if (Build.VERSION.SDK_INT >= 25) {
+            windowManager.clearForcedDisplayDensityForUser(0, 0)
+        } else {
+            windowManager.setForcedDisplayDensity(0, getPhysicalDisplayDensity())
+        }
+    }
+
     fun setStandardDisplay() {
         // 720p + 320dpi
         val physical = getPhysicalDisplaySize()
@@ -476,6 +491,17 @@ class RemoteService(val context: Context) : IRemoteService.Stub() {
         getCommandOutput("appops set ${context.packageName} SYSTEM_ALERT_WINDOW allow")
     }
 
+    // the service checks the calling package belongs to our uid
+    private val callingPackage by lazy {
+        packageManager.getPackagesForUid(android.os.Process.myUid())?.firstOrNull()
+            ?: "com.android.shell"
+    }
+
+    fun activeNotifications(): List<StatusBarNotification> {
+        Binder.clearCallingIdentity()
+        return notificationManager.getActiveNotifications(callingPackage).toList()
+    }
+
     fun getRotation(): Int = if (Build.VERSION.SDK_INT < 26) {
         windowManager.rotation
     } else {
@@ -736,6 +762,12 @@ class RemoteService(val context: Context) : IRemoteService.Stub() {
             )
         )
 
+        notificationManager = INotificationManager.Stub.asInterface(
+            ServiceManager.getService(
+                Context.NOTIFICATION_SERVICE
+            )
+        )
+
 
 
         connectUiAutomation()
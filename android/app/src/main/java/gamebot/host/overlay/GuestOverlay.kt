@@ -0,0 +1,246 @@
+package gamebot.host.overlay
+
+import MyLifecycleOwner
+import android.content.Context
+import android.graphics.Paint
+import android.graphics.PixelFormat
+import android.os.Build
+import android.os.Handler
+import android.os.Looper
+import android.os.SystemClock
+import android.view.Gravity
+import android.view.WindowManager
+import androidx.compose.foundation.Canvas
+import androidx.compose.foundation.background
+import androidx.compose.foundation.layout.Box
+import androidx.compose.foundation.layout.fillMaxSize
+import androidx.compose.foundation.layout.padding
+import androidx.compose.material3.Text
+import androidx.compose.runtime.Composable
+import androidx.compose.runtime.LaunchedEffect
+import androidx.compose.runtime.getValue
+import androidx.compose.runtime.mutableLongStateOf
+import androidx.compose.runtime.mutableStateMapOf
+import androidx.compose.runtime.remember
+import androidx.compose.runtime.setValue
+import androidx.compose.ui.Alignment
+import androidx.compose.ui.Modifier
+import androidx.compose.ui.geometry.Offset
+import androidx.compose.ui.geometry.Size
+import androidx.compose.ui.graphics.Color
+import androidx.compose.ui.graphics.drawscope.Stroke
+import androidx.compose.ui.graphics.nativeCanvas
+import androidx.compose.ui.graphics.toArgb
+import androidx.compose.ui.platform.ComposeView
+import androidx.compose.ui.unit.dp
+import androidx.lifecycle.Lifecycle
+import androidx.lifecycle.ViewModelStore
+import androidx.lifecycle.ViewModelStoreOwner
+import androidx.lifecycle.setViewTreeLifecycleOwner
+import androidx.lifecycle.setViewTreeViewModelStoreOwner
+import androidx.savedstate.setViewTreeSavedStateRegistryOwner
+import kotlinx.coroutines.delay
+import kotlinx.serialization.SerialName
+import kotlinx.serialization.Serializable
+import kotlinx.serialization.json.Json
+
+// what gamebot::overlay sends, ttl is millis left or null until cleared
+@Serializable
+sealed class OverlayShape {
+    abstract val color: Long
+    abstract val label: String
+    abstract val ttl: Long?
+
+    @Serializable
+    @SerialName("Rect")
+    data class Rect(
+        val left: Int,
+        val top: Int,
+        val width: Int,
+        val height: Int,
+        override val color: Long,
+        override val label: String,
+        override val ttl: Long? = null,
+    ) : OverlayShape()
+
+    @Serializable
+    @SerialName("Point")
+    data class Point(
+        val x: Int,
+        val y: Int,
+        override val color: Long,
+        override val label: String,
+        override val ttl: Long? = null,
+    ) : OverlayShape()
+}
+
+@Serializable
+data class OverlayFrame(
+    val shapes: List<OverlayShape> = emptyList(),
+    val status: String = "",
+)
+
+// frame with ttl turned into uptime deadlines
+private data class Shown(val frame: OverlayFrame, val received: Long) {
+    fun alive(now: Long) = frame.shapes.filter { it.ttl == null || received + it.ttl!! > now }
+}
+
+private val overlayJson = Json { ignoreUnknownKeys = true }
+
+// one full screen window drawing what each guest asked for, touches pass
+// through to the game below
+class GuestOverlay(private val context: Context) {
+    private val windowManager = context.getSystemService(Context.WINDOW_SERVICE) as WindowManager
+    private val main = Handler(Looper.getMainLooper())
+    private val shown = mutableStateMapOf<String, Shown>()
+    private var view: ComposeView? = null
+
+    fun update(name: String, data: ByteArray) {
+        val frame: OverlayFrame = overlayJson.decodeFromString(data.decodeToString())
+        val received = SystemClock.uptimeMillis()
+        main.post {
+            if (frame.shapes.isEmpty() && frame.status.isEmpty()) {
+                clear(name)
+                return@post
+            }
+            shown[name] = Shown(frame, received)
+            if (view == null) {
+                view = addView()
+            }
+        }
+    }
+
+    fun clear(name: String) {
+        main.post {
+            shown.remove(name)
+            if (shown.isEmpty()) {
+                view?.let { windowManager.removeView(it) }
+                view = null
+            }
+        }
+    }
+
+    private fun addView(): ComposeView {
+        val layoutFlag: Int = if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.O) {
+            WindowManager.LayoutParams.TYPE_APPLICATION_OVERLAY
+        } else {
+            @Suppress("DEPRECATION") WindowManager.LayoutParams.TYPE_PHONE
+        }
+        val params = WindowManager.LayoutParams(
+            WindowManager.LayoutParams.MATCH_PARENT,
+            WindowManager.LayoutParams.MATCH_PARENT,
+            layoutFlag,
+            WindowManager.LayoutParams.FLAG_NOT_TOUCHABLE or
+                    WindowManager.LayoutParams.FLAG_NOT_FOCUSABLE or
+                    WindowManager.LayoutParams.FLAG_LAYOUT_IN_SCREEN or
+                    WindowManager.LayoutParams.FLAG_LAYOUT_NO_LIMITS,
+            PixelFormat.TRANSLUCENT,
+        )
+        params.gravity = Gravity.TOP or Gravity.START
+        // screenshot coordinates start at the physical corner, cutout or not
+        if (Build.VERSION.SDK_INT >= Build.VERSION_CODES.P) {
+            params.layoutInDisplayCutoutMode =
+                WindowManager.LayoutParams.LAYOUT_IN_DISPLAY_CUTOUT_MODE_SHORT_EDGES
+        }
+
+        val composeView = ComposeView(context)
+        composeView.setContent {
+            Overlay(shown.values.toList())
+        }
+
+        val viewModelStore = ViewModelStore()
+        val viewModelStoreOwner = object : ViewModelStoreOwner {
+            override val viewModelStore: ViewModelStore
+                get() = viewModelStore
+        }
+        val lifecycleOwner = MyLifecycleOwner()
+        lifecycleOwner.performRestore(null)
+        lifecycleOwner.handleLifecycleEvent(Lifecycle.Event.ON_CREATE)
+        composeView.setViewTreeLifecycleOwner(lifecycleOwner)
+        composeView.setViewTreeViewModelStoreOwner(viewModelStoreOwner)
+        composeView.setViewTreeSavedStateRegistryOwner(lifecycleOwner)
+        lifecycleOwner.handleLifecycleEvent(Lifecycle.Event.ON_START)
+        lifecycleOwner.handleLifecycleEvent(Lifecycle.Event.ON_RESUME)
+
+        windowManager.addView(composeView, params)
+        return composeView
+    }
+}
+
+@Composable
+private fun Overlay(shown: List<Shown>) {
+    var now by remember { mutableLongStateOf(SystemClock.uptimeMillis()) }
+    // redraw as shapes expire
+    LaunchedEffect(shown) {
+        while (true) {
+            now = SystemClock.uptimeMillis()
+            val next = shown.flatMap { s ->
+                s.alive(now).mapNotNull { it.ttl?.let { ttl -> s.received + ttl } }
+            }.minOrNull() ?: break
+            delay((next - now).coerceAtLeast(16))
+        }
+    }
+    val shapes = shown.flatMap { it.alive(now) }
+    val status = shown.map { it.frame.status }.filter { it.isNotEmpty() }
+
+    Box(Modifier.fillMaxSize()) {
+        Canvas(Modifier.fillMaxSize()) {
+            val stroke = 2.dp.toPx()
+            val paint = Paint().apply {
+                isAntiAlias = true
+                textSize = 12.dp.toPx()
+            }
+            for (shape in shapes) {
+                val color = Color(shape.color.toInt())
+                paint.color = color.toArgb()
+                when (shape) {
+                    // outside the rect, the pixels a find looks at stay as they are
+                    is OverlayShape.Rect -> {
+                        drawRect(
+                            color,
+                            Offset(shape.left - stroke / 2, shape.top - stroke / 2),
+                            Size(shape.width + stroke, shape.height + stroke),
+                            style = Stroke(stroke),
+                        )
+                        // debug outlines come without one, it would cover pixels
+                        if (shape.label.isNotEmpty()) {
+                            drawContext.canvas.nativeCanvas.drawText(
+                                shape.label,
+                                shape.left.toFloat(),
+                                shape.top - stroke - paint.descent(),
+                                paint,
+                            )
+                        }
+                    }
+
+                    is OverlayShape.Point -> {
+                        drawCircle(
+                            color,
+                            6.dp.toPx(),
+                            Offset(shape.x.toFloat(), shape.y.toFloat()),
+                            style = Stroke(stroke),
+                        )
+                        if (shape.label.isNotEmpty()) {
+                            drawContext.canvas.nativeCanvas.drawText(
+                                shape.label,
+                                shape.x + 8.dp.toPx(),
+                                shape.y - 8.dp.toPx(),
+                                paint,
+                            )
+                        }
+                    }
+                }
+            }
+        }
+        if (status.isNotEmpty()) {
+            Text(
+                status.joinToString("\n"),
+                color = Color.White,
+                modifier = Modifier
+                    .align(Alignment.TopCenter)
+                    .background(Color.Black.copy(alpha = 0.6f))
+                    .padding(horizontal = 8.dp, vertical = 4.dp),
+            )
+        }
+    }
+}
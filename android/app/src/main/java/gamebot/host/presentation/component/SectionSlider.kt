@@ -18,12 +18,14 @@ fun SectionSlider(
     info: String = "",
     value: Float = 0f,
     range: ClosedFloatingPointRange<Float>,
+    steps: Int = 0,
+    format: (Float) -> String = { it.roundToInt().toString() },
     onChange: (Float) -> Unit
 ) {
     var cachedValue by remember(value) {
         mutableStateOf(value)
     }
-    val body = cachedValue.roundToInt().toString()
+    val body = format(cachedValue)
     SectionRow {
         Column(Modifier.weight(1f)) {
             ContentTitle(title)
@@ -32,7 +34,7 @@ fun SectionSlider(
                 ContentBody(text = body, modifier = Modifier.weight(.2f))
                 Slider(
                     modifier = Modifier.weight(.8f),
-                    value = cachedValue, valueRange = range,
+                    value = cachedValue, valueRange = range, steps = steps,
                     onValueChange = {
                         cachedValue = it
                     },
@@ -27,10 +27,15 @@ use axum::{routing::get, Router};
 // use candle_onnx::test_candle_onnx;
 use gamebot::{
     api::*,
+    app::{launch_and_wait, AppEvent, Watchdog},
     color::{ColorPoint, ColorPointGroup, ColorPointGroupIn, Region},
-    d,
-    find::GroupFind,
+    d, device, display,
+    find::{Find, GroupFind},
+    journal::{self, Journal},
     node::NodeSelector,
+    notification::{notification_list, wait_notification},
+    schedule::{self, Schedule, Task},
+    shell::{self, Command, Line},
     ui::{button, col, text, text_field, Element, UIContext, UI},
 };
 use ncnn::{Mat, Net};
@@ -286,6 +291,81 @@ fn test_ui() {
     ui.enter_render_loop();
 }
 
+// same config as test_ui, form generated from the struct
+fn test_config_ui() {
+    use gamebot::ui::{config_form, ConfigUI, Persist};
+    use serde::Deserialize;
+
+    #[derive(Clone, Copy, Default, Serialize, Deserialize, ConfigUI)]
+    enum Server {
+        #[default]
+        Official,
+        Bilibili,
+        #[config(label = "V")]
+        VVV,
+    }
+
+    #[derive(Default, Serialize, Deserialize, Clone, ConfigUI)]
+    struct AccountConfig {
+        username: String,
+        password: String,
+        server: Server,
+        #[config(skip)]
+        id: usize,
+    }
+
+    #[derive(Default, Serialize, Deserialize, Clone, ConfigUI)]
+    struct Config {
+        name: String,
+        account: Vec<AccountConfig>,
+        #[config(label = "enable abc")]
+        enable_abc: bool,
+        #[config(range = 1..=99, visible = self.enable_abc)]
+        abc_times: u32,
+    }
+
+    // version 0 called it times
+    let persist = Persist::new("config").version(1).migrate(0, |state| {
+        state["abc_times"] = state["times"].take();
+        Ok(())
+    });
+    let mut ui = UI::new(Config::default(), config_form).persist(persist);
+    ui.enter_render_loop();
+    d!(ui.into_state().account.len());
+}
+
+// open http://<device ip>:8080 from a pc
+fn test_web_ui() {
+    use gamebot::ui::{lazy_list, row};
+
+    #[derive(Default, Serialize)]
+    struct State {
+        count: usize,
+        item: Vec<usize>,
+    }
+
+    fn view(state: &mut State, _: UIContext<State>) -> Element<State> {
+        col([
+            text(format!("count {}", state.count)),
+            row([
+                button("add", |state: &mut State, _| {
+                    state.count += 1;
+                    state.item.insert(0, state.count);
+                }),
+                button("pop", |state: &mut State, _| {
+                    state.item.pop();
+                }),
+            ]),
+            lazy_list(state.item.iter().map(|&i| text(format!("item {i}")).key(i))),
+        ])
+    }
+
+    let mut ui = UI::new(State::default(), view)
+        .serve("0.0.0.0:8080")
+        .unwrap();
+    ui.enter_render_loop();
+}
+
 #[tokio::main]
 async fn test_axum() {
     d!();
@@ -371,16 +451,197 @@ fn test_screenshot_after() {
     // d!(x.elapsed());
 }
 
+fn test_ocr() {
+    use gamebot::{
+        model::{register, warmup, Model},
+        ocr::{ocr, ocr_line, set_ocr, PaddleOcr},
+    };
+
+    // without register, det / rec are looked up in asset dir
+    register(
+        "det",
+        Model::Ncnn {
+            param: "/data/local/tmp/det.ncnn.param".into(),
+            bin: "/data/local/tmp/det.ncnn.bin".into(),
+        },
+    );
+    register(
+        "rec",
+        Model::Ncnn {
+            param: "/data/local/tmp/rec.ncnn.param".into(),
+            bin: "/data/local/tmp/rec.ncnn.bin".into(),
+        },
+    );
+    let start = Instant::now();
+    warmup(["det", "rec"]).unwrap();
+    d!(start.elapsed());
+
+    set_ocr(PaddleOcr::named(Some("det"), "rec", "/data/local/tmp/ppocr_keys_v1.txt").unwrap());
+
+    let start = Instant::now();
+    d!(ocr((0, 0, 720, 1280)).unwrap());
+    d!(ocr_line((0, 0, 720, 100)).unwrap());
+    d!(start.elapsed());
+}
+
+// same PaddleOcr code on both backends
+fn test_inference_backend() {
+    use gamebot::{
+        model::{load, Model},
+        ocr::{Ocr, PaddleOcr},
+    };
+
+    let img = take_screenshot()
+        .crop_rgb(&(0, 0, 720, 1280).into())
+        .unwrap();
+    for (det, rec) in [
+        ("/data/local/tmp/det.onnx", "/data/local/tmp/rec.onnx"),
+        (
+            "/data/local/tmp/det.ncnn.param",
+            "/data/local/tmp/rec.ncnn.param",
+        ),
+    ] {
+        let (det, rec) = (
+            Model::from_path(det).unwrap(),
+            Model::from_path(rec).unwrap(),
+        );
+        d!(det.backend(), load(&det).unwrap().input_names());
+        let engine = PaddleOcr::new(Some(det), rec, "/data/local/tmp/ppocr_keys_v1.txt").unwrap();
+        let start = Instant::now();
+        d!(engine.recognize(&img).unwrap().len(), start.elapsed());
+    }
+}
+
+fn test_classify() {
+    use gamebot::{
+        classify::{classify, set_classifier, Classifier, Dataset},
+        model::{register, Model},
+    };
+
+    register(
+        "squeeze",
+        Model::from_path("/data/local/tmp/squeezenet1.onnx").unwrap(),
+    );
+    set_classifier(
+        Classifier::new("squeeze", 224, 224)
+            .labels_from("/data/local/tmp/synset.txt")
+            .unwrap(),
+    );
+    let start = Instant::now();
+    d!(classify(5).unwrap(), start.elapsed());
+
+    let dataset = Dataset::new("/data/local/tmp/dataset").resize(224, 224);
+    d!(dataset.add("home", &take_screenshot()).unwrap());
+    d!(dataset.write_labels().unwrap());
+}
+
 fn test_group_find() {
     let x = vec![ColorPointGroup::default()];
     x.all_appear(0.5);
 }
 
+fn test_journal() {
+    let dir = journal::start(Journal::new().keep(3)).unwrap();
+    d!(dir);
+    ColorPointGroup::default().appear(0.5);
+    text_on_screen("Settings").find();
+    click(100.0, 100.0);
+    d!(journal::finish());
+}
+
+fn test_app() {
+    let package = "com.android.settings";
+    d!(launch_and_wait(package, 10));
+    let watchdog = Watchdog::new(package).start();
+    click_recent();
+    wait(2);
+    stop_package(package);
+    while let Some(event) = watchdog.next_event(5) {
+        if matches!(d!(event), AppEvent::Exited) {
+            break;
+        }
+    }
+}
+
+fn test_shell() {
+    d!(shell::run(
+        "settings",
+        &["get", "system", "screen_brightness"],
+        5
+    ));
+    d!(shell::run_root("id", &[], 5));
+    let output = Command::new("logcat")
+        .args(&["-d", "-t", "20"])
+        .timeout(5)
+        .run_streaming(|line| {
+            if let Line::Stdout(line) = line {
+                d!(line);
+            }
+        });
+    d!(output.map(|x| x.status));
+}
+
+fn test_device() {
+    d!(device::battery());
+    d!(device::network());
+    d!(device::screen());
+    d!(device::rotation());
+    d!(device::brightness());
+    d!(device::volume());
+    d!(device::wait_until_connected(10));
+}
+
+fn test_display() {
+    d!(display::display_info());
+    display::with_resolution(1280, 720, 320, || {
+        d!(display::display_info());
+        wait(3);
+    });
+    d!(display::display_info());
+}
+
+fn test_schedule() {
+    let mut schedule = Schedule::new()
+        .task(
+            Task::new("daily", "0 4 * * *", || {
+                d!("daily");
+                Ok(())
+            })
+            .unwrap()
+            .daily_limit(1),
+        )
+        .task(
+            Task::new("stamina", "*/2 * * * *", || {
+                d!("stamina");
+                Ok(())
+            })
+            .unwrap(),
+        );
+    let now = schedule::now();
+    d!(now, schedule.due(now), schedule.next_due(now));
+    schedule.run_due();
+    d!(schedule.runs("daily"));
+    schedule.run_forever();
+}
+
+fn test_notification() {
+    for x in notification_list() {
+        d!(x);
+    }
+    let x = wait_notification(|x| x.title.contains("gamebot"), 30);
+    if let Some(x) = d!(x) {
+        d!(x.click());
+    }
+}
+
 gamebot::entry!(start);
 fn start() {
     d!(1);
     d!(2);
     d!(3);
+    // test_ocr();
+    // test_inference_backend();
+    // test_classify();
     // test_ncnn_paddleocr_multiline();
     // test_ort_paddleocr_multiline();
     // test_ort_ddddocr();
@@ -402,10 +663,19 @@ fn start() {
     // click_recent();
     // wait_secs(1);
     // test_ui();
+    // test_config_ui();
+    // test_web_ui();
 
     // test_axum();
     // test_activity();
     // test_current_activity();
     // test_package();
     // test_screenshot_after();
+    // test_journal();
+    // test_notification();
+    // test_app();
+    // test_shell();
+    // test_device();
+    // test_display();
+    // test_schedule();
 }
@@ -1,6 +1,6 @@
 use std::{fs::File, io::BufReader, path::PathBuf, time::Instant};
 
-use gamebot::d;
+use gamebot::{d, model::Preprocess};
 use ncnn::{self, Mat, Net};
 
 pub fn test_ncnn_ddddocr() {
@@ -20,24 +20,13 @@ pub fn test_ncnn_ddddocr() {
         .decode()
         .unwrap();
 
-    let resized = image::imageops::resize(
-        &i0.grayscale().to_luma8(),
-        i0.width() * 64 / i0.height(),
-        64,
-        image::imageops::FilterType::Triangle,
-    );
-    let mut in0 = Mat::from_pixels(
-        resized.into_raw().as_slice(),
-        ncnn::MatPixelType::GRAY,
-        (i0.width() * 64 / i0.height()) as _,
-        64,
-        None,
-    )
-    .unwrap();
-
-    let mean = [0.5 * 255.0];
-    let norm = [1.0 / 0.5 / 255.0];
-    in0.substract_mean_normalize(&mean, &norm);
+    let in0 = Preprocess::new()
+        .resize_height(64)
+        .grayscale()
+        .normalize(&[0.5], &[0.5])
+        .run(&i0.to_rgb8())
+        .mat()
+        .unwrap();
 
     let mut out = Mat::new();
     let mut ex = net.create_extractor();
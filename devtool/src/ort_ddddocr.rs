@@ -1,6 +1,6 @@
 use std::{fs::File, io::BufReader, num::NonZero, path::PathBuf, time::Instant};
 
-use gamebot::d;
+use gamebot::{d, model::Preprocess};
 use ort::{inputs, NNAPIExecutionProvider, Session, SessionOutputs, XNNPACKExecutionProvider};
 
 pub fn test_ort_ddddocr() {
@@ -10,17 +10,12 @@ pub fn test_ort_ddddocr() {
         .decode()
         .unwrap();
 
-    let resized = image::imageops::resize(
-        &i0.grayscale().to_luma8(),
-        i0.width() * 64 / i0.height(),
-        64,
-        image::imageops::FilterType::Triangle,
-    );
-
-    let mut input = ndarray::Array4::from_shape_fn(
-        (1, 1, 64, (i0.width() * 64 / i0.height()) as _),
-        |(_, c, y, x)| (resized.get_pixel(x as u32, y as u32)[c] as f32 / 255.0 - 0.5) / 0.5,
-    );
+    let mut input = Preprocess::new()
+        .resize_height(64)
+        .grayscale()
+        .normalize(&[0.5], &[0.5])
+        .run(&i0.to_rgb8())
+        .tensor;
 
     let model = Session::builder()
         .unwrap()
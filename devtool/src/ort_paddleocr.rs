@@ -1,6 +1,6 @@
 use std::{num::NonZero, path::PathBuf, time::Instant};
 
-use gamebot::d;
+use gamebot::{d, model::Preprocess};
 use image::{GenericImage, Rgb};
 use ort::{inputs, NNAPIExecutionProvider, Session, SessionOutputs, XNNPACKExecutionProvider};
 
@@ -11,17 +11,11 @@ pub fn test_ort_paddleocr() {
         .decode()
         .unwrap();
 
-    let resized = image::imageops::resize(
-        &i0.to_rgb8(),
-        i0.width() * 48 / i0.height(),
-        48,
-        image::imageops::FilterType::Triangle,
-    );
-
-    let mut input = ndarray::Array4::from_shape_fn(
-        (1, 3, 48, (i0.width() * 48 / i0.height()) as _),
-        |(_, c, y, x)| (resized.get_pixel(x as u32, y as u32)[c] as f32 / 255.0 - 0.5) / 0.5,
-    );
+    let mut input = Preprocess::new()
+        .resize_height(48)
+        .normalize(&[0.5], &[0.5])
+        .run(&i0.to_rgb8())
+        .tensor;
 
     let model = Session::builder()
         .unwrap()
@@ -0,0 +1,212 @@
+// #[derive(ConfigUI)], see gamebot::ui::ConfigUI
+//
+// field attributes:
+//   #[config(label = "Name")]         shown instead of the field name
+//   #[config(range = 1..=10)]         numbers are clamped into it
+//   #[config(visible = self.enable)]  any expression over &self
+//   #[config(skip)]                   not shown, same for #[serde(skip)]
+// variants of a unit enum take #[config(label = "..")] only
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, spanned::Spanned, Attribute, Data, DataEnum, DataStruct, DeriveInput, Error,
+    Expr, Fields, LitStr, RangeLimits, Token,
+};
+
+#[proc_macro_derive(ConfigUI, attributes(config))]
+pub fn derive_config_ui(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let body = match &input.data {
+        Data::Struct(data) => expand_struct(data),
+        Data::Enum(data) => expand_enum(&input, data),
+        Data::Union(_) => Err(Error::new(
+            input.ident.span(),
+            "ConfigUI can't be derived for unions",
+        )),
+    };
+    let body = match body {
+        Ok(body) => body,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    quote! {
+        impl #impl_generics ::gamebot::ui::ConfigUI for #name #ty_generics #where_clause {
+            #[allow(unused_mut, unused_variables)]
+            fn config_ui<__State: ::gamebot::serde::Serialize + 'static>(
+                &self,
+                field: &::gamebot::ui::Field,
+                lens: ::gamebot::ui::Lens<__State, Self>,
+            ) -> ::gamebot::ui::Element<__State> {
+                #body
+            }
+        }
+    }
+    .into()
+}
+
+#[derive(Default)]
+struct Attr {
+    label: Option<String>,
+    range: Option<(TokenStream2, TokenStream2)>,
+    visible: Option<Expr>,
+    skip: bool,
+}
+
+fn parse_attr(attrs: &[Attribute]) -> syn::Result<Attr> {
+    let mut ans = Attr::default();
+    for attr in attrs {
+        if attr.path().is_ident("config") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("label") {
+                    ans.label = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("range") {
+                    ans.range = Some(parse_range(meta.value()?.parse()?)?);
+                } else if meta.path.is_ident("visible") {
+                    ans.visible = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("skip") {
+                    ans.skip = true;
+                } else {
+                    return Err(meta.error("expected label, range, visible or skip"));
+                }
+                Ok(())
+            })?;
+        } else if attr.path().is_ident("serde") {
+            // serde skipped fields are runtime state rather than config,
+            // other serde options are not ours to validate
+            let _ = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    ans.skip = true;
+                }
+                if meta.input.peek(Token![=]) {
+                    meta.value()?.parse::<Expr>()?;
+                } else if !meta.input.is_empty() && !meta.input.peek(Token![,]) {
+                    meta.parse_nested_meta(|inner| {
+                        if inner.input.peek(Token![=]) {
+                            inner.value()?.parse::<Expr>()?;
+                        }
+                        Ok(())
+                    })?;
+                }
+                Ok(())
+            });
+        }
+    }
+    Ok(ans)
+}
+
+// min and max as f64 expressions, open ends are infinite
+fn parse_range(expr: Expr) -> syn::Result<(TokenStream2, TokenStream2)> {
+    let Expr::Range(range) = expr else {
+        return Err(Error::new(expr.span(), "expected a range such as 1..=10"));
+    };
+    if matches!(range.limits, RangeLimits::HalfOpen(_)) && range.end.is_some() {
+        return Err(Error::new(
+            range.span(),
+            "use an inclusive range such as 1..=10",
+        ));
+    }
+    let min = match &range.start {
+        Some(x) => quote!((#x) as f64),
+        None => quote!(f64::NEG_INFINITY),
+    };
+    let max = match &range.end {
+        Some(x) => quote!((#x) as f64),
+        None => quote!(f64::INFINITY),
+    };
+    Ok((min, max))
+}
+
+// section with a row per named field
+fn expand_struct(data: &DataStruct) -> syn::Result<TokenStream2> {
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new(
+            data.struct_token.span,
+            "ConfigUI needs a struct with named fields",
+        ));
+    };
+
+    let mut items = vec![];
+    for field in &fields.named {
+        let attr = parse_attr(&field.attrs)?;
+        if attr.skip {
+            continue;
+        }
+        let ident = field.ident.as_ref().unwrap();
+        let label = attr.label.unwrap_or_else(|| ident.to_string());
+        let field = match attr.range {
+            Some((min, max)) => quote!(::gamebot::ui::Field::new(#label).range(#min, #max)),
+            None => quote!(::gamebot::ui::Field::new(#label)),
+        };
+        let item = quote! {
+            content.push(::gamebot::ui::ConfigUI::config_ui(
+                &self.#ident,
+                &#field,
+                lens.field(|x: &mut Self| &mut x.#ident),
+            ));
+        };
+        items.push(match attr.visible {
+            Some(visible) => quote!(if #visible { #item }),
+            None => item,
+        });
+    }
+
+    Ok(quote! {
+        let mut content = ::std::vec::Vec::new();
+        #(#items)*
+        ::gamebot::ui::section(&field.label, content)
+    })
+}
+
+// select over unit variants
+fn expand_enum(input: &DeriveInput, data: &DataEnum) -> syn::Result<TokenStream2> {
+    if data.variants.is_empty() {
+        return Err(Error::new(
+            input.ident.span(),
+            "ConfigUI needs at least one variant",
+        ));
+    }
+
+    let mut variants = vec![];
+    let mut labels = vec![];
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new(
+                variant.span(),
+                "ConfigUI on enums needs unit variants",
+            ));
+        }
+        let attr = parse_attr(&variant.attrs)?;
+        if attr.range.is_some() || attr.visible.is_some() || attr.skip {
+            return Err(Error::new(
+                variant.span(),
+                "only label is supported on variants",
+            ));
+        }
+        labels.push(attr.label.unwrap_or_else(|| variant.ident.to_string()));
+        variants.push(&variant.ident);
+    }
+    let index = 0..variants.len();
+    let index2 = index.clone();
+
+    Ok(quote! {
+        let selected = match self {
+            #(Self::#variants => #index,)*
+        };
+        ::gamebot::ui::select(
+            &field.label,
+            [#(#labels),*],
+            selected,
+            move |state: &mut __State, i: usize, _| {
+                let new = match i {
+                    #(#index2 => Self::#variants,)*
+                    _ => return,
+                };
+                lens.set(state, new);
+            },
+        )
+    })
+}
@@ -4,7 +4,7 @@ use serde::Deserialize;
 
 use crate::api::{activity_list, start_activity};
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub struct ActivityInfo {
     pub package: String,
     pub class: String,
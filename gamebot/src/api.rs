@@ -10,15 +10,21 @@ use std::{
     time::{Duration, Instant},
 };
 
-use status::{assert_running_status, Status, STATUS_TOKEN};
+pub(crate) use status::{assert_running_status, is_stopped_status};
+use status::{Status, STATUS_TOKEN};
 use store::Store;
 
 use crate::{
     activity::{ActivityInfo, AppProcessInfo, PackageInfo},
+    classify::SceneIs,
     color::{ColorPointGroup, DiskImageIn, ImageIn, Region},
     d,
+    detect::DetectIn,
+    journal,
     node::{ANode, Nodeshot},
+    ocr::{TextIn, TextPattern},
     screenshot::Screenshot,
+    shell,
 };
 
 pub(crate) fn proxy() -> proxy::Proxy {
@@ -29,6 +35,20 @@ pub fn toast(msg: &str) {
     proxy().toast(msg);
 }
 
+// where the guest repo is cloned to
+pub fn guest_dir() -> PathBuf {
+    proxy().guest_dir().into()
+}
+
+pub fn asset_dir() -> PathBuf {
+    guest_dir().join("asset")
+}
+
+// state the guest writes itself, kept apart from the tracked files
+pub fn data_dir() -> PathBuf {
+    guest_dir().join("data")
+}
+
 pub fn take_screenshot() -> Screenshot {
     proxy().take_screenshot()
 }
@@ -50,8 +70,21 @@ pub fn take_nodeshot_after(timestamp: i64, timeout: Duration) -> Nodeshot {
 }
 
 pub fn click(x: f32, y: f32) {
-    touch_down(x, y, 0);
-    touch_up(x, y, 0);
+    let around = Region {
+        left: (x as u32).saturating_sub(32),
+        top: (y as u32).saturating_sub(32),
+        width: 64,
+        height: 64,
+    };
+    journal::step(
+        "click",
+        || format!("({x}, {y})"),
+        journal::Evidence::Before(around),
+        || {
+            touch_down(x, y, 0);
+            touch_up(x, y, 0);
+        },
+    )
 }
 pub fn touch_down(x: f32, y: f32, id: i32) {
     proxy().touch_down(x, y, id);
@@ -80,6 +113,12 @@ pub fn wait(s: impl Seconds) {
     assert_running_status();
 }
 
+// for threads of our own, false instead of unwinding once the guest stopped
+pub(crate) fn wait_running(s: impl Seconds) -> bool {
+    let _ = STATUS_TOKEN.wait_for(Status::Running as u32, s.into_duration());
+    status::is_running_status()
+}
+
 pub fn wait_millis(s: u64) {
     wait(Duration::from_millis(s));
 }
@@ -392,14 +431,22 @@ pub fn start_package(package: &str) {
     start_activity(package, &class);
 }
 pub fn start_activity(package: &str, class: &str) {
-    let _ = std::process::Command::new("am")
-        .args(["start", &format!("{package}/{class}")])
-        .spawn();
+    am(&["start", "-n", &format!("{package}/{class}")]);
 }
 pub fn stop_package(package: &str) {
-    let _ = std::process::Command::new("am")
-        .args(["force-stop", package])
-        .spawn();
+    am(&["force-stop", package]);
+}
+
+// am exits with 0 on most failures, they show up as "Error" in its output
+fn am(args: &[&str]) {
+    match shell::run("am", args, Duration::from_secs(10)) {
+        Ok(output)
+            if output.success()
+                && !output.stdout.contains("Error")
+                && output.stderr.trim().is_empty() => {}
+        Ok(output) => log::warn!("am {}: {output:?}", args.join(" ")),
+        Err(e) => log::warn!("am {e:#}"),
+    }
 }
 
 pub fn screen_width() -> usize {
@@ -426,6 +473,29 @@ pub fn img(path: impl ToString) -> ImageIn {
     .into();
 }
 
+pub fn text_on_screen(pattern: impl Into<TextPattern>) -> TextIn {
+    TextIn {
+        pattern: pattern.into(),
+        region: fullscreen_region(),
+        min_confidence: 0.0,
+    }
+}
+
+pub fn object_on_screen(class: &str) -> DetectIn {
+    DetectIn {
+        class: class.to_owned(),
+        min_score: 0.0,
+        region: fullscreen_region(),
+    }
+}
+
+pub fn scene_is(label: &str) -> SceneIs {
+    SceneIs {
+        label: label.to_owned(),
+        min_score: 0.0,
+    }
+}
+
 pub fn cpg(color: &str) -> ColorPointGroup {
     ColorPointGroup::try_from(color).unwrap()
 }
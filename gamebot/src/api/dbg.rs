@@ -4,16 +4,21 @@ macro_rules! d {
     // of `eprintln!` because `file!` could contain a `{` or
     // `$val` expression could be a block (`{ .. }`), in which case the `eprintln!`
     // will be malformed.
-    () => {
-        $crate::log::error!("[{}:{}:{}]", file!(), line!(), column!())
-    };
+    // formatted once for both the log and the journal
+    () => {{
+        let text = format!("[{}:{}:{}]", file!(), line!(), column!());
+        $crate::log::error!("{}", text);
+        $crate::journal::note(&text);
+    }};
     ($val:expr $(,)?) => {
         // Use of `match` here is intentional because it affects the lifetimes
         // of temporaries - https://stackoverflow.com/a/48732525/1063961
         match $val {
             tmp => {
-                $crate::log::error!("[{}:{}:{}] {} = {:#?}",
+                let text = format!("[{}:{}:{}] {} = {:#?}",
                     file!(), line!(), column!(), stringify!($val), &tmp);
+                $crate::log::error!("{}", text);
+                $crate::journal::note(&text);
                 tmp
             }
         }
@@ -55,6 +55,12 @@ extern "C" fn start(env: &mut JNIEnv, host: JObject) -> *mut c_char {
         });
         if let Err(err) = r {
             ret = format!("{:?}", err);
+            let msg = err
+                .downcast_ref::<&str>()
+                .map(|x| x.to_string())
+                .or_else(|| err.downcast_ref::<String>().cloned())
+                .unwrap_or(ret.clone());
+            crate::journal::note(&format!("panicked: {msg}"));
         }
     }
 
@@ -69,6 +75,12 @@ extern "C" fn stop(env: &mut JNIEnv, host: JObject) {
     set_stopped_status();
     STATUS_TOKEN.wake(i32::MAX);
 
+    // guest library stays loaded after stop, don't keep models alive with it
+    crate::model::unload_all();
+
+    // report of a journal left open, also after a panic
+    crate::journal::finish();
+
     // stop callback / channel
     env.call_method(&host, "onStop", "()V", &[]).unwrap();
 }
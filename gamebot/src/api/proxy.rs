@@ -10,9 +10,12 @@ use serde::Serialize;
 use crate::{
     activity::{ActivityInfo, AppProcessInfo, PackageInfo},
     d,
+    device::{Network, Screen},
+    display::DisplayInfo,
     node::{ANode, Node, Nodeshot},
+    notification::Notification,
     screenshot::Screenshot,
-    ui::{Element, UIEvent},
+    ui::{Patch, UIEvent},
 };
 
 pub(crate) struct Proxy {
@@ -77,8 +80,8 @@ impl Proxy {
             .unwrap()
     }
 
-    pub(crate) fn set_config_ui<State: Serialize>(&mut self, ui: Element<State>) {
-        let byte = serde_json::to_vec(&ui).unwrap();
+    pub(crate) fn set_config_ui(&mut self, ui: &impl Serialize) {
+        let byte = serde_json::to_vec(ui).unwrap();
         let value = self.env.byte_array_from_slice(&byte).unwrap();
         self.env
             .call_method(&self.host, "updateConfigUI", "([B)V", &[(&value).into()])
@@ -86,6 +89,24 @@ impl Proxy {
         self.env.delete_local_ref(value);
     }
 
+    pub(crate) fn patch_config_ui(&mut self, patch: &[Patch]) {
+        let byte = serde_json::to_vec(patch).unwrap();
+        let value = self.env.byte_array_from_slice(&byte).unwrap();
+        self.env
+            .call_method(&self.host, "patchConfigUI", "([B)V", &[(&value).into()])
+            .unwrap();
+        self.env.delete_local_ref(value);
+    }
+
+    pub(crate) fn update_overlay(&mut self, frame: &impl Serialize) {
+        let byte = serde_json::to_vec(frame).unwrap();
+        let value = self.env.byte_array_from_slice(&byte).unwrap();
+        self.env
+            .call_method(&self.host, "updateOverlay", "([B)V", &[(&value).into()])
+            .unwrap();
+        self.env.delete_local_ref(value);
+    }
+
     pub(crate) fn wait_config_ui_event<State>(&mut self) -> Vec<UIEvent<State>> {
         let event = self
             .env
@@ -330,6 +351,152 @@ impl Proxy {
         x
     }
 
+    pub(crate) fn network_info(&mut self) -> Network {
+        let obj: JString = self
+            .env
+            .call_method(self.host, "networkInfo", "()Ljava/lang/String;", &[])
+            .unwrap()
+            .l()
+            .unwrap()
+            .into();
+        let x: String = JavaStr::from_env(&self.env, &obj).unwrap().into();
+        self.env.delete_local_ref(obj);
+        serde_json::from_str(&x).unwrap()
+    }
+
+    pub(crate) fn screen_info(&mut self) -> Screen {
+        let obj: JString = self
+            .env
+            .call_method(self.host, "screenInfo", "()Ljava/lang/String;", &[])
+            .unwrap()
+            .l()
+            .unwrap()
+            .into();
+        let x: String = JavaStr::from_env(&self.env, &obj).unwrap().into();
+        self.env.delete_local_ref(obj);
+        serde_json::from_str(&x).unwrap()
+    }
+
+    pub(crate) fn display_info(&mut self) -> DisplayInfo {
+        let obj: JString = self
+            .env
+            .call_method(self.host, "displayInfo", "()Ljava/lang/String;", &[])
+            .unwrap()
+            .l()
+            .unwrap()
+            .into();
+        let x: String = JavaStr::from_env(&self.env, &obj).unwrap().into();
+        self.env.delete_local_ref(obj);
+        serde_json::from_str(&x).unwrap()
+    }
+
+    pub(crate) fn set_display(&mut self, width: u32, height: u32, density: u32) {
+        self.env
+            .call_method(
+                self.host,
+                "setDisplay",
+                "(III)V",
+                &[
+                    (width as i32).into(),
+                    (height as i32).into(),
+                    (density as i32).into(),
+                ],
+            )
+            .unwrap();
+    }
+
+    // seconds local time is ahead of utc at that time
+    pub(crate) fn utc_offset(&mut self, unix_millis: i64) -> i32 {
+        self.env
+            .call_method(self.host, "utcOffset", "(J)I", &[unix_millis.into()])
+            .unwrap()
+            .i()
+            .unwrap()
+    }
+
+    pub(crate) fn update_schedule(&mut self, tasks: &impl Serialize) {
+        let byte = serde_json::to_vec(tasks).unwrap();
+        let value = self.env.byte_array_from_slice(&byte).unwrap();
+        self.env
+            .call_method(&self.host, "updateSchedule", "([B)V", &[(&value).into()])
+            .unwrap();
+        self.env.delete_local_ref(value);
+    }
+
+    // task the host asked to run, empty when none came in time
+    pub(crate) fn wait_schedule_run(&mut self, timeout_millis: i64) -> String {
+        let obj: JString = self
+            .env
+            .call_method(
+                self.host,
+                "waitScheduleRun",
+                "(J)Ljava/lang/String;",
+                &[timeout_millis.into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap()
+            .into();
+        let x: String = JavaStr::from_env(&self.env, &obj).unwrap().into();
+        self.env.delete_local_ref(obj);
+        x
+    }
+
+    pub(crate) fn rotation(&mut self) -> i32 {
+        self.env
+            .call_method(self.host, "rotation", "()I", &[])
+            .unwrap()
+            .i()
+            .unwrap()
+    }
+
+    pub(crate) fn notification_list(&mut self) -> Vec<Notification> {
+        let obj: JString = self
+            .env
+            .call_method(self.host, "notificationList", "()Ljava/lang/String;", &[])
+            .unwrap()
+            .l()
+            .unwrap()
+            .into();
+        let x: String = JavaStr::from_env(&self.env, &obj).unwrap().into();
+        self.env.delete_local_ref(obj);
+        serde_json::from_str(&x).unwrap()
+    }
+
+    // method is clickNotification or dismissNotification, empty on success
+    pub(crate) fn notification_action(&mut self, method: &str, key: &str) -> String {
+        let key: JObject = self.env.new_string(key).unwrap().into();
+        let obj: JString = self
+            .env
+            .call_method(
+                self.host,
+                method,
+                "(Ljava/lang/String;)Ljava/lang/String;",
+                &[(&key).into()],
+            )
+            .unwrap()
+            .l()
+            .unwrap()
+            .into();
+        let x: String = JavaStr::from_env(&self.env, &obj).unwrap().into();
+        self.env.delete_local_ref(obj);
+        self.env.delete_local_ref(key);
+        x
+    }
+
+    pub(crate) fn guest_dir(&mut self) -> String {
+        let obj: JString = self
+            .env
+            .call_method(self.host, "guestDir", "()Ljava/lang/String;", &[])
+            .unwrap()
+            .l()
+            .unwrap()
+            .into();
+        let x: String = JavaStr::from_env(&self.env, &obj).unwrap().into();
+        self.env.delete_local_ref(obj);
+        x
+    }
+
     pub(crate) fn wait_nodeshot_after(&mut self, timestamp: i64, timeout: Duration) {
         let timeout = timeout.as_millis().min(i64::MAX as _) as i64;
 
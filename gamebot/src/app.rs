@@ -0,0 +1,318 @@
+// launching the game and keeping an eye on it while the script runs
+//
+// the watchdog polls on its own thread and only reports, what to do about a
+// crash or the game going to background is up to the script
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use anyhow::bail;
+
+use crate::{
+    activity::ActivityInfo,
+    api::{
+        current_activity, running_app_process_list, start_package, take_nodeshot, wait,
+        wait_running, Seconds,
+    },
+    node::Nodeshot,
+};
+
+const POLL: Duration = Duration::from_millis(500);
+
+// system dialog ids, the same on aosp and the skins we've seen
+const DIALOG_TITLE: &str = "android:id/alertTitle";
+const DIALOG_CLOSE: &str = "android:id/aerr_close";
+const DIALOG_WAIT: &str = "android:id/aerr_wait";
+
+// starts the package and waits until one of its activities is on top
+pub fn launch_and_wait(package: &str, timeout: impl Seconds) -> anyhow::Result<()> {
+    let start = Instant::now();
+    let timeout = timeout.into_duration();
+    start_package(package);
+    loop {
+        if is_foreground(package) {
+            return Ok(());
+        }
+        let left = timeout.saturating_sub(start.elapsed());
+        if left.is_zero() {
+            bail!(
+                "{package} not in foreground after {timeout:?}, top is {:?}",
+                current_activity()
+            );
+        }
+        wait(left.min(POLL));
+    }
+}
+
+// any of its processes, ":remote" and the like count as well
+pub fn is_running(package: &str) -> bool {
+    running_app_process_list()
+        .iter()
+        .any(|x| owned_by(&x.process, package))
+}
+
+pub fn is_foreground(package: &str) -> bool {
+    current_activity().package == package
+}
+
+fn owned_by(process: &str, package: &str) -> bool {
+    process
+        .strip_prefix(package)
+        .is_some_and(|x| x.is_empty() || x.starts_with(':'))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppEvent {
+    // the process is gone, a crash without dialog or a kill, stop_package too
+    Exited,
+    // running again after Exited
+    Started,
+    // "keeps stopping", message is the dialog title
+    Crashed { message: String },
+    // "isn't responding"
+    NotResponding { message: String },
+    LeftForeground { activity: ActivityInfo },
+    Returned,
+}
+
+pub struct Watchdog {
+    package: String,
+    interval: Duration,
+}
+
+impl Watchdog {
+    pub fn new(package: &str) -> Self {
+        Watchdog {
+            package: package.into(),
+            interval: Duration::from_secs(1),
+        }
+    }
+
+    pub fn interval(mut self, interval: impl Seconds) -> Self {
+        self.interval = interval.into_duration();
+        self
+    }
+
+    // polls until the returned handle is dropped or the guest stops
+    pub fn start(self) -> Watching {
+        let (sender, events) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let handle = std::thread::spawn(move || {
+            let mut tracker = Tracker::new(&self.package);
+            loop {
+                for event in tracker.update(Sample::take(&self.package)) {
+                    log::info!("watchdog {}: {event:?}", self.package);
+                    if sender.send(event).is_err() {
+                        return;
+                    }
+                }
+                if !wait_running(self.interval) || stopped.load(Ordering::Relaxed) {
+                    return;
+                }
+            }
+        });
+        Watching {
+            events,
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+pub struct Watching {
+    events: Receiver<AppEvent>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Watching {
+    // what happened since the last call, doesn't block
+    pub fn events(&self) -> Vec<AppEvent> {
+        self.events.try_iter().collect()
+    }
+
+    pub fn next_event(&self, timeout: impl Seconds) -> Option<AppEvent> {
+        self.events.recv_timeout(timeout.into_duration()).ok()
+    }
+}
+
+impl Drop for Watching {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Dialog {
+    Crash(String),
+    NotResponding(String),
+}
+
+impl Dialog {
+    // the dialog belongs to "android" and only names the app in its title,
+    // so whatever is shown is taken to be about the watched app
+    fn find(shot: &Nodeshot) -> Option<Self> {
+        let has = |id: &str| shot.data.iter().find(|x| x.id == id);
+        let title = || {
+            has(DIALOG_TITLE)
+                .map(|x| x.text.clone())
+                .unwrap_or_default()
+        };
+        if has(DIALOG_WAIT).is_some() {
+            Some(Dialog::NotResponding(title()))
+        } else if has(DIALOG_CLOSE).is_some() {
+            Some(Dialog::Crash(title()))
+        } else {
+            None
+        }
+    }
+}
+
+struct Sample {
+    running: bool,
+    activity: ActivityInfo,
+    dialog: Option<Dialog>,
+}
+
+impl Sample {
+    fn take(package: &str) -> Self {
+        Sample {
+            running: is_running(package),
+            activity: current_activity(),
+            dialog: Dialog::find(&take_nodeshot()),
+        }
+    }
+}
+
+// turns samples into events on changes, a dialog is reported once however
+// long it stays
+struct Tracker {
+    package: String,
+    last: Option<Sample>,
+}
+
+impl Tracker {
+    fn new(package: &str) -> Self {
+        Tracker {
+            package: package.into(),
+            last: None,
+        }
+    }
+
+    fn update(&mut self, now: Sample) -> Vec<AppEvent> {
+        let mut events = vec![];
+        let last_dialog = self.last.as_ref().and_then(|x| x.dialog.as_ref());
+        if now.dialog.as_ref() != last_dialog {
+            match &now.dialog {
+                Some(Dialog::Crash(message)) => events.push(AppEvent::Crashed {
+                    message: message.clone(),
+                }),
+                Some(Dialog::NotResponding(message)) => events.push(AppEvent::NotResponding {
+                    message: message.clone(),
+                }),
+                None => {}
+            }
+        }
+        if let Some(last) = &self.last {
+            if last.running && !now.running {
+                events.push(AppEvent::Exited);
+            } else if !last.running && now.running {
+                events.push(AppEvent::Started);
+            }
+            let was = last.activity.package == self.package;
+            let is = now.activity.package == self.package;
+            if was && !is {
+                events.push(AppEvent::LeftForeground {
+                    activity: now.activity.clone(),
+                });
+            } else if !was && is {
+                events.push(AppEvent::Returned);
+            }
+        }
+        self.last = Some(now);
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(running: bool, package: &str, dialog: Option<Dialog>) -> Sample {
+        Sample {
+            running,
+            activity: ActivityInfo {
+                package: package.into(),
+                class: String::new(),
+            },
+            dialog,
+        }
+    }
+
+    #[test]
+    fn process_of_package() {
+        assert!(owned_by("com.game", "com.game"));
+        assert!(owned_by("com.game:push", "com.game"));
+        assert!(!owned_by("com.game2", "com.game"));
+        assert!(!owned_by("com", "com.game"));
+    }
+
+    #[test]
+    fn crash_then_restart() {
+        let mut tracker = Tracker::new("com.game");
+        assert_eq!(tracker.update(sample(true, "com.game", None)), vec![]);
+        assert_eq!(tracker.update(sample(true, "com.game", None)), vec![]);
+
+        let dialog = Some(Dialog::Crash("Game keeps stopping".into()));
+        assert_eq!(
+            tracker.update(sample(false, "com.launcher", dialog.clone())),
+            vec![
+                AppEvent::Crashed {
+                    message: "Game keeps stopping".into()
+                },
+                AppEvent::Exited,
+                AppEvent::LeftForeground {
+                    activity: ActivityInfo {
+                        package: "com.launcher".into(),
+                        class: String::new(),
+                    }
+                },
+            ]
+        );
+        // still shown, not again
+        assert_eq!(
+            tracker.update(sample(false, "com.launcher", dialog)),
+            vec![]
+        );
+        assert_eq!(
+            tracker.update(sample(true, "com.game", None)),
+            vec![AppEvent::Started, AppEvent::Returned]
+        );
+    }
+
+    #[test]
+    fn dialog_already_shown() {
+        let mut tracker = Tracker::new("com.game");
+        assert_eq!(
+            tracker.update(sample(
+                true,
+                "com.game",
+                Some(Dialog::NotResponding("Game isn't responding".into()))
+            )),
+            vec![AppEvent::NotResponding {
+                message: "Game isn't responding".into()
+            }]
+        );
+    }
+}
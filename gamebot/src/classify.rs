@@ -0,0 +1,455 @@
+// whole screen scene recognition with a small classification network
+// (mobilenet, squeezenet), for telling apart states templates can't
+
+#[cfg(feature = "training")]
+pub mod train;
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context};
+use image::{imageops::FilterType, Rgb, RgbImage};
+
+use crate::{
+    api::take_screenshot,
+    color::{ImageIn, Region},
+    find::miss_on_error,
+    model::{ModelRef, PixelSource, Preprocess, Source},
+    screenshot::Screenshot,
+};
+
+const IMAGENET_MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+const IMAGENET_STD: [f32; 3] = [0.229, 0.224, 0.225];
+
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub name: String,
+    pub id: usize,
+    pub score: f32,
+}
+
+pub struct Classifier {
+    model: ModelRef,
+    // index is class id, empty means ids are used as names
+    pub labels: Vec<String>,
+    pub preprocess: Preprocess,
+    // off for models exported with softmax at the end
+    pub softmax: bool,
+}
+
+impl Classifier {
+    // width x height is the model input size, screenshot is squashed into it
+    pub fn new(model: impl Into<ModelRef>, width: u32, height: u32) -> Self {
+        Classifier {
+            model: model.into(),
+            labels: vec![],
+            preprocess: Preprocess::new()
+                .resize(width, height)
+                .normalize(&IMAGENET_MEAN, &IMAGENET_STD),
+            softmax: true,
+        }
+    }
+
+    pub fn labels(mut self, labels: impl IntoIterator<Item = impl ToString>) -> Self {
+        self.labels = labels.into_iter().map(|x| x.to_string()).collect();
+        self
+    }
+
+    // one label per line, as written by Dataset::write_labels
+    pub fn labels_from(self, source: impl Into<Source>) -> anyhow::Result<Self> {
+        let data = source.into().read()?;
+        let text = std::str::from_utf8(&data)?;
+        let names: Vec<_> = text
+            .lines()
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .collect();
+        Ok(self.labels(names))
+    }
+
+    // k most probable labels, best first
+    pub fn classify(
+        &self,
+        src: &impl PixelSource,
+        region: &Region,
+        k: usize,
+    ) -> anyhow::Result<Vec<Label>> {
+        let prepared = self.preprocess.run_in(src, region);
+        let out = self
+            .model
+            .get()?
+            .run_one(prepared.tensor.into_dyn().view())?;
+
+        // [1, n] or [1, n, 1, 1] from a conv head
+        let mut scores: Vec<f32> = out.iter().copied().collect();
+        if !self.labels.is_empty() && scores.len() != self.labels.len() {
+            bail!(
+                "model has {} classes but {} labels are given",
+                scores.len(),
+                self.labels.len()
+            );
+        }
+        if self.softmax {
+            softmax(&mut scores);
+        }
+
+        let mut ans: Vec<_> = scores
+            .into_iter()
+            .enumerate()
+            .map(|(id, score)| Label {
+                name: self.label_name(id),
+                id,
+                score,
+            })
+            .collect();
+        ans.sort_by(|a, b| b.score.total_cmp(&a.score));
+        ans.truncate(k);
+        Ok(ans)
+    }
+
+    fn label_name(&self, id: usize) -> String {
+        self.labels
+            .get(id)
+            .cloned()
+            .unwrap_or_else(|| id.to_string())
+    }
+}
+
+fn softmax(x: &mut [f32]) {
+    let max = x.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let mut sum = 0.0;
+    for v in x.iter_mut() {
+        *v = (*v - max).exp();
+        sum += *v;
+    }
+    for v in x.iter_mut() {
+        *v /= sum;
+    }
+}
+
+static CLASSIFIER: RwLock<Option<Arc<Classifier>>> = RwLock::new(None);
+
+pub fn set_classifier(classifier: Classifier) {
+    *CLASSIFIER.write().unwrap() = Some(Arc::new(classifier));
+}
+
+fn classifier() -> anyhow::Result<Arc<Classifier>> {
+    CLASSIFIER
+        .read()
+        .unwrap()
+        .clone()
+        .context("classifier is not set, call classify::set_classifier first")
+}
+
+pub fn classify(k: usize) -> anyhow::Result<Vec<Label>> {
+    take_screenshot().classify(k)
+}
+
+// most probable label of current screen
+pub fn scene() -> anyhow::Result<Label> {
+    take_screenshot().scene()
+}
+
+impl Screenshot {
+    pub fn classify(&self, k: usize) -> anyhow::Result<Vec<Label>> {
+        classifier()?.classify(self, &self.region(), k)
+    }
+
+    pub fn scene(&self) -> anyhow::Result<Label> {
+        self.classify(1)?
+            .pop()
+            .context("classifier model has no output")
+    }
+
+    pub fn find_scene_is(&self, target: &SceneIs) -> Option<Label> {
+        miss_on_error("classify", self.scene())
+            .filter(|x| x.name == target.label && x.score >= target.min_score)
+    }
+}
+
+// screen is recognised as label, usable wherever a Find target is
+#[derive(Debug, Clone)]
+pub struct SceneIs {
+    pub label: String,
+    pub min_score: f32,
+}
+
+impl SceneIs {
+    pub fn min_score(&self, min_score: f32) -> SceneIs {
+        SceneIs {
+            min_score,
+            ..self.clone()
+        }
+    }
+}
+
+// screenshots sorted into <root>/<label>/*.png, the layout torchvision
+// ImageFolder reads, ids are directory names in sorted order as in its class_to_idx
+pub struct Dataset {
+    root: PathBuf,
+    size: Option<(u32, u32)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub path: PathBuf,
+    pub label: usize,
+}
+
+impl Dataset {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Dataset {
+            root: root.into(),
+            size: None,
+        }
+    }
+
+    // store downscaled, a tiny model doesn't need full resolution screenshots
+    pub fn resize(mut self, width: u32, height: u32) -> Self {
+        self.size = Some((width, height));
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn add(&self, label: &str, src: &impl PixelSource) -> anyhow::Result<PathBuf> {
+        let (w, h) = src.dimensions();
+        self.add_in(label, src, &(0, 0, w, h).into())
+    }
+
+    // crop of src, e.g. the part of screen a user labelled by hand
+    pub fn add_in(
+        &self,
+        label: &str,
+        src: &impl PixelSource,
+        region: &Region,
+    ) -> anyhow::Result<PathBuf> {
+        if label.is_empty() || label.contains(['/', '\\']) || label.starts_with('.') {
+            bail!("invalid label {label:?}");
+        }
+        let (w, h) = src.dimensions();
+        if region.width == 0 || region.height == 0 || !Region::from((0, 0, w, h)).contains(region) {
+            bail!("region {region:?} is out of {w}x{h}");
+        }
+        let dir = self.root.join(label);
+        std::fs::create_dir_all(&dir)?;
+
+        let mut img = RgbImage::from_fn(region.width, region.height, |x, y| {
+            Rgb(src.rgb(region.left + x, region.top + y))
+        });
+        if let Some((w, h)) = self.size {
+            img = image::imageops::resize(&img, w, h, FilterType::Triangle);
+        }
+
+        let mut stamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let path = loop {
+            let path = dir.join(format!("{stamp}.png"));
+            if !path.exists() {
+                break path;
+            }
+            stamp += 1;
+        };
+        img.save(&path)?;
+        Ok(path)
+    }
+
+    // every place the template matches, cropped to template size
+    pub fn add_matches(
+        &self,
+        label: &str,
+        shot: &Screenshot,
+        target: &ImageIn,
+        max_num: usize,
+    ) -> anyhow::Result<usize> {
+        let (w, h) = target.img.dimensions();
+        let found = shot.find_all_image_in(target, max_num);
+        for p in &found {
+            self.add_in(label, shot, &(p.x as u32, p.y as u32, w, h).into())?;
+        }
+        Ok(found.len())
+    }
+
+    // label images saved during runs, label_of returning None skips the file
+    pub fn import(
+        &self,
+        dir: impl AsRef<Path>,
+        mut label_of: impl FnMut(&Path, &RgbImage) -> Option<String>,
+    ) -> anyhow::Result<usize> {
+        let mut paths = image_files(dir.as_ref())?;
+        paths.sort();
+        let mut n = 0;
+        for path in paths {
+            let img = image::open(&path)?.to_rgb8();
+            if let Some(label) = label_of(&path, &img) {
+                self.add(&label, &img)?;
+                n += 1;
+            }
+        }
+        Ok(n)
+    }
+
+    pub fn labels(&self) -> anyhow::Result<Vec<String>> {
+        let mut ans = vec![];
+        for entry in std::fs::read_dir(&self.root)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                ans.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        ans.sort();
+        Ok(ans)
+    }
+
+    pub fn samples(&self) -> anyhow::Result<Vec<Sample>> {
+        let mut ans = vec![];
+        for (label, name) in self.labels()?.iter().enumerate() {
+            let mut paths = image_files(&self.root.join(name))?;
+            paths.sort();
+            ans.extend(paths.into_iter().map(|path| Sample { path, label }));
+        }
+        Ok(ans)
+    }
+
+    // <root>/labels.txt, for Classifier::labels_from
+    pub fn write_labels(&self) -> anyhow::Result<PathBuf> {
+        let path = self.root.join("labels.txt");
+        std::fs::write(&path, self.labels()?.join("\n") + "\n")?;
+        Ok(path)
+    }
+}
+
+fn image_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut ans = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let ext = path.extension().and_then(|x| x.to_str());
+        if path.is_file()
+            && ext
+                .is_some_and(|x| ["png", "jpg", "jpeg"].contains(&x.to_ascii_lowercase().as_str()))
+        {
+            ans.push(path);
+        }
+    }
+    Ok(ans)
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{ArrayD, ArrayViewD, IxDyn};
+
+    use super::*;
+    use crate::model::Inference;
+    use crate::testing::TempDir;
+
+    // logits from mean color: red, green, blue
+    struct MeanColorModel;
+
+    impl Inference for MeanColorModel {
+        fn input_names(&self) -> Vec<String> {
+            vec!["input".into()]
+        }
+
+        fn output_names(&self) -> Vec<String> {
+            vec!["output".into()]
+        }
+
+        fn run(
+            &self,
+            inputs: &[(&str, ArrayViewD<f32>)],
+            _: &[&str],
+        ) -> anyhow::Result<Vec<ArrayD<f32>>> {
+            let x = &inputs[0].1;
+            let logits: Vec<f32> = (0..3)
+                .map(|c| x.index_axis(ndarray::Axis(1), c).mean().unwrap() * 4.0)
+                .collect();
+            Ok(vec![ArrayD::from_shape_vec(IxDyn(&[1, 3]), logits)?])
+        }
+    }
+
+    fn classifier() -> Classifier {
+        let model: Arc<dyn Inference> = Arc::new(MeanColorModel);
+        let mut ans = Classifier::new(model, 16, 16).labels(["battle", "home", "loading"]);
+        ans.preprocess = Preprocess::new().resize(16, 16).normalize(&[0.0], &[1.0]);
+        ans
+    }
+
+    fn solid(rgb: [u8; 3]) -> RgbImage {
+        RgbImage::from_pixel(40, 30, Rgb(rgb))
+    }
+
+    #[test]
+    fn top_k() {
+        let img = solid([20, 240, 120]);
+        let top = classifier()
+            .classify(&img, &(0, 0, 40, 30).into(), 2)
+            .unwrap();
+        let names: Vec<_> = top.iter().map(|x| x.name.as_str()).collect();
+        assert_eq!(names, ["home", "loading"]);
+        assert!(top[0].score > top[1].score);
+
+        let all = classifier()
+            .classify(&img, &(0, 0, 40, 30).into(), 10)
+            .unwrap();
+        assert_eq!(all.len(), 3);
+        let sum: f32 = all.iter().map(|x| x.score).sum();
+        assert!((sum - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn label_count_mismatch() {
+        let img = solid([0, 0, 0]);
+        let classifier = classifier().labels(["a", "b"]);
+        assert!(classifier
+            .classify(&img, &(0, 0, 40, 30).into(), 1)
+            .is_err());
+    }
+
+    #[test]
+    fn dataset_round_trip() {
+        let root = TempDir::new("dataset");
+
+        // screenshots saved during a run, labelled by their dominant channel
+        let runs = root.join("runs");
+        std::fs::create_dir_all(&runs).unwrap();
+        for (i, rgb) in [[200, 10, 10], [10, 10, 200], [10, 200, 10], [220, 0, 0]]
+            .into_iter()
+            .enumerate()
+        {
+            solid(rgb).save(runs.join(format!("{i}.png"))).unwrap();
+        }
+
+        let dataset = Dataset::new(root.join("data")).resize(8, 6);
+        let n = dataset
+            .import(&runs, |_, img| {
+                let [r, g, b] = img.get_pixel(0, 0).0;
+                match () {
+                    _ if r > g && r > b => Some("battle".into()),
+                    _ if b > g => Some("loading".into()),
+                    _ => None,
+                }
+            })
+            .unwrap();
+        assert_eq!(n, 3);
+        assert!(dataset.add("../escape", &solid([0; 3])).is_err());
+        assert!(dataset
+            .add_in("battle", &solid([0; 3]), &(30, 20, 20, 20).into())
+            .is_err());
+
+        assert_eq!(dataset.labels().unwrap(), ["battle", "loading"]);
+        let samples = dataset.samples().unwrap();
+        let labels: Vec<_> = samples.iter().map(|x| x.label).collect();
+        assert_eq!(labels, [0, 0, 1]);
+        let img = image::open(&samples[0].path).unwrap();
+        assert_eq!((img.width(), img.height()), (8, 6));
+
+        let labels = dataset.write_labels().unwrap();
+        let model: Arc<dyn Inference> = Arc::new(MeanColorModel);
+        let classifier = Classifier::new(model, 8, 8).labels_from(labels).unwrap();
+        assert_eq!(classifier.labels, ["battle", "loading"]);
+    }
+}
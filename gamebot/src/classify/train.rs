@@ -0,0 +1,261 @@
+// fine-tune a classifier on device with onnxruntime training artifacts
+//
+// dir holds what onnxruntime.training.artifacts.generate_artifacts writes:
+// training_model.onnx, eval_model.onnx, optimizer_model.onnx and checkpoint.
+// the checkpoint and progress are saved together under resume/ so a stopped
+// run picks up where it left off
+
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use image::RgbImage;
+use ndarray::{concatenate, Array1, Axis};
+use ort::{Allocator, Checkpoint, DynValue, Session, SessionInputValue, Tensor, Trainer};
+use serde::{Deserialize, Serialize};
+
+use super::{Dataset, IMAGENET_MEAN, IMAGENET_STD};
+use crate::model::{register, Model, PixelSource, Preprocess};
+
+const RESUME: &str = "resume";
+// the previous resume while a save swaps in the new one
+const RESUME_OLD: &str = "resume.old";
+const RESUME_TMP: &str = "resume.tmp";
+const CKPT: &str = "checkpoint";
+const PROGRESS: &str = "progress.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Progress {
+    // optimizer updates so far, across resumes
+    pub step: usize,
+    // finished epochs of fit
+    pub epoch: usize,
+    pub loss: f32,
+    pub lr: f32,
+}
+
+pub struct FineTune {
+    trainer: Trainer,
+    dir: PathBuf,
+    progress: Progress,
+    // must match the Classifier that runs the exported model
+    pub preprocess: Preprocess,
+    pub batch_size: usize,
+}
+
+impl FineTune {
+    // width x height is the model input size, resumes from last save if any
+    pub fn open(dir: impl Into<PathBuf>, width: u32, height: u32) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        let (ckpt, mut progress) = match resume_dir(&dir) {
+            Some(resume) => (
+                Some(Checkpoint::load(resume.join(CKPT))?),
+                serde_json::from_slice(&std::fs::read(resume.join(PROGRESS))?)?,
+            ),
+            None => (None, Progress::default()),
+        };
+        let trainer =
+            Trainer::new_from_artifacts(Session::builder()?, Allocator::default(), &dir, ckpt)?;
+
+        // lr is not part of the checkpoint
+        if progress.lr > 0.0 {
+            trainer.optimizer().set_lr(progress.lr)?;
+        } else {
+            progress.lr = trainer.optimizer().lr()?;
+        }
+
+        Ok(FineTune {
+            trainer,
+            dir,
+            progress,
+            preprocess: Preprocess::new()
+                .resize(width, height)
+                .normalize(&IMAGENET_MEAN, &IMAGENET_STD),
+            batch_size: 8,
+        })
+    }
+
+    pub fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    pub fn set_lr(&mut self, lr: f32) -> anyhow::Result<()> {
+        self.trainer.optimizer().set_lr(lr)?;
+        self.progress.lr = lr;
+        Ok(())
+    }
+
+    // forward, backward and one optimizer update, returns the loss
+    pub fn step<P: PixelSource>(&mut self, batch: &[(P, usize)]) -> anyhow::Result<f32> {
+        let (images, labels) = self.collate(batch)?;
+        let loss = {
+            let out = self.trainer.step([images], [labels])?;
+            out[0].try_extract_scalar::<f32>()?
+        };
+        let optimizer = self.trainer.optimizer();
+        optimizer.step()?;
+        optimizer.reset_grad()?;
+
+        self.progress.step += 1;
+        self.progress.loss = loss;
+        Ok(loss)
+    }
+
+    // loss without touching the weights
+    pub fn eval<P: PixelSource>(&self, batch: &[(P, usize)]) -> anyhow::Result<f32> {
+        let (images, labels) = self.collate(batch)?;
+        let out = self.trainer.eval_step([images], [labels])?;
+        Ok(out[0].try_extract_scalar::<f32>()?)
+    }
+
+    // train until epochs are done in total, progress is saved after every epoch
+    pub fn fit(
+        &mut self,
+        dataset: &Dataset,
+        epochs: usize,
+        mut on_step: impl FnMut(&Progress),
+    ) -> anyhow::Result<()> {
+        let samples = dataset.samples()?;
+        if samples.is_empty() {
+            bail!("dataset {} is empty", dataset.root().display());
+        }
+        while self.progress.epoch < epochs {
+            let mut order: Vec<_> = (0..samples.len()).collect();
+            shuffle(&mut order, self.progress.epoch as u64);
+            for chunk in order.chunks(self.batch_size.max(1)) {
+                let batch = chunk
+                    .iter()
+                    .map(|&i| Ok((image::open(&samples[i].path)?.to_rgb8(), samples[i].label)))
+                    .collect::<anyhow::Result<Vec<(RgbImage, usize)>>>()?;
+                self.step(&batch)?;
+                on_step(&self.progress);
+            }
+            self.progress.epoch += 1;
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    // checkpoint with optimizer state and progress, swapped in as one dir
+    pub fn save(&self) -> anyhow::Result<()> {
+        save_dir(&self.dir, |tmp| {
+            self.trainer.checkpoint().save(tmp.join(CKPT), true)?;
+            std::fs::write(tmp.join(PROGRESS), serde_json::to_vec(&self.progress)?)?;
+            Ok(())
+        })
+    }
+
+    // inference graph as <dir>/<name>.onnx, registered under name so the next
+    // model::get(name) and a Classifier holding name pick it up
+    pub fn export(&self, name: &str, output_names: &[&str]) -> anyhow::Result<PathBuf> {
+        let path = self.dir.join(format!("{name}.onnx"));
+        self.trainer.export(&path, output_names)?;
+        register(name, Model::Onnx(path.clone().into()));
+        Ok(path)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn collate<P: PixelSource>(
+        &self,
+        batch: &[(P, usize)],
+    ) -> anyhow::Result<(SessionInputValue<'static>, SessionInputValue<'static>)> {
+        if batch.is_empty() {
+            bail!("empty batch");
+        }
+        let tensors: Vec<_> = batch
+            .iter()
+            .map(|(src, _)| self.preprocess.run(src).tensor)
+            .collect();
+        let views: Vec<_> = tensors.iter().map(|x| x.view()).collect();
+        let images = concatenate(Axis(0), &views)?;
+        // torch cross entropy takes int64 class ids
+        let labels: Array1<i64> = batch.iter().map(|(_, label)| *label as i64).collect();
+
+        Ok((
+            DynValue::try_from(images.view())?.into(),
+            Tensor::from_array(labels)?.into(),
+        ))
+    }
+}
+
+// written into resume.tmp, then renamed over resume. a dir can't be renamed
+// over a non-empty one, so the old resume steps aside to resume.old first
+fn save_dir(dir: &Path, write: impl FnOnce(&Path) -> anyhow::Result<()>) -> anyhow::Result<()> {
+    let tmp = dir.join(RESUME_TMP);
+    let _ = std::fs::remove_dir_all(&tmp);
+    std::fs::create_dir_all(&tmp)?;
+    write(&tmp)?;
+
+    let resume = dir.join(RESUME);
+    let old = dir.join(RESUME_OLD);
+    if resume.exists() {
+        let _ = std::fs::remove_dir_all(&old);
+        std::fs::rename(&resume, &old)?;
+    }
+    std::fs::rename(&tmp, &resume)?;
+    let _ = std::fs::remove_dir_all(&old);
+    Ok(())
+}
+
+// resume.old is only left alone when a save was killed between the renames
+fn resume_dir(dir: &Path) -> Option<PathBuf> {
+    [RESUME, RESUME_OLD]
+        .into_iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.exists())
+}
+
+// seeded by epoch so the order differs between epochs but is reproducible
+fn shuffle(x: &mut [usize], seed: u64) {
+    let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+    for i in (1..x.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        x.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn shuffle_is_stable_permutation() {
+        let mut a: Vec<_> = (0..50).collect();
+        let mut b = a.clone();
+        shuffle(&mut a, 3);
+        shuffle(&mut b, 3);
+        assert_eq!(a, b);
+        assert_ne!(a, (0..50).collect::<Vec<_>>());
+
+        let mut c = (0..50).collect::<Vec<_>>();
+        shuffle(&mut c, 4);
+        assert_ne!(a, c);
+
+        a.sort();
+        assert_eq!(a, (0..50).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn save_swaps_whole_dir() {
+        let dir = TempDir::new("resume");
+        assert_eq!(resume_dir(&dir), None);
+
+        for step in ["1", "2"] {
+            save_dir(&dir, |tmp| Ok(std::fs::write(tmp.join(PROGRESS), step)?)).unwrap();
+        }
+        let resume = resume_dir(&dir).unwrap();
+        assert_eq!(resume, dir.join(RESUME));
+        assert_eq!(std::fs::read_to_string(resume.join(PROGRESS)).unwrap(), "2");
+        assert!(!dir.join(RESUME_OLD).exists());
+        assert!(!dir.join(RESUME_TMP).exists());
+
+        // killed after the old resume stepped aside
+        std::fs::rename(dir.join(RESUME), dir.join(RESUME_OLD)).unwrap();
+        assert_eq!(resume_dir(&dir), Some(dir.join(RESUME_OLD)));
+    }
+}
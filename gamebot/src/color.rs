@@ -5,7 +5,7 @@ use image::{ImageReader, RgbaImage};
 use serde::Deserialize;
 use thiserror::Error;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct Point {
     pub x: i32,
     pub y: i32,
@@ -18,6 +18,88 @@ impl Point {
     }
 }
 
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl From<(u8, u8, u8)> for Color {
+    fn from((red, green, blue): (u8, u8, u8)) -> Self {
+        Color { red, green, blue }
+    }
+}
+
+// inclusive per channel range
+#[derive(Default, Clone, Debug)]
+pub struct ColorMatch {
+    pub min: Color,
+    pub max: Color,
+}
+
+impl ColorMatch {
+    pub fn new(color: impl Into<Color>, tolerance: u8) -> Self {
+        let color = color.into();
+        ColorMatch {
+            min: Color {
+                red: color.red.saturating_sub(tolerance),
+                green: color.green.saturating_sub(tolerance),
+                blue: color.blue.saturating_sub(tolerance),
+            },
+            max: Color {
+                red: color.red.saturating_add(tolerance),
+                green: color.green.saturating_add(tolerance),
+                blue: color.blue.saturating_add(tolerance),
+            },
+        }
+    }
+
+    pub fn range(min: impl Into<Color>, max: impl Into<Color>) -> Self {
+        ColorMatch {
+            min: min.into(),
+            max: max.into(),
+        }
+    }
+
+    pub fn matches(&self, red: u8, green: u8, blue: u8) -> bool {
+        (self.min.red..=self.max.red).contains(&red)
+            && (self.min.green..=self.max.green).contains(&green)
+            && (self.min.blue..=self.max.blue).contains(&blue)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Histogram {
+    pub red: [u32; 256],
+    pub green: [u32; 256],
+    pub blue: [u32; 256],
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Histogram {
+            red: [0; 256],
+            green: [0; 256],
+            blue: [0; 256],
+        }
+    }
+}
+
+// connected pixels matching a ColorMatch
+#[derive(Clone, Debug)]
+pub struct Blob {
+    pub rect: Rect,
+    pub centroid: Point,
+    pub area: u32,
+}
+
+impl Blob {
+    pub fn click(&self) {
+        self.centroid.click()
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct ColorPoint {
     pub red: u8,
@@ -74,6 +156,13 @@ impl Rect {
         self.top + self.height as i32
     }
 
+    pub fn center(&self) -> Point {
+        Point {
+            x: self.left + self.width as i32 / 2,
+            y: self.top + self.height as i32 / 2,
+        }
+    }
+
     pub fn contains(&self, x: &Rect) -> bool {
         x.left >= self.left
             && x.right() <= self.right()
@@ -105,6 +194,17 @@ impl Region {
             && x.bottom() <= self.bottom()
     }
 }
+impl From<&Region> for Rect {
+    fn from(value: &Region) -> Self {
+        Rect {
+            left: value.left as _,
+            top: value.top as _,
+            width: value.width,
+            height: value.height,
+        }
+    }
+}
+
 impl From<(u32, u32, u32, u32)> for Region {
     fn from(value: (u32, u32, u32, u32)) -> Self {
         Region {
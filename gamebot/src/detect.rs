@@ -0,0 +1,547 @@
+// object detection with yolo / nanodet style models, boxes are decoded here
+// so any Inference backend works
+
+use std::sync::{Arc, RwLock};
+
+use anyhow::{bail, Context};
+use ndarray::{ArrayViewD, Axis};
+
+use crate::{
+    api::take_screenshot,
+    color::{Point, Rect, Region},
+    find::miss_on_error,
+    model::{ModelRef, PixelSource, Preprocess, Source},
+    screenshot::Screenshot,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Head {
+    // [1, n, 5 + classes]: cx, cy, w, h, objectness, class scores
+    YoloV5,
+    // [1, 4 + classes, n]: cx, cy, w, h, class scores, either axis order is accepted
+    YoloV8,
+    // [1, n, classes + 4 * (reg_max + 1)], nanodet-plus export with sigmoid scores
+    NanoDet { reg_max: usize },
+}
+
+impl Head {
+    pub fn preprocess(&self, width: u32, height: u32) -> Preprocess {
+        match self {
+            Head::YoloV5 | Head::YoloV8 => {
+                Preprocess::new().letterbox(width, height).fill([114; 3])
+            }
+            Head::NanoDet { .. } => Preprocess::new().resize(width, height).bgr().normalize(
+                &[103.53 / 255.0, 116.28 / 255.0, 123.675 / 255.0],
+                &[57.375 / 255.0, 57.12 / 255.0, 58.395 / 255.0],
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Detection {
+    pub class: String,
+    pub class_id: usize,
+    pub score: f32,
+    pub rect: Rect,
+}
+
+impl Detection {
+    pub fn center(&self) -> Point {
+        self.rect.center()
+    }
+
+    pub fn click(&self) {
+        self.center().click()
+    }
+}
+
+pub struct Detector {
+    model: ModelRef,
+    head: Head,
+    width: u32,
+    height: u32,
+    // index is class id, empty means ids are used as names
+    pub classes: Vec<String>,
+    pub preprocess: Preprocess,
+    pub score_threshold: f32,
+    pub iou_threshold: f32,
+    pub max_detections: usize,
+    // nanodet strides, in output order
+    pub strides: Vec<u32>,
+}
+
+impl Detector {
+    // width x height is the model input size
+    pub fn new(model: impl Into<ModelRef>, head: Head, width: u32, height: u32) -> Self {
+        Detector {
+            model: model.into(),
+            head,
+            width,
+            height,
+            classes: vec![],
+            preprocess: head.preprocess(width, height),
+            score_threshold: 0.25,
+            iou_threshold: 0.45,
+            max_detections: 300,
+            strides: vec![8, 16, 32, 64],
+        }
+    }
+
+    pub fn classes(mut self, classes: impl IntoIterator<Item = impl ToString>) -> Self {
+        self.classes = classes.into_iter().map(|x| x.to_string()).collect();
+        self
+    }
+
+    // one class name per line, as coco.names
+    pub fn classes_from(self, source: impl Into<Source>) -> anyhow::Result<Self> {
+        let data = source.into().read()?;
+        let text = std::str::from_utf8(&data)?;
+        let names: Vec<_> = text
+            .lines()
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .collect();
+        Ok(self.classes(names))
+    }
+
+    // rect in result is in src coordinate
+    pub fn detect(
+        &self,
+        src: &impl PixelSource,
+        region: &Region,
+    ) -> anyhow::Result<Vec<Detection>> {
+        let prepared = self.preprocess.run_in(src, region);
+        let out = self
+            .model
+            .get()?
+            .run_one(prepared.tensor.into_dyn().view())?;
+
+        let mut boxes = self.decode(out.view())?;
+        boxes.retain(|x| x.score >= self.score_threshold);
+        let boxes = nms(boxes, self.iou_threshold, self.max_detections);
+
+        Ok(boxes
+            .into_iter()
+            .map(|x| {
+                let (l, t) = prepared.transform.to_source((x.x0, x.y0));
+                let (r, b) = prepared.transform.to_source((x.x1, x.y1));
+                Detection {
+                    class: self.class_name(x.class),
+                    class_id: x.class,
+                    score: x.score,
+                    rect: Rect {
+                        left: l.round() as i32,
+                        top: t.round() as i32,
+                        width: (r - l).round().max(0.0) as u32,
+                        height: (b - t).round().max(0.0) as u32,
+                    },
+                }
+            })
+            .collect())
+    }
+
+    fn class_name(&self, id: usize) -> String {
+        self.classes
+            .get(id)
+            .cloned()
+            .unwrap_or_else(|| id.to_string())
+    }
+
+    fn decode(&self, out: ArrayViewD<f32>) -> anyhow::Result<Vec<RawBox>> {
+        let out = match out.ndim() {
+            3 => out.index_axis_move(Axis(0), 0),
+            2 => out,
+            _ => bail!("unexpected detection output shape {:?}", out.shape()),
+        };
+        let (rows, cols) = (out.shape()[0], out.shape()[1]);
+        let nc = self.classes.len();
+
+        let mut ans = vec![];
+        match self.head {
+            Head::YoloV5 => {
+                for row in out.outer_iter() {
+                    let row: Vec<f32> = row.iter().copied().collect();
+                    let Some((class, score)) = argmax(row.get(5..).unwrap_or_default()) else {
+                        continue;
+                    };
+                    ans.push(RawBox::center(&row, class, row[4] * score));
+                }
+            }
+            Head::YoloV8 => {
+                // channel first unless rows already look like 4 + classes
+                let out = if (nc > 0 && cols == 4 + nc) || (nc == 0 && rows > cols) {
+                    out
+                } else {
+                    out.reversed_axes()
+                };
+                for row in out.outer_iter() {
+                    let row: Vec<f32> = row.iter().copied().collect();
+                    let Some((class, score)) = argmax(row.get(4..).unwrap_or_default()) else {
+                        continue;
+                    };
+                    ans.push(RawBox::center(&row, class, score));
+                }
+            }
+            Head::NanoDet { reg_max } => {
+                let bins = reg_max + 1;
+                let nc = cols.saturating_sub(4 * bins);
+                let centers = self.nanodet_centers();
+                if centers.len() != rows {
+                    bail!(
+                        "nanodet output has {rows} rows, strides give {}",
+                        centers.len()
+                    );
+                }
+                for (row, (cx, cy, stride)) in out.outer_iter().zip(centers) {
+                    let row: Vec<f32> = row.iter().copied().collect();
+                    let Some((class, score)) = argmax(&row[..nc]) else {
+                        continue;
+                    };
+                    if score < self.score_threshold {
+                        continue;
+                    }
+                    let d: Vec<f32> = row[nc..]
+                        .chunks(bins)
+                        .map(|x| integral(x) * stride)
+                        .collect();
+                    ans.push(RawBox {
+                        x0: cx - d[0],
+                        y0: cy - d[1],
+                        x1: cx + d[2],
+                        y1: cy + d[3],
+                        class,
+                        score,
+                    });
+                }
+            }
+        }
+        Ok(ans)
+    }
+
+    fn nanodet_centers(&self) -> Vec<(f32, f32, f32)> {
+        let mut ans = vec![];
+        for &stride in &self.strides {
+            for y in 0..self.height.div_ceil(stride) {
+                for x in 0..self.width.div_ceil(stride) {
+                    ans.push(((x * stride) as f32, (y * stride) as f32, stride as f32));
+                }
+            }
+        }
+        ans
+    }
+}
+
+// corner box in model input coordinate
+#[derive(Debug, Clone)]
+struct RawBox {
+    x0: f32,
+    y0: f32,
+    x1: f32,
+    y1: f32,
+    class: usize,
+    score: f32,
+}
+
+impl RawBox {
+    fn center(row: &[f32], class: usize, score: f32) -> RawBox {
+        let (cx, cy, w, h) = (row[0], row[1], row[2], row[3]);
+        RawBox {
+            x0: cx - w / 2.0,
+            y0: cy - h / 2.0,
+            x1: cx + w / 2.0,
+            y1: cy + h / 2.0,
+            class,
+            score,
+        }
+    }
+
+    fn area(&self) -> f32 {
+        (self.x1 - self.x0).max(0.0) * (self.y1 - self.y0).max(0.0)
+    }
+
+    fn iou(&self, other: &RawBox) -> f32 {
+        let w = (self.x1.min(other.x1) - self.x0.max(other.x0)).max(0.0);
+        let h = (self.y1.min(other.y1) - self.y0.max(other.y0)).max(0.0);
+        let inter = w * h;
+        let union = self.area() + other.area() - inter;
+        if union <= 0.0 {
+            0.0
+        } else {
+            inter / union
+        }
+    }
+}
+
+fn argmax(x: &[f32]) -> Option<(usize, f32)> {
+    x.iter()
+        .copied()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+// expectation of softmax over distance bins
+fn integral(x: &[f32]) -> f32 {
+    let max = x.iter().copied().fold(f32::MIN, f32::max);
+    let exp: Vec<f32> = x.iter().map(|&v| (v - max).exp()).collect();
+    let sum: f32 = exp.iter().sum();
+    exp.iter()
+        .enumerate()
+        .map(|(i, &e)| i as f32 * e)
+        .sum::<f32>()
+        / sum
+}
+
+// greedy, per class, highest score first
+fn nms(mut boxes: Vec<RawBox>, iou_threshold: f32, max: usize) -> Vec<RawBox> {
+    boxes.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut ans: Vec<RawBox> = vec![];
+    for x in boxes {
+        if ans.len() >= max {
+            break;
+        }
+        if ans
+            .iter()
+            .all(|y| y.class != x.class || y.iou(&x) <= iou_threshold)
+        {
+            ans.push(x);
+        }
+    }
+    ans
+}
+
+static DETECTOR: RwLock<Option<Arc<Detector>>> = RwLock::new(None);
+
+pub fn set_detector(detector: Detector) {
+    *DETECTOR.write().unwrap() = Some(Arc::new(detector));
+}
+
+fn detector() -> anyhow::Result<Arc<Detector>> {
+    DETECTOR
+        .read()
+        .unwrap()
+        .clone()
+        .context("detector is not set, call detect::set_detector first")
+}
+
+pub fn detect(region: impl Into<Region>) -> anyhow::Result<Vec<Detection>> {
+    take_screenshot().detect(&region.into())
+}
+
+impl Screenshot {
+    pub fn detect(&self, region: &Region) -> anyhow::Result<Vec<Detection>> {
+        if !self.region().contains(region) {
+            return Ok(vec![]);
+        }
+        detector()?.detect(self, region)
+    }
+
+    pub fn find_detect_in(&self, target: &DetectIn) -> Option<Detection> {
+        miss_on_error("detect", self.detect(&target.region_in(self.region())))?
+            .into_iter()
+            .filter(|x| x.class == target.class && x.score >= target.min_score)
+            .max_by(|a, b| a.score.total_cmp(&b.score))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DetectIn {
+    pub class: String,
+    pub min_score: f32,
+    pub region: Region,
+}
+
+impl DetectIn {
+    pub fn within(&self, region: impl Into<Region>) -> DetectIn {
+        DetectIn {
+            region: region.into(),
+            ..self.clone()
+        }
+    }
+
+    pub fn min_score(&self, min_score: f32) -> DetectIn {
+        DetectIn {
+            min_score,
+            ..self.clone()
+        }
+    }
+
+    // empty region means whole screen
+    fn region_in(&self, full: Region) -> Region {
+        if self.region.width == 0 || self.region.height == 0 {
+            full
+        } else {
+            self.region.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbImage;
+    use ndarray::{Array3, ArrayD};
+
+    use super::*;
+    use crate::model::Inference;
+
+    // stands in for a yolov8 model: box around pure red / blue pixels of the
+    // input, plus a weaker copy shifted by a pixel that nms has to drop
+    struct ColorBlobModel;
+
+    impl Inference for ColorBlobModel {
+        fn input_names(&self) -> Vec<String> {
+            vec!["images".into()]
+        }
+
+        fn output_names(&self) -> Vec<String> {
+            vec!["output0".into()]
+        }
+
+        fn run(
+            &self,
+            inputs: &[(&str, ArrayViewD<f32>)],
+            _: &[&str],
+        ) -> anyhow::Result<Vec<ArrayD<f32>>> {
+            let x = &inputs[0].1;
+            let (h, w) = (x.shape()[2], x.shape()[3]);
+            let mut rows = vec![];
+            for class in 0..2 {
+                let target = if class == 0 {
+                    [1.0, 0.0, 0.0]
+                } else {
+                    [0.0, 0.0, 1.0]
+                };
+                let (mut x0, mut y0, mut x1, mut y1) = (w, h, 0, 0);
+                for y in 0..h {
+                    for i in 0..w {
+                        if (0..3).all(|c| (x[[0, c, y, i]] - target[c]).abs() < 0.05) {
+                            (x0, y0, x1, y1) = (x0.min(i), y0.min(y), x1.max(i + 1), y1.max(y + 1));
+                        }
+                    }
+                }
+                if x0 >= x1 {
+                    continue;
+                }
+                let (cx, cy) = ((x0 + x1) as f32 / 2.0, (y0 + y1) as f32 / 2.0);
+                let (bw, bh) = ((x1 - x0) as f32, (y1 - y0) as f32);
+                let mut scores = [0.0; 2];
+                scores[class] = 0.9;
+                rows.push([cx, cy, bw, bh, scores[0], scores[1]]);
+                scores[class] = 0.6;
+                rows.push([cx + 1.0, cy, bw, bh, scores[0], scores[1]]);
+            }
+            let mut out = Array3::zeros((1, 6, rows.len()));
+            for (j, row) in rows.iter().enumerate() {
+                for (c, &v) in row.iter().enumerate() {
+                    out[(0, c, j)] = v;
+                }
+            }
+            Ok(vec![out.into_dyn()])
+        }
+    }
+
+    fn fixture(name: &str) -> RgbImage {
+        let path = format!("{}/fixture/{name}", env!("CARGO_MANIFEST_DIR"));
+        image::open(path).unwrap().to_rgb8()
+    }
+
+    fn detector() -> Detector {
+        let model: Arc<dyn Inference> = Arc::new(ColorBlobModel);
+        Detector::new(model, Head::YoloV8, 64, 64).classes(["red", "blue"])
+    }
+
+    #[test]
+    fn yolo_v8_fixture() {
+        // 200x100, red square 20..40 x 30..50, blue square 150..170 x 60..80
+        let img = fixture("detect_squares.png");
+        let mut found = detector()
+            .detect(&img, &Region::from((0, 0, 200, 100)))
+            .unwrap();
+        found.sort_by_key(|x| x.class_id);
+        assert_eq!(found.len(), 2, "{found:?}");
+        assert_eq!(found[0].class, "red");
+        assert_eq!(found[1].class, "blue");
+        let red = found[0].center();
+        let blue = found[1].center();
+        assert!(
+            (red.x - 30).abs() <= 4 && (red.y - 40).abs() <= 4,
+            "{red:?}"
+        );
+        assert!(
+            (blue.x - 160).abs() <= 4 && (blue.y - 70).abs() <= 4,
+            "{blue:?}"
+        );
+    }
+
+    #[test]
+    fn region_offsets_result() {
+        let img = fixture("detect_squares.png");
+        let found = detector()
+            .detect(&img, &Region::from((100, 0, 100, 100)))
+            .unwrap();
+        assert_eq!(found.len(), 1, "{found:?}");
+        assert_eq!(found[0].class, "blue");
+        assert!((found[0].center().x - 160).abs() <= 4);
+    }
+
+    #[test]
+    fn nms_per_class() {
+        let b = |x0: f32, class, score| RawBox {
+            x0,
+            y0: 0.0,
+            x1: x0 + 10.0,
+            y1: 10.0,
+            class,
+            score,
+        };
+        let kept = nms(
+            vec![
+                b(0.0, 0, 0.5),
+                b(1.0, 0, 0.9),
+                b(1.0, 1, 0.3),
+                b(50.0, 0, 0.4),
+            ],
+            0.45,
+            300,
+        );
+        let got: Vec<_> = kept.iter().map(|x| (x.x0, x.class)).collect();
+        assert_eq!(got, [(1.0, 0), (50.0, 0), (1.0, 1)]);
+    }
+
+    #[test]
+    fn yolo_v5_decode() {
+        let model: Arc<dyn Inference> = Arc::new(ColorBlobModel);
+        let detector = Detector::new(model, Head::YoloV5, 64, 64).classes(["a", "b"]);
+        let out = ndarray::arr3(&[[[32.0, 32.0, 10.0, 20.0, 0.5, 0.2, 0.8]]]);
+        let boxes = detector.decode(out.view().into_dyn()).unwrap();
+        assert_eq!(boxes.len(), 1);
+        assert_eq!(boxes[0].class, 1);
+        assert!((boxes[0].score - 0.4).abs() < 1e-6);
+        assert_eq!(
+            (boxes[0].x0, boxes[0].y0, boxes[0].x1, boxes[0].y1),
+            (27.0, 22.0, 37.0, 42.0)
+        );
+    }
+
+    #[test]
+    fn nanodet_decode() {
+        let model: Arc<dyn Inference> = Arc::new(ColorBlobModel);
+        let mut detector = Detector::new(model, Head::NanoDet { reg_max: 1 }, 16, 16);
+        detector.strides = vec![8, 16];
+        // 4 + 1 centers, 1 class, 2 bins per side
+        let mut out = ndarray::Array3::<f32>::zeros((1, 5, 9));
+        // center (8, 8) of stride 8, distance bins strongly pick 1 -> 8 px each side
+        out[(0, 3, 0)] = 0.9;
+        for side in 0..4 {
+            out[(0, 3, 1 + side * 2)] = -20.0;
+            out[(0, 3, 2 + side * 2)] = 20.0;
+        }
+        let boxes = detector.decode(out.view().into_dyn()).unwrap();
+        assert_eq!(boxes.len(), 1);
+        let b = &boxes[0];
+        assert!(
+            (b.x0 - 0.0).abs() < 1e-3 && (b.x1 - 16.0).abs() < 1e-3,
+            "{b:?}"
+        );
+    }
+}
@@ -0,0 +1,284 @@
+// state of the device itself, for farms that run for hours: cool down when
+// hot, go easy on battery, wait out a lost connection
+//
+// battery, brightness and volume come from shell commands, network, screen
+// and rotation from the host
+
+use std::{
+    collections::HashMap,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{
+    api::{proxy, wait, Seconds},
+    shell,
+};
+
+// media, what games play through
+const STREAM_MUSIC: &str = "3";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Plugged {
+    #[default]
+    None,
+    Ac,
+    Usb,
+    Wireless,
+    Dock,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Battery {
+    // percent
+    pub level: u32,
+    // celsius
+    pub temperature: f32,
+    // full while plugged in counts as charging
+    pub charging: bool,
+    pub plugged: Plugged,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub enum NetworkType {
+    #[default]
+    None,
+    Wifi,
+    Cellular,
+    Ethernet,
+    Other,
+}
+
+// the host leaves out fields at their default
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Network {
+    pub connected: bool,
+    #[serde(rename = "type")]
+    pub kind: NetworkType,
+    pub metered: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Screen {
+    // on and not dozing
+    pub interactive: bool,
+    pub locked: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Volume {
+    pub level: u32,
+    pub max: u32,
+}
+
+pub fn battery() -> anyhow::Result<Battery> {
+    let output = shell::run("dumpsys", &["battery"], 5)?;
+    parse_battery(&output.stdout)
+}
+
+pub fn network() -> Network {
+    proxy().network_info()
+}
+
+pub fn screen() -> Screen {
+    proxy().screen_info()
+}
+
+// clockwise degrees from the natural orientation, 0 90 180 or 270
+pub fn rotation() -> u32 {
+    proxy().rotation() as u32 * 90
+}
+
+// 0 to 255, whatever the slider in settings shows
+pub fn brightness() -> anyhow::Result<u32> {
+    let output = settings(&["get", "system", "screen_brightness"])?;
+    output
+        .trim()
+        .parse()
+        .with_context(|| format!("screen_brightness is {output:?}"))
+}
+
+// turns off auto brightness, it would move it again
+pub fn set_brightness(level: u32) -> anyhow::Result<()> {
+    settings(&["put", "system", "screen_brightness_mode", "0"])?;
+    settings(&[
+        "put",
+        "system",
+        "screen_brightness",
+        &level.min(255).to_string(),
+    ])?;
+    Ok(())
+}
+
+pub fn volume() -> anyhow::Result<Volume> {
+    let output = media_volume(&["--get"])?;
+    parse_volume(&output).with_context(|| format!("volume from {output:?}"))
+}
+
+pub fn set_volume(level: u32) -> anyhow::Result<()> {
+    media_volume(&["--set", &level.to_string()])?;
+    Ok(())
+}
+
+// polls every second, false when the timeout passed first
+pub fn wait_until(condition: impl Fn() -> bool, timeout: impl Seconds) -> bool {
+    let start = Instant::now();
+    let timeout = timeout.into_duration();
+    loop {
+        if condition() {
+            return true;
+        }
+        let left = timeout.saturating_sub(start.elapsed());
+        if left.is_zero() {
+            return false;
+        }
+        wait(left.min(Duration::from_secs(1)));
+    }
+}
+
+pub fn wait_until_connected(timeout: impl Seconds) -> bool {
+    wait_until(|| network().connected, timeout)
+}
+
+pub fn wait_until_cooler(celsius: f32, timeout: impl Seconds) -> bool {
+    wait_until(
+        || battery().is_ok_and(|x| x.temperature <= celsius),
+        timeout,
+    )
+}
+
+pub fn wait_until_charged(level: u32, timeout: impl Seconds) -> bool {
+    wait_until(|| battery().is_ok_and(|x| x.level >= level), timeout)
+}
+
+fn settings(args: &[&str]) -> anyhow::Result<String> {
+    let output = shell::run("settings", args, 5)?;
+    if !output.success() {
+        bail!("settings {}: {}", args.join(" "), output.stderr.trim());
+    }
+    Ok(output.stdout)
+}
+
+// media_session takes it since android 11, the media tool before
+fn media_volume(args: &[&str]) -> anyhow::Result<String> {
+    let args = [&["volume", "--stream", STREAM_MUSIC][..], args].concat();
+    let output = shell::run("cmd", &[&["media_session"][..], &args].concat(), 5)?;
+    if output.success() && output.stdout.contains("volume") {
+        return Ok(output.stdout);
+    }
+    let output = shell::run("media", &args, 5)?;
+    if !output.success() {
+        bail!("media {}: {}", args.join(" "), output.stderr.trim());
+    }
+    Ok(output.stdout)
+}
+
+// "key: value" lines of dumpsys battery
+fn parse_battery(text: &str) -> anyhow::Result<Battery> {
+    let fields: HashMap<&str, &str> = text
+        .lines()
+        .filter_map(|x| x.split_once(':'))
+        .map(|(k, v)| (k.trim(), v.trim()))
+        .collect();
+    let int = |key: &str| -> anyhow::Result<i64> {
+        fields
+            .get(key)
+            .with_context(|| format!("no {key} in dumpsys battery"))?
+            .parse()
+            .with_context(|| format!("{key} in dumpsys battery"))
+    };
+    let powered = |key: &str| fields.get(key).is_some_and(|x| *x == "true");
+
+    let scale = int("scale").unwrap_or(100).max(1);
+    // BatteryManager.BATTERY_STATUS_CHARGING and _FULL
+    let status = int("status")?;
+    let plugged = if powered("AC powered") {
+        Plugged::Ac
+    } else if powered("USB powered") {
+        Plugged::Usb
+    } else if powered("Wireless powered") {
+        Plugged::Wireless
+    } else if powered("Dock powered") {
+        Plugged::Dock
+    } else {
+        Plugged::None
+    };
+    Ok(Battery {
+        level: (int("level")? * 100 / scale) as u32,
+        // tenths of a degree
+        temperature: int("temperature")? as f32 / 10.0,
+        charging: status == 2 || (status == 5 && plugged != Plugged::None),
+        plugged,
+    })
+}
+
+fn parse_volume(text: &str) -> Option<Volume> {
+    static VOLUME: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"volume is (\d+) in range \[\d+\.\.(\d+)\]").unwrap());
+    let x = VOLUME.captures(text)?;
+    Some(Volume {
+        level: x[1].parse().ok()?,
+        max: x[2].parse().ok()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn battery_from_dumpsys() {
+        let text = "Current Battery Service state:
+  AC powered: false
+  USB powered: true
+  Wireless powered: false
+  Max charging current: 500000
+  status: 2
+  health: 2
+  present: true
+  level: 85
+  scale: 100
+  voltage: 4200
+  temperature: 342
+  technology: Li-ion
+";
+        assert_eq!(
+            parse_battery(text).unwrap(),
+            Battery {
+                level: 85,
+                temperature: 34.2,
+                charging: true,
+                plugged: Plugged::Usb,
+            }
+        );
+        assert!(parse_battery("Current Battery Service state:\n").is_err());
+    }
+
+    #[test]
+    fn volume_from_media() {
+        assert_eq!(
+            parse_volume("[v] will get volume\n[v] volume is 7 in range [0..15]\n"),
+            Some(Volume { level: 7, max: 15 })
+        );
+        assert_eq!(parse_volume("Error: unknown command"), None);
+    }
+
+    #[test]
+    fn network_from_host_json() {
+        let x: Network = serde_json::from_str(r#"{"connected":true,"type":"Wifi"}"#).unwrap();
+        assert_eq!(
+            x,
+            Network {
+                connected: true,
+                kind: NetworkType::Wifi,
+                metered: false,
+            }
+        );
+    }
+}
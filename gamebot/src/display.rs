@@ -0,0 +1,92 @@
+// run at the resolution the assets were made for, like `wm size` and
+// `wm density`, on any phone
+//
+// the previous size and density come back when the closure returns or
+// unwinds, stopping the guest unwinds it too. whatever is still changed
+// when the guest stops is put back by the host
+
+use serde::Deserialize;
+
+use crate::api::proxy;
+
+// sizes in the natural orientation, physical is the panel without overrides
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(default)]
+pub struct DisplayInfo {
+    pub width: u32,
+    pub height: u32,
+    pub density: u32,
+    pub physical_width: u32,
+    pub physical_height: u32,
+    pub physical_density: u32,
+}
+
+pub fn display_info() -> DisplayInfo {
+    proxy().display_info()
+}
+
+// width and height in either orientation, e.g. 1280x720 for a landscape
+// game works on a phone that is portrait when upright
+pub fn with_resolution<T>(width: u32, height: u32, dpi: u32, f: impl FnOnce() -> T) -> T {
+    let _restore = set_resolution(width, height, dpi);
+    f()
+}
+
+// for the whole run, keep the guard until the end or forget it and leave
+// restoring to the host on stop
+#[must_use = "the resolution is restored when this is dropped"]
+pub fn set_resolution(width: u32, height: u32, dpi: u32) -> Restore {
+    let before = display_info();
+    let (width, height) = natural(width, height, &before);
+    log::info!("display {width}x{height} {dpi}dpi, was {before:?}");
+    proxy().set_display(width, height, dpi);
+    Restore { before }
+}
+
+pub struct Restore {
+    before: DisplayInfo,
+}
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let DisplayInfo {
+            width,
+            height,
+            density,
+            ..
+        } = self.before;
+        proxy().set_display(width, height, density);
+    }
+}
+
+// turned to match the panel, overrides are in its orientation
+fn natural(width: u32, height: u32, info: &DisplayInfo) -> (u32, u32) {
+    if (width > height) == (info.physical_width > info.physical_height) {
+        (width, height)
+    } else {
+        (height, width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turn_to_panel() {
+        let phone = DisplayInfo {
+            physical_width: 1080,
+            physical_height: 2400,
+            ..Default::default()
+        };
+        assert_eq!(natural(1280, 720, &phone), (720, 1280));
+        assert_eq!(natural(720, 1280, &phone), (720, 1280));
+
+        let tablet = DisplayInfo {
+            physical_width: 2560,
+            physical_height: 1600,
+            ..Default::default()
+        };
+        assert_eq!(natural(720, 1280, &tablet), (1280, 720));
+    }
+}
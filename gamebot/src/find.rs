@@ -1,15 +1,23 @@
 use std::{
+    collections::BTreeSet,
     mem::take,
     ops::Deref,
-    sync::{atomic::AtomicU64, LazyLock},
+    sync::{atomic::AtomicU64, LazyLock, Mutex},
     time::{Duration, Instant},
     u64,
 };
 
 use crate::{
     api::{take_nodeshot, take_screenshot, wait, wait_screenshot_after, Seconds},
-    color::{ColorPoint, ColorPointGroup, ColorPointGroupIn, DiskImageIn, ImageIn, Point},
+    classify::{Label, SceneIs},
+    color::{
+        ColorPoint, ColorPointGroup, ColorPointGroupIn, DiskImageIn, ImageIn, Point, Rect, Region,
+    },
+    detect::{DetectIn, Detection},
+    journal::{self, Evidence, Outcome},
     node::{ANode, NodeSelector, Nodeshot},
+    ocr::TextIn,
+    overlay,
     screenshot::Screenshot,
 };
 
@@ -40,25 +48,25 @@ pub trait GroupFind<'a, I: Find + 'a>: GroupFindOnce<'a, I> + Copy {
 impl<'a, T: IntoIterator<Item = &'a ColorPoint>> GroupFindOnce<'a, ColorPoint> for T {
     fn all_exist(self) -> bool {
         let shot = take_screenshot();
-        self.into_iter().all(|x| shot.find_color_point(x).is_some())
+        self.into_iter().all(|x| x.find_on(&shot).is_some())
     }
 
     fn any_exist(self) -> bool {
         let shot = take_screenshot();
-        self.into_iter().any(|x| shot.find_color_point(x).is_some())
+        self.into_iter().any(|x| x.find_on(&shot).is_some())
     }
 }
 
 impl<'a, T: IntoIterator<Item = &'a ColorPoint> + Copy> GroupFind<'a, ColorPoint> for T {
     fn all_appear(self, timeout: impl Seconds) -> bool {
         appear_with_screenshot(timeout, |shot| {
-            self.into_iter().all(|x| shot.find_color_point(x).is_some())
+            self.into_iter().all(|x| x.find_on(shot).is_some())
         })
     }
 
     fn any_appear(self, timeout: impl Seconds) -> bool {
         appear_with_screenshot(timeout, |shot| {
-            self.into_iter().any(|x| shot.find_color_point(x).is_some())
+            self.into_iter().any(|x| x.find_on(shot).is_some())
         })
     }
 }
@@ -66,29 +74,25 @@ impl<'a, T: IntoIterator<Item = &'a ColorPoint> + Copy> GroupFind<'a, ColorPoint
 impl<'a, T: IntoIterator<Item = &'a ColorPointGroup>> GroupFindOnce<'a, ColorPointGroup> for T {
     fn all_exist(self) -> bool {
         let shot = take_screenshot();
-        self.into_iter()
-            .all(|x| shot.find_color_point_group(x).is_some())
+        self.into_iter().all(|x| x.find_on(&shot).is_some())
     }
 
     fn any_exist(self) -> bool {
         let shot = take_screenshot();
-        self.into_iter()
-            .any(|x| shot.find_color_point_group(x).is_some())
+        self.into_iter().any(|x| x.find_on(&shot).is_some())
     }
 }
 
 impl<'a, T: IntoIterator<Item = &'a ColorPointGroup> + Copy> GroupFind<'a, ColorPointGroup> for T {
     fn all_appear(self, timeout: impl Seconds) -> bool {
         appear_with_screenshot(timeout, |shot| {
-            self.into_iter()
-                .all(|x| shot.find_color_point_group(x).is_some())
+            self.into_iter().all(|x| x.find_on(shot).is_some())
         })
     }
 
     fn any_appear(self, timeout: impl Seconds) -> bool {
         appear_with_screenshot(timeout, |shot| {
-            self.into_iter()
-                .any(|x| shot.find_color_point_group(x).is_some())
+            self.into_iter().any(|x| x.find_on(shot).is_some())
         })
     }
 }
@@ -96,14 +100,12 @@ impl<'a, T: IntoIterator<Item = &'a ColorPointGroup> + Copy> GroupFind<'a, Color
 impl<'a, T: IntoIterator<Item = &'a ColorPointGroupIn>> GroupFindOnce<'a, ColorPointGroupIn> for T {
     fn all_exist(self) -> bool {
         let shot = take_screenshot();
-        self.into_iter()
-            .all(|x| shot.find_color_point_group_in(x).is_some())
+        self.into_iter().all(|x| x.find_on(&shot).is_some())
     }
 
     fn any_exist(self) -> bool {
         let shot = take_screenshot();
-        self.into_iter()
-            .any(|x| shot.find_color_point_group_in(x).is_some())
+        self.into_iter().any(|x| x.find_on(&shot).is_some())
     }
 }
 
@@ -112,15 +114,13 @@ impl<'a, T: IntoIterator<Item = &'a ColorPointGroupIn> + Copy> GroupFind<'a, Col
 {
     fn all_appear(self, timeout: impl Seconds) -> bool {
         appear_with_screenshot(timeout, |shot| {
-            self.into_iter()
-                .all(|x| shot.find_color_point_group_in(x).is_some())
+            self.into_iter().all(|x| x.find_on(shot).is_some())
         })
     }
 
     fn any_appear(self, timeout: impl Seconds) -> bool {
         appear_with_screenshot(timeout, |shot| {
-            self.into_iter()
-                .any(|x| shot.find_color_point_group_in(x).is_some())
+            self.into_iter().any(|x| x.find_on(shot).is_some())
         })
     }
 }
@@ -128,25 +128,25 @@ impl<'a, T: IntoIterator<Item = &'a ColorPointGroupIn> + Copy> GroupFind<'a, Col
 impl<'a, T: IntoIterator<Item = &'a ImageIn>> GroupFindOnce<'a, ImageIn> for T {
     fn all_exist(self) -> bool {
         let shot = take_screenshot();
-        self.into_iter().all(|x| shot.find_image_in(x).is_some())
+        self.into_iter().all(|x| x.find_on(&shot).is_some())
     }
 
     fn any_exist(self) -> bool {
         let shot = take_screenshot();
-        self.into_iter().any(|x| shot.find_image_in(x).is_some())
+        self.into_iter().any(|x| x.find_on(&shot).is_some())
     }
 }
 
 impl<'a, T: IntoIterator<Item = &'a ImageIn> + Copy> GroupFind<'a, ImageIn> for T {
     fn all_appear(self, timeout: impl Seconds) -> bool {
         appear_with_screenshot(timeout, |shot| {
-            self.into_iter().all(|x| shot.find_image_in(x).is_some())
+            self.into_iter().all(|x| x.find_on(shot).is_some())
         })
     }
 
     fn any_appear(self, timeout: impl Seconds) -> bool {
         appear_with_screenshot(timeout, |shot| {
-            self.into_iter().any(|x| shot.find_image_in(x).is_some())
+            self.into_iter().any(|x| x.find_on(shot).is_some())
         })
     }
 }
@@ -156,14 +156,14 @@ impl<'a, T: IntoIterator<Item = &'a DiskImageIn>> GroupFindOnce<'a, DiskImageIn>
         let shot = take_screenshot();
         self.into_iter()
             .map(|x| ImageIn::from(x.clone()))
-            .all(|x| shot.find_image_in(&x).is_some())
+            .all(|x| x.find_on(&shot).is_some())
     }
 
     fn any_exist(self) -> bool {
         let shot = take_screenshot();
         self.into_iter()
             .map(|x| ImageIn::from(x.clone()))
-            .any(|x| shot.find_image_in(&x).is_some())
+            .any(|x| x.find_on(&shot).is_some())
     }
 }
 
@@ -171,14 +171,14 @@ impl<'a, T: IntoIterator<Item = &'a DiskImageIn> + Copy> GroupFind<'a, DiskImage
     fn all_appear(self, timeout: impl Seconds) -> bool {
         let img: Vec<_> = self.into_iter().map(|x| ImageIn::from(x.clone())).collect();
         appear_with_screenshot(timeout, |shot| {
-            img.iter().all(|x| shot.find_image_in(x).is_some())
+            img.iter().all(|x| x.find_on(shot).is_some())
         })
     }
 
     fn any_appear(self, timeout: impl Seconds) -> bool {
         let img: Vec<_> = self.into_iter().map(|x| ImageIn::from(x.clone())).collect();
         appear_with_screenshot(timeout, |shot| {
-            img.iter().any(|x| shot.find_image_in(x).is_some())
+            img.iter().any(|x| x.find_on(shot).is_some())
         })
     }
 }
@@ -186,25 +186,25 @@ impl<'a, T: IntoIterator<Item = &'a DiskImageIn> + Copy> GroupFind<'a, DiskImage
 impl<'a, T: IntoIterator<Item = &'a NodeSelector>> GroupFindOnce<'a, NodeSelector> for T {
     fn all_exist(self) -> bool {
         let shot = take_nodeshot();
-        self.into_iter().all(|x| shot.find_selector(x).is_some())
+        self.into_iter().all(|x| x.find_on(&shot).is_some())
     }
 
     fn any_exist(self) -> bool {
         let shot = take_nodeshot();
-        self.into_iter().any(|x| shot.find_selector(x).is_some())
+        self.into_iter().any(|x| x.find_on(&shot).is_some())
     }
 }
 
 impl<'a, T: IntoIterator<Item = &'a NodeSelector> + Copy> GroupFind<'a, NodeSelector> for T {
     fn all_appear(self, timeout: impl Seconds) -> bool {
         appear_with_nodeshot(timeout, |shot| {
-            self.into_iter().all(|x| shot.find_selector(x).is_some())
+            self.into_iter().all(|x| x.find_on(shot).is_some())
         })
     }
 
     fn any_appear(self, timeout: impl Seconds) -> bool {
         appear_with_nodeshot(timeout, |shot| {
-            self.into_iter().any(|x| shot.find_selector(x).is_some())
+            self.into_iter().any(|x| x.find_on(shot).is_some())
         })
     }
 }
@@ -316,7 +316,7 @@ fn appear_with_screenshot(timeout: impl Seconds, f: impl Fn(&Screenshot) -> bool
         if start.elapsed() > timeout {
             return false;
         }
-        let shot = take_screenshot();
+        let shot = searched();
         if f(&shot) {
             return true;
         }
@@ -339,14 +339,296 @@ fn appear_with_nodeshot(timeout: impl Seconds, f: impl Fn(&Nodeshot) -> bool) ->
     }
 }
 
+// what a single evaluation against a shot found, outlined when the overlay
+// is in debug mode
+fn traced<T>(label: &str, region: Rect, out: Option<T>, hit: impl FnOnce(&T) -> Rect) -> Option<T> {
+    if overlay::is_debug() {
+        match &out {
+            Some(x) => overlay::debug_hit(label, hit(x)),
+            // empty means whole screen, outlining that tells nothing
+            None if region.width > 0 && region.height > 0 => overlay::debug_miss(label, region),
+            None => {}
+        }
+    }
+    out
+}
+
+// bounding box of color points, moved so the first one is at point
+fn bounds(group: &[ColorPoint], at: &Point) -> Rect {
+    let left = group.iter().map(|x| x.x).min().unwrap_or(0);
+    let top = group.iter().map(|x| x.y).min().unwrap_or(0);
+    let right = group.iter().map(|x| x.x).max().unwrap_or(0);
+    let bottom = group.iter().map(|x| x.y).max().unwrap_or(0);
+    let (dx, dy) = group
+        .first()
+        .map_or((0, 0), |x| (at.x - x.x as i32, at.y - x.y as i32));
+    Rect {
+        left: left as i32 + dx,
+        top: top as i32 + dy,
+        width: right - left + 1,
+        height: bottom - top + 1,
+    }
+}
+
+// a box to see where a match given as a point is
+fn around(point: &Point) -> Rect {
+    Rect {
+        left: point.x - 16,
+        top: point.y - 16,
+        width: 32,
+        height: 32,
+    }
+}
+
+impl ColorPoint {
+    fn find_on(&self, shot: &Screenshot) -> Option<Point> {
+        let region = Rect {
+            left: self.x as _,
+            top: self.y as _,
+            width: 1,
+            height: 1,
+        };
+        traced("color", region.clone(), shot.find_color_point(self), |_| {
+            region
+        })
+    }
+}
+
+impl ColorPointGroup {
+    fn find_on(&self, shot: &Screenshot) -> Option<Point> {
+        let at = self.group.first().map(Point::from).unwrap_or_default();
+        traced(
+            "colors",
+            bounds(&self.group, &at),
+            shot.find_color_point_group(self),
+            |x| bounds(&self.group, x),
+        )
+    }
+}
+
+impl ColorPointGroupIn {
+    fn find_on(&self, shot: &Screenshot) -> Option<Point> {
+        traced(
+            "colors",
+            (&self.region).into(),
+            shot.find_color_point_group_in(self),
+            |x| bounds(&self.group, x),
+        )
+    }
+}
+
+impl ImageIn {
+    fn find_on(&self, shot: &Screenshot) -> Option<Point> {
+        traced(
+            "image",
+            (&self.region).into(),
+            shot.find_image_in(self),
+            |x| Rect {
+                left: x.x,
+                top: x.y,
+                width: self.img.width(),
+                height: self.img.height(),
+            },
+        )
+    }
+}
+
+impl NodeSelector {
+    fn find_on(&self, shot: &Nodeshot) -> Option<ANode> {
+        traced("node", Rect::default(), shot.find_selector(self), |x| {
+            x.region.clone()
+        })
+    }
+}
+
+impl DetectIn {
+    fn find_on(&self, shot: &Screenshot) -> Option<Detection> {
+        traced(
+            &self.class,
+            (&self.region).into(),
+            shot.find_detect_in(self),
+            |x| x.rect.clone(),
+        )
+    }
+}
+
+// how a find target shows in the journal
+trait Target {
+    fn args(&self) -> String;
+    // cropped into the journal
+    fn region(&self) -> Option<Region>;
+}
+
+// finds poll, so an ocr, detect or classify error is a miss rather than a
+// panic. each distinct error is logged once, an appear would repeat it every
+// frame
+pub(crate) fn miss_on_error<T>(kind: &str, result: anyhow::Result<T>) -> Option<T> {
+    static LOGGED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+    match result {
+        Ok(x) => Some(x),
+        Err(e) => {
+            let message = format!("{kind}: {e:#}");
+            if LOGGED.lock().unwrap().insert(message.clone()) {
+                log::warn!("{message}");
+            }
+            None
+        }
+    }
+}
+
+fn journaled<T: Outcome>(
+    kind: &str,
+    target: &impl Target,
+    timeout: Option<Duration>,
+    run: impl FnOnce() -> T,
+) -> T {
+    let args = || match timeout {
+        Some(timeout) => format!("{} within {:.1}s", target.args(), timeout.as_secs_f32()),
+        None => target.args(),
+    };
+    let evidence = match target.region() {
+        Some(region) => Evidence::Seen(region),
+        None => Evidence::None,
+    };
+    journal::step(kind, args, evidence, run)
+}
+
+// a frame a find searches, what the journal crops its step from
+fn searched() -> Screenshot {
+    let shot = take_screenshot();
+    journal::seen(&shot);
+    shot
+}
+
+fn show(region: &Region) -> String {
+    if region.width == 0 || region.height == 0 {
+        return "screen".into();
+    }
+    format!(
+        "({}, {}) {}x{}",
+        region.left, region.top, region.width, region.height
+    )
+}
+
+// rect clamped to screen coordinates
+fn to_region(rect: &Rect) -> Region {
+    let left = rect.left.max(0);
+    let top = rect.top.max(0);
+    Region {
+        left: left as _,
+        top: top as _,
+        width: (rect.right() - left).max(0) as _,
+        height: (rect.bottom() - top).max(0) as _,
+    }
+}
+
+impl Target for ColorPoint {
+    fn args(&self) -> String {
+        format!(
+            "color #{:02x}{:02x}{:02x} at ({}, {})",
+            self.red, self.green, self.blue, self.x, self.y
+        )
+    }
+    fn region(&self) -> Option<Region> {
+        Some(to_region(&around(&self.into())))
+    }
+}
+
+impl Target for ColorPointGroup {
+    fn args(&self) -> String {
+        let at = self.group.first().map(Point::from).unwrap_or_default();
+        format!("{} colors at ({}, {})", self.group.len(), at.x, at.y)
+    }
+    fn region(&self) -> Option<Region> {
+        let at = self.group.first().map(Point::from).unwrap_or_default();
+        Some(to_region(&bounds(&self.group, &at)))
+    }
+}
+
+impl Target for ColorPointGroupIn {
+    fn args(&self) -> String {
+        format!("{} colors in {}", self.group.len(), show(&self.region))
+    }
+    fn region(&self) -> Option<Region> {
+        Some(self.region.clone())
+    }
+}
+
+impl Target for ImageIn {
+    fn args(&self) -> String {
+        format!(
+            "image {}x{} in {}",
+            self.img.width(),
+            self.img.height(),
+            show(&self.region)
+        )
+    }
+    fn region(&self) -> Option<Region> {
+        Some(self.region.clone())
+    }
+}
+
+impl Target for DiskImageIn {
+    fn args(&self) -> String {
+        format!("image {} in {}", self.img.display(), show(&self.region))
+    }
+    fn region(&self) -> Option<Region> {
+        Some(self.region.clone())
+    }
+}
+
+impl Target for NodeSelector {
+    fn args(&self) -> String {
+        "node".into()
+    }
+    fn region(&self) -> Option<Region> {
+        None
+    }
+}
+
+impl Target for TextIn {
+    fn args(&self) -> String {
+        format!("text {:?} in {}", self.pattern, show(&self.region))
+    }
+    fn region(&self) -> Option<Region> {
+        Some(self.region.clone())
+    }
+}
+
+impl Target for DetectIn {
+    fn args(&self) -> String {
+        format!(
+            "object {} >= {:.2} in {}",
+            self.class,
+            self.min_score,
+            show(&self.region)
+        )
+    }
+    fn region(&self) -> Option<Region> {
+        Some(self.region.clone())
+    }
+}
+
+impl Target for SceneIs {
+    fn args(&self) -> String {
+        format!("scene {} >= {:.2}", self.label, self.min_score)
+    }
+    fn region(&self) -> Option<Region> {
+        None
+    }
+}
+
 impl Find for ColorPoint {
     type FindOut = Point;
 
     fn find(&self) -> Option<Self::FindOut> {
-        take_screenshot().find_color_point(self)
+        journaled("find", self, None, || self.find_on(&searched()))
     }
     fn appear(&self, timeout: impl Seconds) -> bool {
-        appear_with_screenshot(timeout, |shot| shot.find_color_point(self).is_some())
+        let timeout = timeout.into_duration();
+        journaled("appear", self, Some(timeout), || {
+            appear_with_screenshot(timeout, |shot| self.find_on(shot).is_some())
+        })
     }
 }
 
@@ -354,10 +636,13 @@ impl Find for ColorPointGroup {
     type FindOut = Point;
 
     fn find(&self) -> Option<Self::FindOut> {
-        take_screenshot().find_color_point_group(self)
+        journaled("find", self, None, || self.find_on(&searched()))
     }
     fn appear(&self, timeout: impl Seconds) -> bool {
-        appear_with_screenshot(timeout, |shot| shot.find_color_point_group(self).is_some())
+        let timeout = timeout.into_duration();
+        journaled("appear", self, Some(timeout), || {
+            appear_with_screenshot(timeout, |shot| self.find_on(shot).is_some())
+        })
     }
 }
 
@@ -365,11 +650,12 @@ impl Find for ColorPointGroupIn {
     type FindOut = Point;
 
     fn find(&self) -> Option<Self::FindOut> {
-        take_screenshot().find_color_point_group_in(self)
+        journaled("find", self, None, || self.find_on(&searched()))
     }
     fn appear(&self, timeout: impl Seconds) -> bool {
-        appear_with_screenshot(timeout, |shot| {
-            shot.find_color_point_group_in(self).is_some()
+        let timeout = timeout.into_duration();
+        journaled("appear", self, Some(timeout), || {
+            appear_with_screenshot(timeout, |shot| self.find_on(shot).is_some())
         })
     }
 }
@@ -378,10 +664,13 @@ impl Find for ImageIn {
     type FindOut = Point;
 
     fn find(&self) -> Option<Self::FindOut> {
-        take_screenshot().find_image_in(self)
+        journaled("find", self, None, || self.find_on(&searched()))
     }
     fn appear(&self, timeout: impl Seconds) -> bool {
-        appear_with_screenshot(timeout, |shot| shot.find_image_in(self).is_some())
+        let timeout = timeout.into_duration();
+        journaled("appear", self, Some(timeout), || {
+            appear_with_screenshot(timeout, |shot| self.find_on(shot).is_some())
+        })
     }
 }
 
@@ -389,10 +678,15 @@ impl Find for DiskImageIn {
     type FindOut = Point;
 
     fn find(&self) -> Option<Self::FindOut> {
-        ImageIn::from(self.clone()).find()
+        let img = ImageIn::from(self.clone());
+        journaled("find", self, None, || img.find_on(&searched()))
     }
     fn appear(&self, timeout: impl Seconds) -> bool {
-        ImageIn::from(self.clone()).appear(timeout)
+        let img = ImageIn::from(self.clone());
+        let timeout = timeout.into_duration();
+        journaled("appear", self, Some(timeout), || {
+            appear_with_screenshot(timeout, |shot| img.find_on(shot).is_some())
+        })
     }
 }
 
@@ -418,11 +712,38 @@ impl Find for NodeSelector {
     type FindOut = ANode;
 
     fn find(&self) -> Option<Self::FindOut> {
-        take_nodeshot().find_selector(self)
+        journaled("find", self, None, || self.find_on(&take_nodeshot()))
     }
 
     fn appear(&self, timeout: impl Seconds) -> bool {
-        appear_with_nodeshot(timeout, |shot| shot.find_selector(self).is_some())
+        let timeout = timeout.into_duration();
+        journaled("appear", self, Some(timeout), || {
+            appear_with_nodeshot(timeout, |shot| self.find_on(shot).is_some())
+        })
+    }
+}
+
+impl TextIn {
+    fn find_now(&self) -> Option<Point> {
+        let out = take_nodeshot()
+            .find_text_in(self)
+            .or_else(|| searched().find_text_in(self));
+        traced("text", (&self.region).into(), out, around)
+    }
+}
+
+impl Find for TextIn {
+    type FindOut = Point;
+
+    fn find(&self) -> Option<Self::FindOut> {
+        journaled("find", self, None, || self.find_now())
+    }
+
+    fn appear(&self, timeout: impl Seconds) -> bool {
+        let timeout = timeout.into_duration();
+        journaled("appear", self, Some(timeout), || {
+            wait_for(|| self.find_now(), timeout, DEFAULT_WAIT_INTERVAL).is_some()
+        })
     }
 }
 
@@ -484,3 +805,33 @@ impl<T> Find for ConditionOption<T> {
         wait_for_true(|| self.evaluate().is_some(), timeout, DEFAULT_WAIT_INTERVAL)
     }
 }
+
+impl Find for DetectIn {
+    type FindOut = Detection;
+
+    fn find(&self) -> Option<Self::FindOut> {
+        journaled("find", self, None, || self.find_on(&searched()))
+    }
+
+    fn appear(&self, timeout: impl Seconds) -> bool {
+        let timeout = timeout.into_duration();
+        journaled("appear", self, Some(timeout), || {
+            appear_with_screenshot(timeout, |shot| self.find_on(shot).is_some())
+        })
+    }
+}
+
+impl Find for SceneIs {
+    type FindOut = Label;
+
+    fn find(&self) -> Option<Self::FindOut> {
+        journaled("find", self, None, || searched().find_scene_is(self))
+    }
+
+    fn appear(&self, timeout: impl Seconds) -> bool {
+        let timeout = timeout.into_duration();
+        journaled("appear", self, Some(timeout), || {
+            appear_with_screenshot(timeout, |shot| shot.find_scene_is(self).is_some())
+        })
+    }
+}
@@ -0,0 +1,594 @@
+// record of what a run did, for failures on devices we can't watch
+//
+// each find, appear and click is a step with its arguments, result and
+// duration, optionally with a crop of its region: for a find the last frame
+// it searched, for a click the screen before the tap.
+// steps are appended to steps.jsonl in a directory per run so a crash keeps
+// what came before, and finish renders them to a single report.html with the
+// crops inlined, small enough to pull off the device and open anywhere
+
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use image::RgbImage;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{data_dir, take_screenshot},
+    classify::Label,
+    color::{Point, Region},
+    detect::Detection,
+    node::ANode,
+    screenshot::Screenshot,
+    ui::base64,
+};
+
+static RUN: Mutex<Option<Run>> = Mutex::new(None);
+
+// region of the step running on this thread, and its crop of the last
+// frame passed to seen
+type Seen = Option<(Region, Option<RgbImage>)>;
+
+thread_local! {
+    static SEEN: RefCell<Seen> = const { RefCell::new(None) };
+}
+
+pub struct Journal {
+    dir: PathBuf,
+    keep: usize,
+    screenshots: bool,
+}
+
+impl Journal {
+    // runs go to <data_dir>/journal/<start time>
+    pub fn new() -> Self {
+        Self::at(data_dir().join("journal"))
+    }
+
+    pub fn at(dir: impl Into<PathBuf>) -> Self {
+        Journal {
+            dir: dir.into(),
+            keep: 10,
+            screenshots: true,
+        }
+    }
+
+    // runs kept including the new one, older ones are deleted on start
+    pub fn keep(mut self, keep: usize) -> Self {
+        self.keep = keep.max(1);
+        self
+    }
+
+    // crop of the searched region with each step
+    pub fn screenshots(mut self, screenshots: bool) -> Self {
+        self.screenshots = screenshots;
+        self
+    }
+}
+
+impl Default for Journal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Run {
+    dir: PathBuf,
+    steps: File,
+    screenshots: bool,
+    start: Instant,
+    count: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Step {
+    // since start of the run
+    at: u64,
+    kind: String,
+    args: String,
+    result: String,
+    ok: bool,
+    millis: u64,
+    // png next to steps.jsonl
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shot: Option<String>,
+}
+
+// begin recording, finishing a run still open. the directory of the run
+pub fn start(journal: Journal) -> anyhow::Result<PathBuf> {
+    finish();
+    std::fs::create_dir_all(&journal.dir)?;
+    let name = run_name(SystemTime::now());
+    let mut dir = journal.dir.join(&name);
+    let mut n = 1;
+    while dir.exists() {
+        n += 1;
+        dir = journal.dir.join(format!("{name}-{n}"));
+    }
+    std::fs::create_dir(&dir)?;
+    rotate(&journal.dir, journal.keep)?;
+    let steps = File::create(dir.join("steps.jsonl"))?;
+    let run = Run {
+        dir: dir.clone(),
+        steps,
+        screenshots: journal.screenshots,
+        start: Instant::now(),
+        count: 0,
+    };
+    // one started by another thread meanwhile
+    if let Some(open) = lock().replace(run) {
+        close(open);
+    }
+    Ok(dir)
+}
+
+pub fn is_recording() -> bool {
+    lock().is_some()
+}
+
+// a panic while it was held mustn't take the journal down with it, stop
+// finishes the run through here
+fn lock() -> MutexGuard<'static, Option<Run>> {
+    RUN.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// free text in the timeline, d! lands here as well
+pub fn note(text: &str) {
+    write(
+        Step {
+            at: 0,
+            kind: "note".into(),
+            args: text.into(),
+            result: String::new(),
+            ok: true,
+            millis: 0,
+            shot: None,
+        },
+        None,
+    );
+}
+
+// stop recording and write the report, its path. called when the guest stops
+pub fn finish() -> Option<PathBuf> {
+    let run = lock().take()?;
+    close(run)
+}
+
+fn close(run: Run) -> Option<PathBuf> {
+    drop(run.steps);
+    match report(&run.dir) {
+        Ok(path) => Some(path),
+        Err(e) => {
+            log::warn!("journal report {}: {e:#}", run.dir.display());
+            None
+        }
+    }
+}
+
+// render report.html of a run directory
+pub fn report(dir: &Path) -> anyhow::Result<PathBuf> {
+    let file = File::open(dir.join("steps.jsonl")).context("open steps")?;
+    let mut rows = String::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        // a run killed mid write leaves half a line at the end
+        let Ok(step) = serde_json::from_str::<Step>(&line) else {
+            continue;
+        };
+        let img = match &step.shot {
+            Some(shot) => match std::fs::read(dir.join(shot)) {
+                Ok(png) => format!("<img src=\"data:image/png;base64,{}\">", base64(&png)),
+                Err(_) => String::new(),
+            },
+            None => String::new(),
+        };
+        rows.push_str(&format!(
+            "<tr class=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{img}</td></tr>\n",
+            if step.ok { "ok" } else { "miss" },
+            clock(step.at),
+            escape(&step.kind),
+            escape(&step.args),
+            escape(&step.result),
+            if step.kind == "note" {
+                String::new()
+            } else {
+                format!("{}ms", step.millis)
+            },
+        ));
+    }
+    let title = dir
+        .file_name()
+        .map(|x| x.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let html = REPORT
+        .replace("{title}", &escape(&title))
+        .replace("{rows}", &rows);
+    let path = dir.join("report.html");
+    std::fs::write(&path, html)?;
+    Ok(path)
+}
+
+// what a step returned, and whether that counts as found
+pub(crate) trait Outcome {
+    fn ok(&self) -> bool {
+        true
+    }
+    fn describe(&self) -> String;
+}
+
+impl Outcome for () {
+    fn describe(&self) -> String {
+        String::new()
+    }
+}
+
+impl Outcome for bool {
+    fn ok(&self) -> bool {
+        *self
+    }
+    fn describe(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl<T: Outcome> Outcome for Option<T> {
+    fn ok(&self) -> bool {
+        self.is_some()
+    }
+    fn describe(&self) -> String {
+        match self {
+            Some(x) => x.describe(),
+            None => "not found".into(),
+        }
+    }
+}
+
+impl Outcome for Point {
+    fn describe(&self) -> String {
+        format!("({}, {})", self.x, self.y)
+    }
+}
+
+impl Outcome for ANode {
+    fn describe(&self) -> String {
+        let r = &self.region;
+        format!(
+            "{} {:?} at ({}, {}) {}x{}",
+            self.class, self.text, r.left, r.top, r.width, r.height
+        )
+    }
+}
+
+impl Outcome for Detection {
+    fn describe(&self) -> String {
+        format!(
+            "{} {:.2} at {}",
+            self.class,
+            self.score,
+            self.center().describe()
+        )
+    }
+}
+
+impl Outcome for Label {
+    fn describe(&self) -> String {
+        format!("{} {:.2}", self.name, self.score)
+    }
+}
+
+// where the crop of a step comes from
+pub(crate) enum Evidence {
+    None,
+    // the screen before run, for actions that change it
+    Before(Region),
+    // the last frame run passed to seen, what a find searched. the screen
+    // after run when it saw none, as a node find does
+    Seen(Region),
+}
+
+// run f as a step when recording, args are only formatted then
+pub(crate) fn step<T: Outcome>(
+    kind: &str,
+    args: impl FnOnce() -> String,
+    evidence: Evidence,
+    run: impl FnOnce() -> T,
+) -> T {
+    let Some(screenshots) = lock().as_ref().map(|x| x.screenshots) else {
+        return run();
+    };
+    let evidence = if screenshots {
+        evidence
+    } else {
+        Evidence::None
+    };
+    let before = match &evidence {
+        Evidence::Before(region) => crop(&take_screenshot(), region),
+        _ => None,
+    };
+    let watch = match &evidence {
+        Evidence::Seen(region) => Some(Watch::new(region.clone())),
+        _ => None,
+    };
+    let start = Instant::now();
+    let out = run();
+    let millis = start.elapsed().as_millis() as u64;
+    let img = match (&evidence, watch) {
+        (Evidence::Seen(region), Some(watch)) => {
+            watch.take().or_else(|| crop(&take_screenshot(), region))
+        }
+        _ => before,
+    };
+    write(
+        Step {
+            at: 0,
+            kind: kind.into(),
+            args: args(),
+            result: out.describe(),
+            ok: out.ok(),
+            millis,
+            shot: None,
+        },
+        img,
+    );
+    out
+}
+
+// a frame a find is about to search, cropped when a step on this thread
+// wants it
+pub(crate) fn seen(shot: &Screenshot) {
+    SEEN.with_borrow_mut(|x| {
+        if let Some((region, img)) = x {
+            *img = crop(shot, region);
+        }
+    });
+}
+
+// SEEN for the length of a step, put back also when run unwinds
+struct Watch {
+    // what to put back, none once it was
+    outer: Option<Seen>,
+}
+
+impl Watch {
+    fn new(region: Region) -> Self {
+        Watch {
+            outer: Some(SEEN.replace(Some((region, None)))),
+        }
+    }
+
+    fn take(mut self) -> Option<RgbImage> {
+        SEEN.replace(self.outer.take().flatten())
+            .and_then(|(_, img)| img)
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        if let Some(outer) = self.outer.take() {
+            SEEN.set(outer);
+        }
+    }
+}
+
+// fills in time and saves the crop, the lock is held for the line only
+fn write(mut step: Step, img: Option<RgbImage>) {
+    let (dir, name) = {
+        let mut guard = lock();
+        let Some(run) = guard.as_mut() else {
+            return;
+        };
+        run.count += 1;
+        // when it began, steps are written once done
+        step.at = (run.start.elapsed().as_millis() as u64).saturating_sub(step.millis);
+        step.shot = img.is_some().then(|| format!("{:06}.png", run.count));
+        let mut line = serde_json::to_string(&step).unwrap();
+        line.push('\n');
+        if let Err(e) = run.steps.write_all(line.as_bytes()) {
+            log::warn!("journal {}: {e}", run.dir.display());
+        }
+        (run.dir.clone(), step.shot)
+    };
+    // report leaves out a png that never made it
+    if let (Some(img), Some(name)) = (img, name) {
+        if let Err(e) = img.save(dir.join(name)) {
+            log::warn!("journal crop: {e}");
+        }
+    }
+}
+
+fn crop(shot: &Screenshot, region: &Region) -> Option<RgbImage> {
+    shot.crop_rgb(&clip(region, &shot.region())?)
+}
+
+// part of region on screen, none if nothing is
+fn clip(region: &Region, screen: &Region) -> Option<Region> {
+    let left = region.left.max(screen.left);
+    let top = region.top.max(screen.top);
+    let right = region.right().min(screen.right());
+    let bottom = region.bottom().min(screen.bottom());
+    (left < right && top < bottom).then(|| (left, top, right - left, bottom - top).into())
+}
+
+// oldest first by name, which is the start time
+fn rotate(dir: &Path, keep: usize) -> anyhow::Result<()> {
+    let mut runs: Vec<_> = std::fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .filter(|x| x.path().is_dir())
+        .map(|x| x.path())
+        .collect();
+    runs.sort();
+    let remove = runs.len().saturating_sub(keep);
+    for run in &runs[..remove] {
+        std::fs::remove_dir_all(run)?;
+    }
+    Ok(())
+}
+
+// utc, sorts by time
+fn run_name(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (year, month, day) = civil(secs / 86400);
+    let secs = secs % 86400;
+    format!(
+        "{year:04}{month:02}{day:02}-{:02}{:02}{:02}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+// days since 1970-01-01 to year, month, day
+fn civil(days: u64) -> (u64, u64, u64) {
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z / 146097;
+    let doe = z % 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as u64;
+    (year, month, day)
+}
+
+// m:ss.mmm since start
+fn clock(millis: u64) -> String {
+    let d = Duration::from_millis(millis);
+    format!(
+        "{}:{:02}.{:03}",
+        d.as_secs() / 60,
+        d.as_secs() % 60,
+        d.subsec_millis()
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const REPORT: &str = r#"<!doctype html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body { font-family: system-ui, sans-serif; margin: 16px; }
+table { border-collapse: collapse; width: 100%; }
+td { border-bottom: 1px solid #ddd; padding: 4px 8px; vertical-align: top; }
+tr.miss td:nth-child(5) { color: #b00; }
+tr.ok td:nth-child(5) { color: #070; }
+td:nth-child(4) { font-family: monospace; word-break: break-all; }
+img { max-width: 320px; max-height: 240px; }
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+<table>
+<tr><th>time</th><th>step</th><th>args</th><th>result</th><th>took</th><th>screen</th></tr>
+{rows}</table>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    // tests that record share RUN
+    static RECORDING: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn run_names_sort_by_time() {
+        let at = |secs| run_name(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(at(0), "19700101-000000");
+        assert_eq!(at(951_782_400), "20000229-000000");
+        assert_eq!(at(1_700_000_000), "20231114-221320");
+        assert!(at(1_700_000_000) < at(1_700_000_001));
+    }
+
+    #[test]
+    fn clip_to_screen() {
+        let screen = (0, 0, 100, 50).into();
+        let clipped = clip(&(90, 40, 64, 64).into(), &screen).unwrap();
+        assert_eq!(
+            (clipped.left, clipped.top, clipped.width, clipped.height),
+            (90, 40, 10, 10)
+        );
+        assert!(clip(&(100, 0, 10, 10).into(), &screen).is_none());
+    }
+
+    #[test]
+    fn crop_of_searched_frame() {
+        let _recording = RECORDING.lock().unwrap_or_else(PoisonError::into_inner);
+        let dir = TempDir::new("journal-seen");
+        let run = start(Journal::at(dir.to_path_buf())).unwrap();
+        let shot = Screenshot {
+            width: 4,
+            height: 4,
+            data: Vec::leak(vec![255; 4 * 4 * 4]),
+            timestamp: 0,
+        };
+        step(
+            "find",
+            String::new,
+            Evidence::Seen((1, 1, 2, 8).into()),
+            || seen(&shot),
+        );
+        finish();
+        let img = image::open(run.join("000001.png")).unwrap();
+        assert_eq!((img.width(), img.height()), (2, 3));
+    }
+
+    #[test]
+    fn record_and_report() {
+        let _recording = RECORDING.lock().unwrap_or_else(PoisonError::into_inner);
+        let dir = TempDir::new("journal");
+        let journal = || Journal::at(dir.to_path_buf()).keep(2).screenshots(false);
+
+        let first = start(journal()).unwrap();
+        assert!(is_recording());
+        assert_eq!(
+            step(
+                "find",
+                || "image <a>".into(),
+                Evidence::None,
+                || Some(Point { x: 1, y: 2 })
+            ),
+            Some(Point { x: 1, y: 2 })
+        );
+        step("appear", || "text".into(), Evidence::None, || false);
+        note("done");
+        let report = finish().unwrap();
+        assert!(!is_recording());
+        // not recording, runs without a trace
+        assert!(step("find", || unreachable!(), Evidence::None, || true));
+
+        let html = std::fs::read_to_string(report).unwrap();
+        assert!(html.contains("<td>image &lt;a&gt;</td><td>(1, 2)</td>"));
+        assert!(html.contains("<tr class=\"miss\">"));
+        assert!(html.contains("<td>done</td>"));
+
+        // starting again finishes the open run
+        let open = start(journal()).unwrap();
+        start(journal()).unwrap();
+        assert!(open.join("report.html").exists());
+        finish();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        assert!(!first.exists());
+    }
+}
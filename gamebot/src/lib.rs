@@ -1,9 +1,27 @@
 #![feature(trait_upcasting)]
+// derive output names ::gamebot paths, this lets it work inside the crate too
+extern crate self as gamebot;
+
 pub mod activity;
 pub mod api;
+pub mod app;
+pub mod classify;
 pub mod color;
+pub mod detect;
+pub mod device;
+pub mod display;
 pub mod find;
+pub mod journal;
+pub mod model;
 pub mod node;
+pub mod notification;
+pub mod ocr;
+pub mod overlay;
+pub mod schedule;
 pub mod screenshot;
+pub mod shell;
+#[cfg(test)]
+mod testing;
 pub mod ui;
 pub use log;
+pub use serde;
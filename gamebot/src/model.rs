@@ -0,0 +1,378 @@
+// process wide cache of loaded networks, keyed by name
+//
+// a name resolves to a model given to register, or else to a file in the
+// guest asset dir: <name>.onnx for ort, <name>.param + <name>.bin for ncnn
+
+mod inference;
+mod preprocess;
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex},
+    time::Instant,
+};
+
+use anyhow::bail;
+
+pub use inference::{array_from_mat, mat_from_array, Inference};
+pub use preprocess::{Layout, PixelSource, Prepared, Preprocess, Transform};
+
+use crate::api::asset_dir;
+
+// model or charset file, on disk or embedded in guest via include_bytes!
+#[derive(Debug, Clone)]
+pub enum Source {
+    Path(PathBuf),
+    Bytes(&'static [u8]),
+}
+
+impl Source {
+    pub fn read(&self) -> std::io::Result<Cow<'static, [u8]>> {
+        match self {
+            Source::Path(path) => std::fs::read(path).map(Cow::Owned),
+            Source::Bytes(bytes) => Ok(Cow::Borrowed(bytes)),
+        }
+    }
+
+    fn size(&self) -> u64 {
+        match self {
+            Source::Path(path) => std::fs::metadata(path).map_or(0, |x| x.len()),
+            Source::Bytes(bytes) => bytes.len() as u64,
+        }
+    }
+}
+
+impl From<&str> for Source {
+    fn from(value: &str) -> Self {
+        Source::Path(value.into())
+    }
+}
+
+impl From<PathBuf> for Source {
+    fn from(value: PathBuf) -> Self {
+        Source::Path(value)
+    }
+}
+
+impl From<&'static [u8]> for Source {
+    fn from(value: &'static [u8]) -> Self {
+        Source::Bytes(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Ort,
+    Ncnn,
+}
+
+#[derive(Debug, Clone)]
+pub enum Model {
+    Onnx(Source),
+    Ncnn { param: Source, bin: Source },
+}
+
+impl Model {
+    // backend from extension, .param and .bin are expected side by side
+    pub fn from_path(path: impl AsRef<Path>) -> anyhow::Result<Model> {
+        let path = path.as_ref();
+        match path.extension().and_then(|x| x.to_str()) {
+            Some("onnx") => Ok(Model::Onnx(path.to_owned().into())),
+            Some("param") | Some("bin") => Ok(Model::Ncnn {
+                param: path.with_extension("param").into(),
+                bin: path.with_extension("bin").into(),
+            }),
+            _ => bail!("unknown model format {}", path.display()),
+        }
+    }
+
+    pub fn backend(&self) -> Backend {
+        match self {
+            Model::Onnx(_) => Backend::Ort,
+            Model::Ncnn { .. } => Backend::Ncnn,
+        }
+    }
+
+    // file size, good enough as memory estimation
+    fn size(&self) -> u64 {
+        match self {
+            Model::Onnx(source) => source.size(),
+            Model::Ncnn { param, bin } => param.size() + bin.size(),
+        }
+    }
+}
+
+// load without going through the registry
+pub fn load(model: &Model) -> anyhow::Result<Arc<dyn Inference>> {
+    match model {
+        Model::Onnx(source) => {
+            let builder = ort::Session::builder()?
+                .with_optimization_level(ort::GraphOptimizationLevel::Level3)?
+                .with_intra_threads(std::thread::available_parallelism()?.into())?;
+            let session = match source {
+                Source::Path(path) => builder.commit_from_file(path)?,
+                Source::Bytes(bytes) => builder.commit_from_memory(bytes)?,
+            };
+            Ok(Arc::new(session))
+        }
+        Model::Ncnn { param, bin } => {
+            let mut net = ncnn::Net::new();
+            net.set_option(&ncnn::Option::new());
+            match param {
+                Source::Path(path) => net.load_param(&path.to_string_lossy())?,
+                Source::Bytes(bytes) => net.load_param_memory(bytes)?,
+            }
+            match bin {
+                Source::Path(path) => net.load_model(&path.to_string_lossy())?,
+                Source::Bytes(bytes) => net.load_model_memory(aligned(bytes))?,
+            }
+            Ok(Arc::new(Mutex::new(net)))
+        }
+    }
+}
+
+// ncnn reads weights in place as f32, include_bytes! data is only byte
+// aligned. copied once per embedded model, a reload after eviction reuses it
+fn aligned(bytes: &'static [u8]) -> &'static [u8] {
+    if bytes.as_ptr().align_offset(4) == 0 {
+        return bytes;
+    }
+    // address and length of the original
+    type Key = (usize, usize);
+    static COPIES: LazyLock<Mutex<HashMap<Key, &'static [u8]>>> = LazyLock::new(Default::default);
+    let mut copies = COPIES.lock().unwrap();
+    copies
+        .entry((bytes.as_ptr() as usize, bytes.len()))
+        .or_insert_with(|| {
+            let words: &'static [u32] = bytes
+                .chunks(4)
+                .map(|x| {
+                    let mut word = [0; 4];
+                    word[..x.len()].copy_from_slice(x);
+                    u32::from_ne_bytes(word)
+                })
+                .collect::<Vec<_>>()
+                .leak();
+            // same bytes in the same order, the words are never freed
+            unsafe { std::slice::from_raw_parts(words.as_ptr().cast(), bytes.len()) }
+        })
+}
+
+// evict idle models before loading when MemAvailable is below this
+const LOW_MEMORY: u64 = 256 << 20;
+
+#[derive(Default)]
+struct Slot {
+    runner: Option<Arc<dyn Inference>>,
+    size: u64,
+    last_used: Option<Instant>,
+}
+
+#[derive(Default)]
+struct Entry {
+    model: Option<Model>,
+    // loading holds only this lock, so different models load in parallel
+    slot: Arc<Mutex<Slot>>,
+}
+
+#[derive(Default)]
+struct Registry {
+    entry: HashMap<String, Entry>,
+    budget: Option<u64>,
+    // picked when both <name>.onnx and <name>.param exist
+    backend: Option<Backend>,
+}
+
+static REGISTRY: LazyLock<Mutex<Registry>> = LazyLock::new(Default::default);
+
+// use model for name instead of looking in asset dir, drop the loaded one if any
+pub fn register(name: &str, model: Model) {
+    REGISTRY.lock().unwrap().entry.insert(
+        name.to_owned(),
+        Entry {
+            model: Some(model),
+            ..Default::default()
+        },
+    );
+}
+
+// load on first use, later calls return the cached one
+pub fn get(name: &str) -> anyhow::Result<Arc<dyn Inference>> {
+    let (model, slot) = {
+        let mut registry = REGISTRY.lock().unwrap();
+        let entry = registry.entry.entry(name.to_owned()).or_default();
+        (entry.model.clone(), entry.slot.clone())
+    };
+
+    let mut slot = slot.lock().unwrap();
+    slot.last_used = Some(Instant::now());
+    if let Some(runner) = &slot.runner {
+        return Ok(runner.clone());
+    }
+
+    let model = match model {
+        Some(model) => model,
+        None => find_in_asset(name)?,
+    };
+    let size = model.size();
+    trim(size);
+
+    let runner = load(&model)?;
+    slot.runner = Some(runner.clone());
+    slot.size = size;
+    Ok(runner)
+}
+
+// load ahead of time so the first inference doesn't pay for it
+pub fn warmup<'a>(names: impl IntoIterator<Item = &'a str>) -> anyhow::Result<()> {
+    for name in names {
+        get(name)?;
+    }
+    Ok(())
+}
+
+pub fn is_loaded(name: &str) -> bool {
+    let Some(slot) = slot(name) else {
+        return false;
+    };
+    let loaded = slot.lock().unwrap().runner.is_some();
+    loaded
+}
+
+// memory is freed once every Arc returned by get is dropped
+pub fn unload(name: &str) {
+    if let Some(slot) = slot(name) {
+        slot.lock().unwrap().runner = None;
+    }
+}
+
+pub fn unload_all() {
+    for slot in slots() {
+        slot.lock().unwrap().runner = None;
+    }
+}
+
+// prefer backend for models found in asset dir, models already loaded are kept
+pub fn set_backend(backend: Option<Backend>) {
+    REGISTRY.lock().unwrap().backend = backend;
+}
+
+// keep total size of loaded models under budget by unloading least recently used
+pub fn set_budget(bytes: Option<u64>) {
+    REGISTRY.lock().unwrap().budget = bytes;
+    trim(0);
+}
+
+// unload least recently used models if memory is low, call it between heavy steps
+pub fn trim_memory() {
+    trim(0);
+}
+
+// how an engine holds its network, a named one is looked up on every use
+// so the registry is free to unload it in between
+#[derive(Clone)]
+pub enum ModelRef {
+    Owned(Arc<dyn Inference>),
+    Named(String),
+}
+
+impl ModelRef {
+    pub fn get(&self) -> anyhow::Result<Arc<dyn Inference>> {
+        match self {
+            ModelRef::Owned(runner) => Ok(runner.clone()),
+            ModelRef::Named(name) => get(name),
+        }
+    }
+}
+
+impl From<Arc<dyn Inference>> for ModelRef {
+    fn from(value: Arc<dyn Inference>) -> Self {
+        ModelRef::Owned(value)
+    }
+}
+
+impl From<&str> for ModelRef {
+    fn from(value: &str) -> Self {
+        ModelRef::Named(value.to_owned())
+    }
+}
+
+fn slot(name: &str) -> Option<Arc<Mutex<Slot>>> {
+    let registry = REGISTRY.lock().unwrap();
+    registry.entry.get(name).map(|entry| entry.slot.clone())
+}
+
+fn slots() -> Vec<Arc<Mutex<Slot>>> {
+    let registry = REGISTRY.lock().unwrap();
+    registry
+        .entry
+        .values()
+        .map(|entry| entry.slot.clone())
+        .collect()
+}
+
+// make room for a model of incoming bytes
+fn trim(incoming: u64) {
+    let budget = REGISTRY.lock().unwrap().budget;
+
+    // slot being loaded or in get is locked, skip it rather than wait
+    let mut loaded = vec![];
+    for slot in slots() {
+        let Ok(guard) = slot.try_lock() else {
+            continue;
+        };
+        if guard.runner.is_some() {
+            loaded.push((guard.last_used, guard.size, slot.clone()));
+        }
+    }
+    loaded.sort_by_key(|(last_used, _, _)| *last_used);
+
+    let total: u64 = loaded.iter().map(|(_, size, _)| size).sum();
+    let over_budget = budget.map_or(0, |budget| (total + incoming).saturating_sub(budget));
+    let over_memory = mem_available().map_or(0, |available| {
+        (LOW_MEMORY + incoming).saturating_sub(available)
+    });
+
+    let mut need = over_budget.max(over_memory);
+    for (_, size, slot) in loaded {
+        if need == 0 {
+            break;
+        }
+        let Ok(mut guard) = slot.try_lock() else {
+            continue;
+        };
+        guard.runner = None;
+        need = need.saturating_sub(size);
+    }
+}
+
+fn find_in_asset(name: &str) -> anyhow::Result<Model> {
+    let dir = asset_dir();
+    let onnx = dir.join(format!("{name}.onnx"));
+    let param = dir.join(format!("{name}.param"));
+    let found: Vec<_> = [onnx, param]
+        .into_iter()
+        .filter(|x| x.exists())
+        .map(Model::from_path)
+        .collect::<anyhow::Result<_>>()?;
+
+    let backend = REGISTRY.lock().unwrap().backend;
+    match found.iter().find(|x| Some(x.backend()) == backend) {
+        Some(model) => Ok(model.clone()),
+        None => match found.into_iter().next() {
+            Some(model) => Ok(model),
+            None => bail!("model {name} not found in {}", dir.display()),
+        },
+    }
+}
+
+fn mem_available() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo
+        .lines()
+        .find(|line| line.starts_with("MemAvailable:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb << 10)
+}
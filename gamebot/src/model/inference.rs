@@ -0,0 +1,173 @@
+use std::{borrow::Cow, sync::Mutex};
+
+use anyhow::{bail, Context};
+use ndarray::{ArrayD, ArrayViewD, IxDyn};
+
+// a loaded network, tensors are f32 and addressed by blob name
+pub trait Inference: Send + Sync {
+    fn input_names(&self) -> Vec<String>;
+
+    fn output_names(&self) -> Vec<String>;
+
+    // results follow the order of outputs and are in standard layout
+    fn run(
+        &self,
+        inputs: &[(&str, ArrayViewD<f32>)],
+        outputs: &[&str],
+    ) -> anyhow::Result<Vec<ArrayD<f32>>>;
+
+    // first input to first output, enough for most single head models
+    fn run_one(&self, input: ArrayViewD<f32>) -> anyhow::Result<ArrayD<f32>> {
+        let (inputs, outputs) = (self.input_names(), self.output_names());
+        let (Some(input_name), Some(output_name)) = (inputs.first(), outputs.first()) else {
+            bail!("model has no input or output");
+        };
+        let mut ans = self.run(&[(input_name, input)], &[output_name])?;
+        Ok(ans.remove(0))
+    }
+}
+
+impl Inference for ort::Session {
+    fn input_names(&self) -> Vec<String> {
+        self.inputs.iter().map(|x| x.name.clone()).collect()
+    }
+
+    fn output_names(&self) -> Vec<String> {
+        self.outputs.iter().map(|x| x.name.clone()).collect()
+    }
+
+    fn run(
+        &self,
+        inputs: &[(&str, ArrayViewD<f32>)],
+        outputs: &[&str],
+    ) -> anyhow::Result<Vec<ArrayD<f32>>> {
+        let mut values = vec![];
+        for (name, tensor) in inputs {
+            let value = ort::DynValue::try_from(tensor.view())?;
+            values.push((Cow::Borrowed(*name), ort::SessionInputValue::from(value)));
+        }
+        let result = ort::Session::run(self, values)?;
+        outputs
+            .iter()
+            .map(|&name| {
+                let value = result
+                    .get(name)
+                    .with_context(|| format!("no output named {name}"))?;
+                Ok(value
+                    .try_extract_tensor::<f32>()?
+                    .as_standard_layout()
+                    .into_owned())
+            })
+            .collect()
+    }
+}
+
+// ncnn::Net is not Sync, extraction is serialized
+impl Inference for Mutex<ncnn::Net> {
+    fn input_names(&self) -> Vec<String> {
+        self.lock().unwrap().input_names()
+    }
+
+    fn output_names(&self) -> Vec<String> {
+        self.lock().unwrap().output_names()
+    }
+
+    fn run(
+        &self,
+        inputs: &[(&str, ArrayViewD<f32>)],
+        outputs: &[&str],
+    ) -> anyhow::Result<Vec<ArrayD<f32>>> {
+        let mats = inputs
+            .iter()
+            .map(|(_, tensor)| mat_from_array(tensor.view()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut net = self.lock().unwrap();
+        let mut ans = vec![];
+        // extract consumes the extractor, intermediate blobs are recomputed per output
+        for name in outputs {
+            let mut ex = net.create_extractor();
+            for ((input, _), mat) in inputs.iter().zip(&mats) {
+                ex.input(input, mat)?;
+            }
+            let mut out = ncnn::Mat::new();
+            ex.extract(name, &mut out)?;
+            ans.push(array_from_mat(&out));
+        }
+        Ok(ans)
+    }
+}
+
+// ncnn has no batch axis, a leading 1 is dropped
+pub fn mat_from_array(tensor: ArrayViewD<f32>) -> anyhow::Result<ncnn::Mat> {
+    let mut shape = tensor.shape();
+    if shape.len() == 4 {
+        if shape[0] != 1 {
+            bail!("ncnn only runs batch of 1, got {shape:?}");
+        }
+        shape = &shape[1..];
+    }
+    let mat = match *shape {
+        [w] => ncnn::Mat::new_1d(w as _, None),
+        [h, w] => ncnn::Mat::new_2d(w as _, h as _, None),
+        [c, h, w] => ncnn::Mat::new_3d(w as _, h as _, c as _, None),
+        _ => bail!("unsupported tensor shape for ncnn {:?}", tensor.shape()),
+    };
+
+    // channels are aligned to cstep
+    let plane = (mat.w() * mat.h() * mat.d()) as usize;
+    let cstep = mat.cstep();
+    let ptr = mat.data() as *mut f32;
+    for (i, &v) in tensor.iter().enumerate() {
+        unsafe { *ptr.add(i / plane * cstep + i % plane) = v };
+    }
+    Ok(mat)
+}
+
+// batch axis is added back so output shape matches the onnx export
+pub fn array_from_mat(mat: &ncnn::Mat) -> ArrayD<f32> {
+    let (c, d, h, w) = (
+        mat.c() as usize,
+        mat.d() as usize,
+        mat.h() as usize,
+        mat.w() as usize,
+    );
+    let shape = match mat.dims() {
+        1 => vec![1, w],
+        2 => vec![1, h, w],
+        3 => vec![1, c, h, w],
+        _ => vec![1, c, d, h, w],
+    };
+
+    let plane = w * h * d;
+    let cstep = mat.cstep();
+    let ptr = mat.data() as *const f32;
+    let mut data = Vec::with_capacity(c * plane);
+    for i in 0..c {
+        data.extend_from_slice(unsafe { std::slice::from_raw_parts(ptr.add(i * cstep), plane) });
+    }
+    ArrayD::from_shape_vec(IxDyn(&shape), data).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::Array;
+
+    use super::*;
+
+    #[test]
+    fn mat_round_trip() {
+        // 3x5 plane is not a multiple of ncnn channel alignment
+        let tensor =
+            Array::from_shape_fn((1, 2, 3, 5), |(_, c, y, x)| (c * 100 + y * 10 + x) as f32);
+        let mat = mat_from_array(tensor.view().into_dyn()).unwrap();
+        assert_eq!((mat.c(), mat.h(), mat.w()), (2, 3, 5));
+        assert_eq!(array_from_mat(&mat), tensor.into_dyn());
+    }
+
+    #[test]
+    fn mat_rejects_batch() {
+        let tensor = ndarray::Array4::<f32>::zeros((2, 1, 4, 4));
+        assert!(mat_from_array(tensor.view().into_dyn()).is_err());
+    }
+}
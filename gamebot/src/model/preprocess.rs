@@ -0,0 +1,470 @@
+// image to tensor in one pass: crop / resize / pad only change the mapping from
+// output pixel to source pixel, which is sampled bilinearly like cv2.INTER_LINEAR
+
+use image::{RgbImage, RgbaImage};
+use ndarray::Array4;
+
+use super::mat_from_array;
+use crate::{
+    color::{Rect, Region},
+    screenshot::Screenshot,
+};
+
+pub trait PixelSource {
+    fn dimensions(&self) -> (u32, u32);
+
+    fn rgb(&self, x: u32, y: u32) -> [u8; 3];
+}
+
+impl PixelSource for RgbImage {
+    fn dimensions(&self) -> (u32, u32) {
+        RgbImage::dimensions(self)
+    }
+
+    fn rgb(&self, x: u32, y: u32) -> [u8; 3] {
+        self.get_pixel(x, y).0
+    }
+}
+
+impl PixelSource for RgbaImage {
+    fn dimensions(&self) -> (u32, u32) {
+        RgbaImage::dimensions(self)
+    }
+
+    fn rgb(&self, x: u32, y: u32) -> [u8; 3] {
+        let [r, g, b, _] = self.get_pixel(x, y).0;
+        [r, g, b]
+    }
+}
+
+// read straight from the shared buffer, no RgbaImage copy
+impl PixelSource for Screenshot {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn rgb(&self, x: u32, y: u32) -> [u8; 3] {
+        let i = ((y * self.width + x) * 4) as usize;
+        [self.data[i], self.data[i + 1], self.data[i + 2]]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    Nchw,
+    Nhwc,
+}
+
+#[derive(Debug, Clone)]
+enum Step {
+    Crop(Region),
+    Resize(u32, u32),
+    ResizeHeight(u32),
+    Fit(u32, u32),
+    Pad(u32, u32, u32, u32),
+    PadToMultiple(u32),
+    // pad around the center
+    PadTo(u32, u32),
+}
+
+#[derive(Debug, Clone)]
+pub struct Preprocess {
+    steps: Vec<Step>,
+    fill: [u8; 3],
+    grayscale: bool,
+    bgr: bool,
+    mean: Vec<f32>,
+    std: Vec<f32>,
+    layout: Layout,
+}
+
+impl Default for Preprocess {
+    fn default() -> Self {
+        Preprocess {
+            steps: vec![],
+            fill: [0, 0, 0],
+            grayscale: false,
+            bgr: false,
+            mean: vec![0.0],
+            std: vec![1.0],
+            layout: Layout::Nchw,
+        }
+    }
+}
+
+// maps tensor coordinate back to source image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub scale_x: f32,
+    pub scale_y: f32,
+    pub offset_x: f32,
+    pub offset_y: f32,
+}
+
+impl Transform {
+    pub fn to_source(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (
+            x * self.scale_x + self.offset_x,
+            y * self.scale_y + self.offset_y,
+        )
+    }
+
+    pub fn rect_to_source(&self, rect: &Rect) -> Rect {
+        let (l, t) = self.to_source((rect.left as f32, rect.top as f32));
+        let (r, b) = self.to_source((
+            rect.left as f32 + rect.width as f32,
+            rect.top as f32 + rect.height as f32,
+        ));
+        Rect {
+            left: l.round() as i32,
+            top: t.round() as i32,
+            width: (r - l).round().max(0.0) as u32,
+            height: (b - t).round().max(0.0) as u32,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Prepared {
+    pub tensor: Array4<f32>,
+    pub transform: Transform,
+}
+
+impl Prepared {
+    // ncnn wants nchw, batch axis is dropped
+    pub fn mat(&self) -> anyhow::Result<ncnn::Mat> {
+        mat_from_array(self.tensor.view().into_dyn())
+    }
+}
+
+impl Preprocess {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // region of the current image, usually the first step
+    pub fn crop(mut self, region: impl Into<Region>) -> Self {
+        self.steps.push(Step::Crop(region.into()));
+        self
+    }
+
+    // stretch to exactly width x height
+    pub fn resize(mut self, width: u32, height: u32) -> Self {
+        self.steps.push(Step::Resize(width, height));
+        self
+    }
+
+    // keep aspect ratio, width follows
+    pub fn resize_height(mut self, height: u32) -> Self {
+        self.steps.push(Step::ResizeHeight(height));
+        self
+    }
+
+    // keep aspect ratio, largest size within width x height
+    pub fn fit(mut self, width: u32, height: u32) -> Self {
+        self.steps.push(Step::Fit(width, height));
+        self
+    }
+
+    // fit then pad to width x height with content centered, as yolo does
+    pub fn letterbox(mut self, width: u32, height: u32) -> Self {
+        self.steps.push(Step::Fit(width, height));
+        self.steps.push(Step::PadTo(width, height));
+        self
+    }
+
+    pub fn pad(mut self, left: u32, top: u32, right: u32, bottom: u32) -> Self {
+        self.steps.push(Step::Pad(left, top, right, bottom));
+        self
+    }
+
+    // pad right and bottom so both sides are multiple of n
+    pub fn pad_to_multiple(mut self, n: u32) -> Self {
+        self.steps.push(Step::PadToMultiple(n));
+        self
+    }
+
+    // pixel value of padding, before normalize
+    pub fn fill(mut self, rgb: [u8; 3]) -> Self {
+        self.fill = rgb;
+        self
+    }
+
+    // one channel luma as PIL convert("L")
+    pub fn grayscale(mut self) -> Self {
+        self.grayscale = true;
+        self
+    }
+
+    // channel order for models trained with opencv imread
+    pub fn bgr(mut self) -> Self {
+        self.bgr = true;
+        self
+    }
+
+    // (x / 255 - mean) / std, a single value applies to all channels
+    pub fn normalize(mut self, mean: &[f32], std: &[f32]) -> Self {
+        self.mean = mean.to_vec();
+        self.std = std.to_vec();
+        self
+    }
+
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    pub fn run(&self, src: &impl PixelSource) -> Prepared {
+        self.run_plan(src, self.plan(src.dimensions(), None))
+    }
+
+    // as if crop(region) were the first step, transform maps back to src
+    pub fn run_in(&self, src: &impl PixelSource, region: &Region) -> Prepared {
+        self.run_plan(src, self.plan(src.dimensions(), Some(region)))
+    }
+
+    fn run_plan(&self, src: &impl PixelSource, plan: Plan) -> Prepared {
+        let (w, h) = (plan.width as usize, plan.height as usize);
+        let c = if self.grayscale { 1 } else { 3 };
+        let shape = match self.layout {
+            Layout::Nchw => (1, c, h, w),
+            Layout::Nhwc => (1, h, w, c),
+        };
+        let mut tensor = Array4::zeros(shape);
+
+        for y in 0..h {
+            for x in 0..w {
+                let rgb = plan
+                    .sample_at(x as f32, y as f32)
+                    .map_or(self.fill.map(f32::from), |(sx, sy)| bilinear(src, sx, sy));
+                let [r, g, b] = rgb;
+                let pixel = if self.grayscale {
+                    [0.299 * r + 0.587 * g + 0.114 * b; 3]
+                } else if self.bgr {
+                    [b, g, r]
+                } else {
+                    rgb
+                };
+                for (i, &v) in pixel.iter().take(c).enumerate() {
+                    let v =
+                        (v / 255.0 - self.mean[i % self.mean.len()]) / self.std[i % self.std.len()];
+                    match self.layout {
+                        Layout::Nchw => tensor[(0, i, y, x)] = v,
+                        Layout::Nhwc => tensor[(0, y, x, i)] = v,
+                    }
+                }
+            }
+        }
+
+        Prepared {
+            tensor,
+            transform: Transform {
+                scale_x: plan.scale_x,
+                scale_y: plan.scale_y,
+                offset_x: plan.offset_x,
+                offset_y: plan.offset_y,
+            },
+        }
+    }
+
+    fn plan(&self, (width, height): (u32, u32), region: Option<&Region>) -> Plan {
+        let mut plan = Plan {
+            width,
+            height,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            offset_x: 0.0,
+            offset_y: 0.0,
+            valid: (0.0, 0.0, width as f32, height as f32),
+        };
+        if let Some(region) = region {
+            plan.crop(region);
+        }
+        for step in &self.steps {
+            match *step {
+                Step::Crop(ref region) => plan.crop(region),
+                Step::Resize(w, h) => plan.resize(w, h),
+                Step::ResizeHeight(h) => {
+                    let w = (plan.width as f32 * h as f32 / plan.height.max(1) as f32).round();
+                    plan.resize((w as u32).max(1), h)
+                }
+                Step::Fit(w, h) => {
+                    let s = (w as f32 / plan.width.max(1) as f32)
+                        .min(h as f32 / plan.height.max(1) as f32);
+                    let nw = (plan.width as f32 * s).round() as u32;
+                    let nh = (plan.height as f32 * s).round() as u32;
+                    plan.resize(nw.clamp(1, w), nh.clamp(1, h))
+                }
+                Step::Pad(l, t, r, b) => plan.pad(l, t, r, b),
+                Step::PadToMultiple(n) => {
+                    let n = n.max(1);
+                    let r = plan.width.div_ceil(n) * n - plan.width;
+                    let b = plan.height.div_ceil(n) * n - plan.height;
+                    plan.pad(0, 0, r, b)
+                }
+                Step::PadTo(w, h) => {
+                    let (dw, dh) = (w.saturating_sub(plan.width), h.saturating_sub(plan.height));
+                    plan.pad(dw / 2, dh / 2, dw - dw / 2, dh - dh / 2)
+                }
+            }
+        }
+        plan
+    }
+}
+
+// source edge coordinate = output edge coordinate * scale + offset
+struct Plan {
+    width: u32,
+    height: u32,
+    scale_x: f32,
+    scale_y: f32,
+    offset_x: f32,
+    offset_y: f32,
+    // part of output backed by source, in output coordinate
+    valid: (f32, f32, f32, f32),
+}
+
+impl Plan {
+    fn crop(&mut self, region: &Region) {
+        let (l, t) = (region.left as f32, region.top as f32);
+        self.offset_x += l * self.scale_x;
+        self.offset_y += t * self.scale_y;
+        let (x0, y0, x1, y1) = self.valid;
+        self.valid = (
+            (x0 - l).max(0.0),
+            (y0 - t).max(0.0),
+            (x1 - l).min(region.width as f32),
+            (y1 - t).min(region.height as f32),
+        );
+        self.width = region.width;
+        self.height = region.height;
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        let fx = self.width as f32 / width.max(1) as f32;
+        let fy = self.height as f32 / height.max(1) as f32;
+        self.scale_x *= fx;
+        self.scale_y *= fy;
+        let (x0, y0, x1, y1) = self.valid;
+        self.valid = (x0 / fx, y0 / fy, x1 / fx, y1 / fy);
+        self.width = width;
+        self.height = height;
+    }
+
+    fn pad(&mut self, left: u32, top: u32, right: u32, bottom: u32) {
+        let (l, t) = (left as f32, top as f32);
+        self.offset_x -= l * self.scale_x;
+        self.offset_y -= t * self.scale_y;
+        let (x0, y0, x1, y1) = self.valid;
+        self.valid = (x0 + l, y0 + t, x1 + l, y1 + t);
+        self.width += left + right;
+        self.height += top + bottom;
+    }
+
+    // source pixel coordinate for center of output pixel, None for padding
+    fn sample_at(&self, x: f32, y: f32) -> Option<(f32, f32)> {
+        let (cx, cy) = (x + 0.5, y + 0.5);
+        let (x0, y0, x1, y1) = self.valid;
+        if cx < x0 || cx > x1 || cy < y0 || cy > y1 {
+            return None;
+        }
+        Some((
+            cx * self.scale_x + self.offset_x - 0.5,
+            cy * self.scale_y + self.offset_y - 0.5,
+        ))
+    }
+}
+
+fn bilinear(src: &impl PixelSource, x: f32, y: f32) -> [f32; 3] {
+    let (w, h) = src.dimensions();
+    let x = x.clamp(0.0, w as f32 - 1.0);
+    let y = y.clamp(0.0, h as f32 - 1.0);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+    let (dx, dy) = (x - x0 as f32, y - y0 as f32);
+    let p = |x, y| src.rgb(x, y).map(f32::from);
+    let (a, b, c, d) = (p(x0, y0), p(x1, y0), p(x0, y1), p(x1, y1));
+    std::array::from_fn(|i| {
+        let top = a[i] + (b[i] - a[i]) * dx;
+        let bottom = c[i] + (d[i] - c[i]) * dx;
+        top + (bottom - top) * dy
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgb;
+
+    use super::*;
+
+    fn gradient(w: u32, h: u32) -> RgbImage {
+        RgbImage::from_fn(w, h, |x, y| Rgb([x as u8, y as u8, 7]))
+    }
+
+    #[test]
+    fn identity() {
+        let img = gradient(4, 3);
+        let out = Preprocess::new().run(&img);
+        assert_eq!(out.tensor.dim(), (1, 3, 3, 4));
+        assert_eq!(out.tensor[(0, 0, 2, 3)], 3.0 / 255.0);
+        assert_eq!(out.tensor[(0, 1, 2, 3)], 2.0 / 255.0);
+    }
+
+    #[test]
+    fn letterbox_maps_back() {
+        let img = gradient(200, 100);
+        let out = Preprocess::new().letterbox(64, 64).fill([114; 3]).run(&img);
+        assert_eq!(out.tensor.dim(), (1, 3, 64, 64));
+        // content is 64x32 centered, 16 rows of padding on top
+        assert_eq!(out.tensor[(0, 2, 0, 0)], 114.0 / 255.0);
+        assert_eq!(out.tensor[(0, 2, 16, 0)], 7.0 / 255.0);
+        let (x, y) = out.transform.to_source((32.0, 32.0));
+        assert!((x - 100.0).abs() < 1e-3 && (y - 50.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn pad_to_multiple() {
+        let img = gradient(33, 20);
+        let out = Preprocess::new().pad_to_multiple(32).run(&img);
+        assert_eq!(out.tensor.dim(), (1, 3, 32, 64));
+        assert_eq!(out.tensor[(0, 0, 0, 32)], 32.0 / 255.0);
+        assert_eq!(out.tensor[(0, 0, 0, 33)], 0.0);
+        assert_eq!(out.tensor[(0, 2, 20, 0)], 0.0);
+    }
+
+    #[test]
+    fn crop_resize_gray_nhwc() {
+        let img = gradient(100, 100);
+        let out = Preprocess::new()
+            .crop((10, 20, 40, 20))
+            .resize_height(10)
+            .grayscale()
+            .normalize(&[0.5], &[0.5])
+            .layout(Layout::Nhwc)
+            .run(&img);
+        assert_eq!(out.tensor.dim(), (1, 10, 20, 1));
+        let (x, y) = out.transform.to_source((0.0, 0.0));
+        assert_eq!((x, y), (10.0, 20.0));
+        // output pixel (0, 0) covers source pixel 10..=11 x 20..=21, sampled in between
+        let luma = 0.299 * 10.5 + 0.587 * 20.5 + 0.114 * 7.0;
+        assert!((out.tensor[(0, 0, 0, 0)] - (luma / 255.0 - 0.5) / 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn screenshot_matches_image() {
+        let img = gradient(30, 20);
+        let rgba = image::DynamicImage::ImageRgb8(img.clone()).into_rgba8();
+        let shot = Screenshot {
+            width: 30,
+            height: 20,
+            data: Box::leak(rgba.into_raw().into_boxed_slice()),
+            timestamp: 0,
+        };
+        let pre = Preprocess::new().crop((5, 5, 20, 10)).resize(8, 4);
+        let expect = pre.run(&img).tensor;
+        assert_eq!(pre.run(&shot).tensor, expect);
+        let region = Region::from((5, 5, 20, 10));
+        let pre = Preprocess::new().resize(8, 4);
+        assert_eq!(pre.run_in(&shot, &region).tensor, expect);
+    }
+}
@@ -0,0 +1,87 @@
+// notifications in the status bar, e.g. stamina full or an event starting
+//
+// listing works with shell, dismissing other apps' notifications needs root
+
+use std::time::{Duration, Instant};
+
+use anyhow::bail;
+use serde::Deserialize;
+
+use crate::api::{proxy, wait, Seconds};
+
+const POLL: Duration = Duration::from_millis(500);
+
+// the host leaves out fields at their default
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Notification {
+    pub key: String,
+    pub package: String,
+    pub id: i32,
+    pub tag: Option<String>,
+    pub title: String,
+    pub text: String,
+    // millis since epoch
+    pub post_time: i64,
+    pub ongoing: bool,
+}
+
+impl Notification {
+    // sends its content intent, what tapping it in the shade does
+    pub fn click(&self) -> anyhow::Result<()> {
+        action("clickNotification", &self.key)
+    }
+
+    pub fn dismiss(&self) -> anyhow::Result<()> {
+        action("dismissNotification", &self.key)
+    }
+}
+
+fn action(method: &str, key: &str) -> anyhow::Result<()> {
+    let msg = proxy().notification_action(method, key);
+    if !msg.is_empty() {
+        bail!("{method} {key}: {msg}");
+    }
+    Ok(())
+}
+
+pub fn notification_list() -> Vec<Notification> {
+    proxy().notification_list()
+}
+
+// one already shown counts as well, compare post_time to wait for a new one
+pub fn wait_notification(
+    filter: impl Fn(&Notification) -> bool,
+    timeout: impl Seconds,
+) -> Option<Notification> {
+    let start = Instant::now();
+    let timeout = timeout.into_duration();
+    loop {
+        if let Some(x) = notification_list().into_iter().find(&filter) {
+            return Some(x);
+        }
+        let left = timeout.saturating_sub(start.elapsed());
+        if left.is_zero() {
+            return None;
+        }
+        wait(left.min(POLL));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_host_json() {
+        let x: Vec<Notification> = serde_json::from_str(
+            r#"[{"key":"0|com.game|7|null|10123","package":"com.game","id":7,
+                "title":"Stamina","text":"stamina is full","post_time":1700000000000}]"#,
+        )
+        .unwrap();
+        assert_eq!(x[0].package, "com.game");
+        assert_eq!(x[0].tag, None);
+        assert_eq!(x[0].post_time, 1_700_000_000_000);
+        assert!(!x[0].ongoing);
+    }
+}
@@ -0,0 +1,194 @@
+mod ctc;
+mod db;
+mod ddddocr;
+mod paddle;
+
+use std::sync::{Arc, RwLock};
+
+use anyhow::Context;
+use image::RgbImage;
+use regex::Regex;
+
+pub use ctc::{Charset, CharsetError, CtcChar, CtcDecoder, CtcOutput};
+pub use db::{crop_quad, sort_lines, DbPostprocess, Quad};
+pub use ddddocr::Ddddocr;
+pub use paddle::PaddleOcr;
+
+use crate::{
+    api::take_screenshot,
+    color::{Point, Rect, Region},
+    find::miss_on_error,
+    node::Nodeshot,
+    screenshot::Screenshot,
+};
+
+#[derive(Debug, Clone)]
+pub struct TextBox {
+    pub text: String,
+    pub rect: Rect,
+    pub confidence: f32,
+}
+
+impl TextBox {
+    pub fn center(&self) -> Point {
+        self.rect.center()
+    }
+
+    pub fn click(&self) {
+        self.center().click()
+    }
+}
+
+pub trait Ocr: Send + Sync {
+    // rect in result is relative to img
+    fn recognize(&self, img: &RgbImage) -> anyhow::Result<Vec<TextBox>>;
+
+    // treat whole img as one line
+    fn recognize_line(&self, img: &RgbImage) -> anyhow::Result<TextBox>;
+}
+
+static OCR: RwLock<Option<Arc<dyn Ocr>>> = RwLock::new(None);
+
+pub fn set_ocr(engine: impl Ocr + 'static) {
+    *OCR.write().unwrap() = Some(Arc::new(engine));
+}
+
+fn engine() -> anyhow::Result<Arc<dyn Ocr>> {
+    OCR.read()
+        .unwrap()
+        .clone()
+        .context("ocr engine is not set, call ocr::set_ocr first")
+}
+
+pub fn ocr(region: impl Into<Region>) -> anyhow::Result<Vec<TextBox>> {
+    take_screenshot().ocr(&region.into())
+}
+
+pub fn ocr_line(region: impl Into<Region>) -> anyhow::Result<String> {
+    take_screenshot().ocr_line(&region.into())
+}
+
+// errors are from the engine, e.g. a missing model. a region off screen
+// has no text
+impl Screenshot {
+    pub fn ocr(&self, region: &Region) -> anyhow::Result<Vec<TextBox>> {
+        let Some(img) = self.crop_rgb(region) else {
+            return Ok(vec![]);
+        };
+        let mut ans = engine()?.recognize(&img)?;
+        for text in &mut ans {
+            text.rect.left += region.left as i32;
+            text.rect.top += region.top as i32;
+        }
+        Ok(ans)
+    }
+
+    pub fn ocr_line(&self, region: &Region) -> anyhow::Result<String> {
+        let Some(img) = self.crop_rgb(region) else {
+            return Ok(String::new());
+        };
+        Ok(engine()?.recognize_line(&img)?.text)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum TextPattern {
+    Exact(String),
+    Contains(String),
+    Regex(Regex),
+}
+
+impl TextPattern {
+    pub fn contains(text: impl ToString) -> Self {
+        TextPattern::Contains(text.to_string())
+    }
+
+    pub fn regex(re: &str) -> Result<Self, regex::Error> {
+        Ok(TextPattern::Regex(Regex::new(re)?))
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            TextPattern::Exact(x) => text == x,
+            TextPattern::Contains(x) => text.contains(x.as_str()),
+            TextPattern::Regex(x) => x.is_match(text),
+        }
+    }
+}
+
+impl From<&str> for TextPattern {
+    fn from(value: &str) -> Self {
+        TextPattern::Exact(value.to_owned())
+    }
+}
+
+impl From<String> for TextPattern {
+    fn from(value: String) -> Self {
+        TextPattern::Exact(value)
+    }
+}
+
+impl From<Regex> for TextPattern {
+    fn from(value: Regex) -> Self {
+        TextPattern::Regex(value)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TextIn {
+    pub pattern: TextPattern,
+    pub region: Region,
+    pub min_confidence: f32,
+}
+
+impl TextIn {
+    pub fn within(&self, region: impl Into<Region>) -> TextIn {
+        TextIn {
+            region: region.into(),
+            ..self.clone()
+        }
+    }
+
+    pub fn min_confidence(&self, min_confidence: f32) -> TextIn {
+        TextIn {
+            min_confidence,
+            ..self.clone()
+        }
+    }
+
+    // empty region means whole screen
+    fn region_in(&self, full: Region) -> Region {
+        if self.region.width == 0 || self.region.height == 0 {
+            full
+        } else {
+            self.region.clone()
+        }
+    }
+}
+
+impl Nodeshot {
+    // accessibility text is exact and free compared to ocr
+    pub fn find_text_in(&self, text: &TextIn) -> Option<Point> {
+        let region = match text.region.width == 0 || text.region.height == 0 {
+            true => None,
+            false => Some(Rect::from(&text.region)),
+        };
+        self.data
+            .iter()
+            .find(|node| {
+                !node.text.is_empty()
+                    && text.pattern.matches(&node.text)
+                    && region.as_ref().is_none_or(|r| r.contains(&node.region))
+            })
+            .map(|node| node.region.center())
+    }
+}
+
+impl Screenshot {
+    pub fn find_text_in(&self, text: &TextIn) -> Option<Point> {
+        miss_on_error("ocr", self.ocr(&text.region_in(self.region())))?
+            .into_iter()
+            .find(|x| x.confidence >= text.min_confidence && text.pattern.matches(&x.text))
+            .map(|x| x.center())
+    }
+}
@@ -0,0 +1,387 @@
+// CTC decoding of recognition output laid out as [time, class], class 0 is blank
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::model::Source;
+
+#[derive(Error, Debug)]
+pub enum CharsetError {
+    #[error("fail to read charset: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("charset is not utf-8: {0}")]
+    Utf8(#[from] std::str::Utf8Error),
+    #[error("charset is not a json string array: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("charset is empty")]
+    Empty,
+}
+
+// class index to text, index 0 is blank
+#[derive(Debug, Clone)]
+pub struct Charset {
+    chars: Vec<String>,
+}
+
+impl Charset {
+    // ppocr_keys_v1.txt: one char per line, space is appended as last class
+    pub fn from_paddle_keys(bytes: &[u8]) -> Result<Self, CharsetError> {
+        let text = std::str::from_utf8(bytes)?;
+        let mut chars = vec![String::new()];
+        chars.extend(text.lines().map(ToOwned::to_owned));
+        if chars.len() == 1 {
+            return Err(CharsetError::Empty);
+        }
+        chars.push(" ".into());
+        Ok(Charset { chars })
+    }
+
+    // ddddocr charset.json: string array with blank "" at 0
+    pub fn from_ddddocr_json(bytes: &[u8]) -> Result<Self, CharsetError> {
+        let chars: Vec<String> = serde_json::from_slice(bytes)?;
+        if chars.len() <= 1 {
+            return Err(CharsetError::Empty);
+        }
+        Ok(Charset { chars })
+    }
+
+    pub fn read_paddle_keys(source: &Source) -> Result<Self, CharsetError> {
+        Self::from_paddle_keys(&source.read()?)
+    }
+
+    pub fn read_ddddocr_json(source: &Source) -> Result<Self, CharsetError> {
+        Self::from_ddddocr_json(&source.read()?)
+    }
+
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.len() <= 1
+    }
+
+    pub fn get(&self, class: usize) -> Option<&str> {
+        self.chars.get(class).map(String::as_str)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CtcChar {
+    pub text: String,
+    pub prob: f32,
+    // first time step emitting this char
+    pub time: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CtcOutput {
+    pub text: String,
+    pub chars: Vec<CtcChar>,
+    // mean of char probabilities
+    pub confidence: f32,
+}
+
+impl CtcOutput {
+    fn new(chars: Vec<CtcChar>) -> Self {
+        let text = chars.iter().map(|x| x.text.as_str()).collect();
+        let confidence = if chars.is_empty() {
+            0.0
+        } else {
+            chars.iter().map(|x| x.prob).sum::<f32>() / chars.len() as f32
+        };
+        CtcOutput {
+            text,
+            chars,
+            confidence,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CtcDecoder {
+    charset: Charset,
+    allowed: Option<Vec<bool>>,
+    // 1 means greedy
+    pub beam_width: usize,
+}
+
+impl CtcDecoder {
+    pub fn new(charset: Charset) -> Self {
+        CtcDecoder {
+            charset,
+            allowed: None,
+            beam_width: 1,
+        }
+    }
+
+    pub fn charset(&self) -> &Charset {
+        &self.charset
+    }
+
+    // only emit classes whose text is one of these chars, e.g. "0123456789"
+    pub fn whitelist(mut self, chars: &str) -> Self {
+        let allowed = (0..self.charset.len())
+            .map(|i| {
+                let c = self.charset.get(i).unwrap_or_default();
+                i == 0 || (!c.is_empty() && c.chars().all(|c| chars.contains(c)))
+            })
+            .collect();
+        self.allowed = Some(allowed);
+        self
+    }
+
+    pub fn beam_width(mut self, beam_width: usize) -> Self {
+        self.beam_width = beam_width.max(1);
+        self
+    }
+
+    pub fn decode(&self, scores: &[f32], classes: usize) -> CtcOutput {
+        if self.beam_width <= 1 {
+            self.greedy(scores, classes)
+        } else {
+            self.beam_search(scores, classes, self.beam_width)
+        }
+    }
+
+    // best class per step, merge repeats then drop blank
+    pub fn greedy(&self, scores: &[f32], classes: usize) -> CtcOutput {
+        let mut chars: Vec<CtcChar> = vec![];
+        let mut prev = 0;
+        for (time, step) in scores.chunks_exact(classes).enumerate() {
+            let prob = self.probabilities(step);
+            let Some((class, &p)) = prob.iter().enumerate().max_by(|x, y| x.1.total_cmp(y.1))
+            else {
+                continue;
+            };
+            if class != 0 && class == prev {
+                if let Some(last) = chars.last_mut() {
+                    last.prob = last.prob.max(p);
+                }
+            } else if class != 0 {
+                if let Some(text) = self.charset.get(class) {
+                    chars.push(CtcChar {
+                        text: text.to_owned(),
+                        prob: p,
+                        time,
+                    });
+                }
+            }
+            prev = class;
+        }
+        CtcOutput::new(chars)
+    }
+
+    // prefix beam search in log space
+    pub fn beam_search(&self, scores: &[f32], classes: usize, beam_width: usize) -> CtcOutput {
+        #[derive(Clone)]
+        struct Beam {
+            blank: f32,
+            non_blank: f32,
+            chars: Vec<(usize, f32, usize)>,
+        }
+        impl Beam {
+            fn total(&self) -> f32 {
+                log_add(self.blank, self.non_blank)
+            }
+        }
+        fn entry<'a>(
+            next: &'a mut HashMap<Vec<usize>, Beam>,
+            prefix: Vec<usize>,
+            chars: &[(usize, f32, usize)],
+        ) -> &'a mut Beam {
+            next.entry(prefix).or_insert_with(|| Beam {
+                blank: f32::NEG_INFINITY,
+                non_blank: f32::NEG_INFINITY,
+                chars: chars.to_vec(),
+            })
+        }
+
+        let mut beams: HashMap<Vec<usize>, Beam> = HashMap::from([(
+            vec![],
+            Beam {
+                blank: 0.0,
+                non_blank: f32::NEG_INFINITY,
+                chars: vec![],
+            },
+        )]);
+
+        for (time, step) in scores.chunks_exact(classes).enumerate() {
+            let prob = self.probabilities(step);
+            let mut candidates: Vec<usize> = (1..classes).filter(|&i| prob[i] > 0.0).collect();
+            candidates.sort_by(|&a, &b| prob[b].total_cmp(&prob[a]));
+            candidates.truncate(beam_width);
+
+            let mut next: HashMap<Vec<usize>, Beam> = HashMap::new();
+            for (prefix, beam) in &beams {
+                let total = beam.total();
+
+                // stay on same prefix via blank
+                let b = entry(&mut next, prefix.clone(), &beam.chars);
+                b.blank = log_add(b.blank, total + prob[0].ln());
+
+                // stay on same prefix via repeated last char
+                if let Some(&last) = prefix.last() {
+                    let b = entry(&mut next, prefix.clone(), &beam.chars);
+                    b.non_blank = log_add(b.non_blank, beam.non_blank + prob[last].ln());
+                    if let Some(c) = b.chars.last_mut() {
+                        c.1 = c.1.max(prob[last]);
+                    }
+                }
+
+                for &class in &candidates {
+                    let mut extended = prefix.clone();
+                    extended.push(class);
+                    let mut chars = beam.chars.clone();
+                    chars.push((class, prob[class], time));
+                    let b = entry(&mut next, extended, &chars);
+                    // repeated char needs a blank in between
+                    let from = if prefix.last() == Some(&class) {
+                        beam.blank
+                    } else {
+                        total
+                    };
+                    b.non_blank = log_add(b.non_blank, from + prob[class].ln());
+                }
+            }
+
+            let mut sorted: Vec<_> = next.into_iter().collect();
+            sorted.sort_by(|a, b| b.1.total().total_cmp(&a.1.total()));
+            sorted.truncate(beam_width);
+            beams = sorted.into_iter().collect();
+        }
+
+        let Some((_, best)) = beams
+            .into_iter()
+            .max_by(|a, b| a.1.total().total_cmp(&b.1.total()))
+        else {
+            return CtcOutput::default();
+        };
+        CtcOutput::new(
+            best.chars
+                .into_iter()
+                .filter_map(|(class, prob, time)| {
+                    Some(CtcChar {
+                        text: self.charset.get(class)?.to_owned(),
+                        prob,
+                        time,
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    // softmax unless step already looks like a distribution, then apply whitelist
+    fn probabilities(&self, step: &[f32]) -> Vec<f32> {
+        let sum: f32 = step.iter().sum();
+        let mut prob: Vec<f32> =
+            if step.iter().all(|&x| (0.0..=1.0).contains(&x)) && (sum - 1.0).abs() < 1e-2 {
+                step.to_vec()
+            } else {
+                let max = step.iter().copied().fold(f32::MIN, f32::max);
+                let exp: Vec<f32> = step.iter().map(|&x| (x - max).exp()).collect();
+                let sum: f32 = exp.iter().sum();
+                exp.into_iter().map(|x| x / sum).collect()
+            };
+        if let Some(allowed) = &self.allowed {
+            for (i, p) in prob.iter_mut().enumerate() {
+                if !allowed.get(i).copied().unwrap_or(false) {
+                    *p = 0.0;
+                }
+            }
+        }
+        prob
+    }
+}
+
+fn log_add(a: f32, b: f32) -> f32 {
+    if a == f32::NEG_INFINITY {
+        return b;
+    }
+    if b == f32::NEG_INFINITY {
+        return a;
+    }
+    let max = a.max(b);
+    max + ((a - max).exp() + (b - max).exp()).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn charset() -> Charset {
+        Charset::from_ddddocr_json(br#"["", "a", "b", "1", "2"]"#).unwrap()
+    }
+
+    // one hot-ish steps over 5 classes
+    fn steps(classes: &[usize]) -> Vec<f32> {
+        classes
+            .iter()
+            .flat_map(|&c| (0..5).map(move |i| if i == c { 0.9 } else { 0.025 }))
+            .collect()
+    }
+
+    #[test]
+    fn greedy_collapse() {
+        let decoder = CtcDecoder::new(charset());
+        let out = decoder.greedy(&steps(&[1, 1, 0, 1, 2, 2, 0, 0, 2]), 5);
+        assert_eq!(out.text, "aabb");
+        assert_eq!(out.chars[0].time, 0);
+        assert_eq!(out.chars[1].time, 3);
+        assert!((out.confidence - 0.9).abs() < 1e-4);
+    }
+
+    #[test]
+    fn logits_are_softmaxed() {
+        let decoder = CtcDecoder::new(charset());
+        let out = decoder.greedy(&[0.0, 5.0, 0.0, 0.0, 0.0], 5);
+        assert_eq!(out.text, "a");
+        assert!(out.confidence > 0.9 && out.confidence < 1.0);
+    }
+
+    #[test]
+    fn whitelist() {
+        let decoder = CtcDecoder::new(charset()).whitelist("0123456789");
+        let mut scores = steps(&[1, 0, 2]);
+        // second best is a digit
+        scores[3] = 0.05;
+        scores[14] = 0.05;
+        assert_eq!(decoder.greedy(&scores, 5).text, "12");
+    }
+
+    #[test]
+    fn beam_matches_greedy_on_peaked_input() {
+        let decoder = CtcDecoder::new(charset()).beam_width(5);
+        let scores = steps(&[1, 1, 0, 1, 2, 0, 3]);
+        assert_eq!(decoder.decode(&scores, 5).text, "aab1");
+    }
+
+    #[test]
+    fn beam_beats_greedy() {
+        // greedy picks blank at every step, but "a" has more total mass
+        let scores = [0.4, 0.35, 0.25, 0.0, 0.0, 0.4, 0.35, 0.25, 0.0, 0.0];
+        let decoder = CtcDecoder::new(charset());
+        assert_eq!(decoder.greedy(&scores, 5).text, "");
+        assert_eq!(decoder.beam_search(&scores, 5, 4).text, "a");
+    }
+
+    #[test]
+    fn charset_errors() {
+        assert!(matches!(
+            Charset::from_ddddocr_json(b"{}"),
+            Err(CharsetError::Json(_))
+        ));
+        assert!(matches!(
+            Charset::from_paddle_keys(&[0xff, 0xfe]),
+            Err(CharsetError::Utf8(_))
+        ));
+        assert!(matches!(
+            Charset::from_paddle_keys(b""),
+            Err(CharsetError::Empty)
+        ));
+        let keys = Charset::from_paddle_keys("a\r\nb\n".as_bytes()).unwrap();
+        assert_eq!(keys.get(2), Some("b"));
+        assert_eq!(keys.get(3), Some(" "));
+    }
+}
@@ -0,0 +1,474 @@
+// DBNet postprocess, follows PaddleOCR ppocr/postprocess/db_postprocess.py
+
+use image::{GenericImageView, Rgb, RgbImage};
+
+use crate::color::Rect;
+
+pub struct DbPostprocess {
+    // binarize probability map
+    pub threshold: f32,
+    // drop box whose mean probability is lower
+    pub box_threshold: f32,
+    // expand distance = area * unclip_ratio / perimeter
+    pub unclip_ratio: f32,
+    // drop box whose short side is shorter
+    pub min_size: f32,
+    pub max_candidates: usize,
+}
+
+impl Default for DbPostprocess {
+    fn default() -> Self {
+        DbPostprocess {
+            threshold: 0.3,
+            box_threshold: 0.6,
+            unclip_ratio: 1.5,
+            min_size: 3.0,
+            max_candidates: 1000,
+        }
+    }
+}
+
+// rotated box in top-left, top-right, bottom-right, bottom-left order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quad {
+    pub points: [(f32, f32); 4],
+    pub score: f32,
+}
+
+impl Quad {
+    pub fn bounding_rect(&self) -> Rect {
+        let (mut l, mut t, mut r, mut b) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+        for &(x, y) in &self.points {
+            l = l.min(x);
+            t = t.min(y);
+            r = r.max(x);
+            b = b.max(y);
+        }
+        let (l, t) = (l.floor() as i32, t.floor() as i32);
+        Rect {
+            left: l,
+            top: t,
+            width: (r.ceil() as i32 - l).max(0) as u32,
+            height: (b.ceil() as i32 - t).max(0) as u32,
+        }
+    }
+
+    pub fn width(&self) -> f32 {
+        let [p0, p1, p2, p3] = self.points;
+        distance(p0, p1).max(distance(p3, p2))
+    }
+
+    pub fn height(&self) -> f32 {
+        let [p0, p1, p2, p3] = self.points;
+        distance(p0, p3).max(distance(p1, p2))
+    }
+}
+
+impl DbPostprocess {
+    // prob is a row major width x height map, result is in map coordinate
+    pub fn run(&self, prob: &[f32], width: usize, height: usize) -> Vec<Quad> {
+        let mut mask: Vec<bool> = prob.iter().map(|&x| x > self.threshold).collect();
+        let mut ans = vec![];
+        for points in components(&mut mask, width, height)
+            .into_iter()
+            .take(self.max_candidates)
+        {
+            let hull = convex_hull(points);
+            let Some(quad) = min_area_rect(&hull) else {
+                continue;
+            };
+            let (w, h) = side_length(&quad);
+            if w.min(h) < self.min_size {
+                continue;
+            }
+
+            let score = polygon_mean(prob, width, height, &quad);
+            if score < self.box_threshold {
+                continue;
+            }
+
+            let area = w * h;
+            let perimeter = 2.0 * (w + h);
+            let quad = unclip(&quad, area * self.unclip_ratio / perimeter);
+            let (w, h) = side_length(&quad);
+            if w.min(h) < self.min_size + 2.0 {
+                continue;
+            }
+
+            let clip = |(x, y): (f32, f32)| {
+                (
+                    x.clamp(0.0, width as f32 - 1.0),
+                    y.clamp(0.0, height as f32 - 1.0),
+                )
+            };
+            ans.push(Quad {
+                points: order_points(quad.map(clip)),
+                score,
+            });
+        }
+        sort_lines(&mut ans);
+        ans
+    }
+}
+
+// top to bottom, boxes on roughly the same line left to right
+pub fn sort_lines(quads: &mut [Quad]) {
+    quads.sort_by(|a, b| {
+        (a.points[0].1, a.points[0].0)
+            .partial_cmp(&(b.points[0].1, b.points[0].0))
+            .unwrap()
+    });
+    for i in 0..quads.len() {
+        for j in (0..i).rev() {
+            let (a, b) = (&quads[j], &quads[j + 1]);
+            if (b.points[0].1 - a.points[0].1).abs() < 10.0 && b.points[0].0 < a.points[0].0 {
+                quads.swap(j, j + 1);
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+// warp quad to an upright image, tall result is rotated to horizontal
+pub fn crop_quad(img: &RgbImage, quad: &Quad) -> RgbImage {
+    let w = quad.width().round().max(1.0) as u32;
+    let h = quad.height().round().max(1.0) as u32;
+    let dst = [
+        (0.0, 0.0),
+        (w as f32, 0.0),
+        (w as f32, h as f32),
+        (0.0, h as f32),
+    ];
+    let m = homography(&dst, &quad.points);
+    let mut ans = RgbImage::new(w, h);
+    for (x, y, pixel) in ans.enumerate_pixels_mut() {
+        let (fx, fy) = (x as f32 + 0.5, y as f32 + 0.5);
+        let z = m[6] * fx + m[7] * fy + m[8];
+        let sx = (m[0] * fx + m[1] * fy + m[2]) / z - 0.5;
+        let sy = (m[3] * fx + m[4] * fy + m[5]) / z - 0.5;
+        *pixel = bilinear(img, sx, sy);
+    }
+    if h as f32 >= w as f32 * 1.5 {
+        ans = image::imageops::rotate270(&ans);
+    }
+    ans
+}
+
+fn bilinear(img: &RgbImage, x: f32, y: f32) -> Rgb<u8> {
+    let (w, h) = img.dimensions();
+    let x = x.clamp(0.0, w as f32 - 1.0);
+    let y = y.clamp(0.0, h as f32 - 1.0);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+    let (dx, dy) = (x - x0 as f32, y - y0 as f32);
+    let p = |x, y| img.get_pixel(x, y).0.map(|v| v as f32);
+    let (a, b, c, d) = (p(x0, y0), p(x1, y0), p(x0, y1), p(x1, y1));
+    Rgb(std::array::from_fn(|i| {
+        let top = a[i] + (b[i] - a[i]) * dx;
+        let bottom = c[i] + (d[i] - c[i]) * dx;
+        (top + (bottom - top) * dy).round() as u8
+    }))
+}
+
+// 3x3 row major matrix mapping src points to dst points
+fn homography(src: &[(f32, f32); 4], dst: &[(f32, f32); 4]) -> [f32; 9] {
+    let mut a = [[0f64; 9]; 8];
+    for i in 0..4 {
+        let (x, y) = (src[i].0 as f64, src[i].1 as f64);
+        let (u, v) = (dst[i].0 as f64, dst[i].1 as f64);
+        a[2 * i] = [x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y, u];
+        a[2 * i + 1] = [0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y, v];
+    }
+    // gauss jordan with partial pivoting
+    for col in 0..8 {
+        let pivot = (col..8)
+            .max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))
+            .unwrap();
+        a.swap(col, pivot);
+        if a[col][col].abs() < 1e-12 {
+            continue;
+        }
+        let pivot = a[col];
+        for (row, r) in a.iter_mut().enumerate() {
+            if row != col {
+                let f = r[col] / pivot[col];
+                for (x, p) in r[col..].iter_mut().zip(&pivot[col..]) {
+                    *x -= f * p;
+                }
+            }
+        }
+    }
+    let mut m = [0f32; 9];
+    for i in 0..8 {
+        m[i] = if a[i][i].abs() < 1e-12 {
+            0.0
+        } else {
+            (a[i][8] / a[i][i]) as f32
+        };
+    }
+    m[8] = 1.0;
+    m
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+fn side_length(quad: &[(f32, f32); 4]) -> (f32, f32) {
+    (distance(quad[0], quad[1]), distance(quad[1], quad[2]))
+}
+
+// pixel coordinates of each 8-connected component
+fn components(mask: &mut [bool], w: usize, h: usize) -> Vec<Vec<(f32, f32)>> {
+    let mut ans = vec![];
+    let mut stack = vec![];
+    for start in 0..mask.len() {
+        if !mask[start] {
+            continue;
+        }
+        mask[start] = false;
+        stack.push(start);
+        let mut points = vec![];
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % w, i / w);
+            points.push((x as f32, y as f32));
+            for ny in y.saturating_sub(1)..(y + 2).min(h) {
+                for nx in x.saturating_sub(1)..(x + 2).min(w) {
+                    let j = ny * w + nx;
+                    if mask[j] {
+                        mask[j] = false;
+                        stack.push(j);
+                    }
+                }
+            }
+        }
+        ans.push(points);
+    }
+    ans
+}
+
+// andrew monotone chain, counter clockwise in y-down coordinate
+fn convex_hull(mut points: Vec<(f32, f32)>) -> Vec<(f32, f32)> {
+    points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let cross = |o: (f32, f32), a: (f32, f32), b: (f32, f32)| {
+        (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+    };
+    let mut hull: Vec<(f32, f32)> = vec![];
+    for pass in 0..2 {
+        let start = hull.len();
+        let iter: Box<dyn Iterator<Item = &(f32, f32)>> = if pass == 0 {
+            Box::new(points.iter())
+        } else {
+            Box::new(points.iter().rev())
+        };
+        for &p in iter {
+            while hull.len() >= start + 2
+                && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+// rotating calipers over hull edges
+fn min_area_rect(hull: &[(f32, f32)]) -> Option<[(f32, f32); 4]> {
+    if hull.is_empty() {
+        return None;
+    }
+    if hull.len() == 1 {
+        return Some([hull[0]; 4]);
+    }
+    let mut best: Option<(f32, [(f32, f32); 4])> = None;
+    for i in 0..hull.len() {
+        let (a, b) = (hull[i], hull[(i + 1) % hull.len()]);
+        let len = distance(a, b);
+        if len == 0.0 {
+            continue;
+        }
+        let (ux, uy) = ((b.0 - a.0) / len, (b.1 - a.1) / len);
+        let (vx, vy) = (-uy, ux);
+        let (mut min_u, mut max_u, mut min_v, mut max_v) = (f32::MAX, f32::MIN, f32::MAX, f32::MIN);
+        for &(x, y) in hull {
+            let u = x * ux + y * uy;
+            let v = x * vx + y * vy;
+            min_u = min_u.min(u);
+            max_u = max_u.max(u);
+            min_v = min_v.min(v);
+            max_v = max_v.max(v);
+        }
+        let area = (max_u - min_u) * (max_v - min_v);
+        if best.as_ref().is_none_or(|x| area < x.0) {
+            let p = |u: f32, v: f32| (u * ux + v * vx, u * uy + v * vy);
+            best = Some((
+                area,
+                [
+                    p(min_u, min_v),
+                    p(max_u, min_v),
+                    p(max_u, max_v),
+                    p(min_u, max_v),
+                ],
+            ));
+        }
+    }
+    best.map(|x| order_points(x.1))
+}
+
+// order like get_mini_boxes: split by x, then by y
+fn order_points(mut points: [(f32, f32); 4]) -> [(f32, f32); 4] {
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let (tl, bl) = if points[0].1 <= points[1].1 {
+        (points[0], points[1])
+    } else {
+        (points[1], points[0])
+    };
+    let (tr, br) = if points[2].1 <= points[3].1 {
+        (points[2], points[3])
+    } else {
+        (points[3], points[2])
+    };
+    [tl, tr, br, bl]
+}
+
+// offset a rectangle outward by distance on every side
+fn unclip(quad: &[(f32, f32); 4], distance: f32) -> [(f32, f32); 4] {
+    let cx = quad.iter().map(|p| p.0).sum::<f32>() / 4.0;
+    let cy = quad.iter().map(|p| p.1).sum::<f32>() / 4.0;
+    let (w, h) = side_length(quad);
+    let unit = |a: (f32, f32), b: (f32, f32), len: f32| {
+        if len == 0.0 {
+            (0.0, 0.0)
+        } else {
+            ((b.0 - a.0) / len, (b.1 - a.1) / len)
+        }
+    };
+    let (ux, uy) = unit(quad[0], quad[1], w);
+    let (vx, vy) = unit(quad[1], quad[2], h);
+    let (hw, hh) = (w / 2.0 + distance, h / 2.0 + distance);
+    let p = |su: f32, sv: f32| {
+        (
+            cx + su * hw * ux + sv * hh * vx,
+            cy + su * hw * uy + sv * hh * vy,
+        )
+    };
+    [p(-1.0, -1.0), p(1.0, -1.0), p(1.0, 1.0), p(-1.0, 1.0)]
+}
+
+// mean probability of pixels inside quad
+fn polygon_mean(prob: &[f32], width: usize, height: usize, quad: &[(f32, f32); 4]) -> f32 {
+    let l = quad
+        .iter()
+        .map(|p| p.0)
+        .fold(f32::MAX, f32::min)
+        .floor()
+        .max(0.0) as usize;
+    let r = quad.iter().map(|p| p.0).fold(f32::MIN, f32::max).ceil() as usize;
+    let t = quad
+        .iter()
+        .map(|p| p.1)
+        .fold(f32::MAX, f32::min)
+        .floor()
+        .max(0.0) as usize;
+    let b = quad.iter().map(|p| p.1).fold(f32::MIN, f32::max).ceil() as usize;
+    let (r, b) = (r.min(width - 1), b.min(height - 1));
+
+    let inside = |x: f32, y: f32| {
+        let mut sign = 0f32;
+        for i in 0..4 {
+            let (a, c) = (quad[i], quad[(i + 1) % 4]);
+            let cross = (c.0 - a.0) * (y - a.1) - (c.1 - a.1) * (x - a.0);
+            if cross.abs() < 1e-3 {
+                continue;
+            }
+            if sign == 0.0 {
+                sign = cross.signum();
+            } else if sign != cross.signum() {
+                return false;
+            }
+        }
+        true
+    };
+
+    let (mut sum, mut n) = (0.0, 0);
+    for y in t..=b {
+        for x in l..=r {
+            if inside(x as f32, y as f32) {
+                sum += prob[y * width + x];
+                n += 1;
+            }
+        }
+    }
+    if n == 0 {
+        0.0
+    } else {
+        sum / n as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, ImageReader};
+
+    use super::*;
+
+    fn load_prob(name: &str) -> (Vec<f32>, usize, usize) {
+        let path = format!("{}/fixture/{name}", env!("CARGO_MANIFEST_DIR"));
+        let img: GrayImage = ImageReader::open(path)
+            .unwrap()
+            .decode()
+            .unwrap()
+            .into_luma8();
+        let (w, h) = img.dimensions();
+        let prob = img.pixels().map(|p| p.0[0] as f32 / 255.0).collect();
+        (prob, w as usize, h as usize)
+    }
+
+    #[test]
+    fn rotated_box() {
+        let (prob, w, h) = load_prob("db_rotated.png");
+        let quads = DbPostprocess::default().run(&prob, w, h);
+        assert_eq!(quads.len(), 1);
+
+        let [p0, p1, _, p3] = quads[0].points;
+        let angle = (p1.1 - p0.1).atan2(p1.0 - p0.0).to_degrees();
+        assert!((angle.abs() - 20.0).abs() < 3.0, "angle {angle}");
+        assert!(distance(p0, p1) > distance(p0, p3) * 2.0);
+    }
+
+    #[test]
+    fn line_order() {
+        let (prob, w, h) = load_prob("db_lines.png");
+        let quads = DbPostprocess::default().run(&prob, w, h);
+        let origin: Vec<_> = quads
+            .iter()
+            .map(|q| (q.points[0].0 as i32 / 50, q.points[0].1 as i32 / 50))
+            .collect();
+        assert_eq!(origin, [(0, 0), (4, 0), (0, 2)]);
+    }
+
+    #[test]
+    fn crop_upright() {
+        let mut img = RgbImage::from_pixel(40, 40, Rgb([0, 0, 0]));
+        for y in 10..20 {
+            for x in 5..35 {
+                img.put_pixel(x, y, Rgb([255, 255, 255]));
+            }
+        }
+        let quad = Quad {
+            points: [(5.0, 10.0), (35.0, 10.0), (35.0, 20.0), (5.0, 20.0)],
+            score: 1.0,
+        };
+        let crop = crop_quad(&img, &quad);
+        assert_eq!(crop.dimensions(), (30, 10));
+        assert!(crop.pixels().all(|p| p.0[0] > 200));
+        assert_eq!(crop.view(0, 0, 1, 1).to_image().get_pixel(0, 0).0, [255; 3]);
+    }
+}
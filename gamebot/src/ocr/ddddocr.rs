@@ -0,0 +1,69 @@
+use image::RgbImage;
+
+use super::{Charset, CtcDecoder, Ocr, TextBox};
+use crate::{
+    api::asset_dir,
+    color::Rect,
+    model::{load, Model, ModelRef, Preprocess, Source},
+};
+
+const REC_HEIGHT: u32 = 64;
+
+// ddddocr single line recognition, no detection
+pub struct Ddddocr {
+    rec: ModelRef,
+    pub decoder: CtcDecoder,
+}
+
+impl Ddddocr {
+    pub fn new(rec: Model, charset: impl Into<Source>) -> anyhow::Result<Self> {
+        Ok(Ddddocr {
+            rec: load(&rec)?.into(),
+            decoder: CtcDecoder::new(Charset::read_ddddocr_json(&charset.into())?),
+        })
+    }
+
+    // model from gamebot::model registry, charset is relative to asset dir
+    pub fn named(rec: &str, charset: &str) -> anyhow::Result<Self> {
+        let charset = Source::Path(asset_dir().join(charset));
+        Ok(Ddddocr {
+            rec: rec.into(),
+            decoder: CtcDecoder::new(Charset::read_ddddocr_json(&charset)?),
+        })
+    }
+}
+
+impl Ocr for Ddddocr {
+    fn recognize(&self, img: &RgbImage) -> anyhow::Result<Vec<TextBox>> {
+        let line = self.recognize_line(img)?;
+        Ok(if line.text.is_empty() {
+            vec![]
+        } else {
+            vec![line]
+        })
+    }
+
+    fn recognize_line(&self, img: &RgbImage) -> anyhow::Result<TextBox> {
+        let (w, h) = img.dimensions();
+        let input = Preprocess::new()
+            .resize_height(REC_HEIGHT)
+            .grayscale()
+            .normalize(&[0.5], &[0.5])
+            .run(img)
+            .tensor;
+        let out = self.rec.get()?.run_one(input.into_dyn().view())?;
+        let out = self
+            .decoder
+            .decode(out.as_slice().unwrap(), out.shape()[out.ndim() - 1]);
+        Ok(TextBox {
+            text: out.text,
+            rect: Rect {
+                left: 0,
+                top: 0,
+                width: w,
+                height: h,
+            },
+            confidence: out.confidence,
+        })
+    }
+}
@@ -0,0 +1,115 @@
+use image::RgbImage;
+
+use super::{crop_quad, Charset, CtcDecoder, DbPostprocess, Ocr, Quad, TextBox};
+use crate::{
+    api::asset_dir,
+    color::Rect,
+    model::{load, Model, ModelRef, Preprocess, Source},
+};
+
+const REC_HEIGHT: u32 = 48;
+const DET_MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+const DET_STD: [f32; 3] = [0.229, 0.224, 0.225];
+
+// PP-OCR text detection + recognition, detection is optional
+pub struct PaddleOcr {
+    det: Option<ModelRef>,
+    rec: ModelRef,
+    pub decoder: CtcDecoder,
+    pub postprocess: DbPostprocess,
+}
+
+impl PaddleOcr {
+    pub fn new(det: Option<Model>, rec: Model, charset: impl Into<Source>) -> anyhow::Result<Self> {
+        Ok(PaddleOcr {
+            det: det
+                .as_ref()
+                .map(|x| load(x).map(ModelRef::from))
+                .transpose()?,
+            rec: load(&rec)?.into(),
+            decoder: CtcDecoder::new(Charset::read_paddle_keys(&charset.into())?),
+            postprocess: DbPostprocess::default(),
+        })
+    }
+
+    // models from gamebot::model registry, charset is relative to asset dir
+    pub fn named(det: Option<&str>, rec: &str, charset: &str) -> anyhow::Result<Self> {
+        let charset = Source::Path(asset_dir().join(charset));
+        Ok(PaddleOcr {
+            det: det.map(ModelRef::from),
+            rec: rec.into(),
+            decoder: CtcDecoder::new(Charset::read_paddle_keys(&charset)?),
+            postprocess: DbPostprocess::default(),
+        })
+    }
+
+    pub fn detect(&self, img: &RgbImage) -> anyhow::Result<Vec<Quad>> {
+        let Some(det) = &self.det else {
+            return Ok(vec![]);
+        };
+        let (w, h) = img.dimensions();
+        let input = Preprocess::new()
+            .pad_to_multiple(32)
+            .normalize(&DET_MEAN, &DET_STD)
+            .run(img)
+            .tensor;
+        let out = det.get()?.run_one(input.into_dyn().view())?;
+        let shape = out.shape();
+        let (mw, mh) = (shape[shape.len() - 1], shape[shape.len() - 2]);
+
+        // drop padding before postprocess so boxes stay inside img
+        let (cw, ch) = ((w as usize).min(mw), (h as usize).min(mh));
+        let prob: Vec<f32> = out
+            .as_slice()
+            .unwrap()
+            .chunks(mw)
+            .take(ch)
+            .flat_map(|row| &row[..cw])
+            .copied()
+            .collect();
+        Ok(self.postprocess.run(&prob, cw, ch))
+    }
+}
+
+impl Ocr for PaddleOcr {
+    fn recognize(&self, img: &RgbImage) -> anyhow::Result<Vec<TextBox>> {
+        if self.det.is_none() {
+            return Ok(vec![self.recognize_line(img)?]);
+        }
+        let mut ans = vec![];
+        for quad in self.detect(img)? {
+            let line = self.recognize_line(&crop_quad(img, &quad))?;
+            if line.text.is_empty() {
+                continue;
+            }
+            ans.push(TextBox {
+                rect: quad.bounding_rect(),
+                ..line
+            });
+        }
+        Ok(ans)
+    }
+
+    fn recognize_line(&self, img: &RgbImage) -> anyhow::Result<TextBox> {
+        let (w, h) = img.dimensions();
+        let input = Preprocess::new()
+            .resize_height(REC_HEIGHT)
+            .normalize(&[0.5], &[0.5])
+            .run(img)
+            .tensor;
+        let out = self.rec.get()?.run_one(input.into_dyn().view())?;
+        let out = self
+            .decoder
+            .decode(out.as_slice().unwrap(), out.shape()[out.ndim() - 1]);
+        Ok(TextBox {
+            text: out.text,
+            rect: Rect {
+                left: 0,
+                top: 0,
+                width: w,
+                height: h,
+            },
+            confidence: out.confidence,
+        })
+    }
+}
@@ -0,0 +1,275 @@
+// boxes, points and a status line drawn over the screen, to see what the
+// script sees while debugging
+//
+// the guest keeps what is shown and sends all of it on every change, each
+// shape with the time it has left, so the host drops expired shapes even
+// while the guest is busy elsewhere.
+//
+// the overlay is part of screenshots too. outlines are drawn just outside
+// the rect so the pixels inside are left alone, but rect labels, points and
+// the status line cover what is under them and later finds see that. debug
+// outlines come without labels for this reason
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::{
+    api::{proxy, Seconds},
+    color::{Color, Point, Rect},
+};
+
+static OVERLAY: Mutex<Overlay> = Mutex::new(Overlay::new());
+static DEBUG: AtomicBool = AtomicBool::new(false);
+
+// how long debug outlines stay after the find that drew them
+const DEBUG_TTL: Duration = Duration::from_millis(800);
+
+const HIT: Color = Color {
+    red: 0,
+    green: 200,
+    blue: 0,
+};
+const MISS: Color = Color {
+    red: 220,
+    green: 0,
+    blue: 0,
+};
+
+// ttl zero keeps the rect until clear. a label goes just above the rect,
+// over whatever is there
+pub fn draw_rect(rect: impl Into<Rect>, color: impl Into<Color>, label: &str, ttl: impl Seconds) {
+    let Rect {
+        left,
+        top,
+        width,
+        height,
+    } = rect.into();
+    add(
+        Shape::Rect {
+            left,
+            top,
+            width,
+            height,
+            color: argb(&color.into()),
+            label: label.into(),
+        },
+        ttl.into_duration(),
+    );
+}
+
+// a circle around the point, it covers the pixels there
+pub fn draw_point(
+    point: impl Into<Point>,
+    color: impl Into<Color>,
+    label: &str,
+    ttl: impl Seconds,
+) {
+    let Point { x, y } = point.into();
+    add(
+        Shape::Point {
+            x,
+            y,
+            color: argb(&color.into()),
+            label: label.into(),
+        },
+        ttl.into_duration(),
+    );
+}
+
+// one line at the top on a dark box, empty hides it
+pub fn show_text(status: &str) {
+    let mut overlay = OVERLAY.lock().unwrap();
+    overlay.status = status.into();
+    overlay.send();
+}
+
+// shapes and status
+pub fn clear() {
+    let mut overlay = OVERLAY.lock().unwrap();
+    *overlay = Overlay::new();
+    overlay.send();
+}
+
+// outline what every Find matched in green and the region it missed in red
+pub fn set_debug(enabled: bool) {
+    DEBUG.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_debug() -> bool {
+    DEBUG.load(Ordering::Relaxed)
+}
+
+// labels only go to the log, drawn they would change what the next find sees
+pub(crate) fn debug_hit(label: &str, rect: Rect) {
+    log::debug!("{label} hit {rect:?}");
+    draw_rect(rect, HIT, "", DEBUG_TTL);
+}
+
+pub(crate) fn debug_miss(label: &str, region: Rect) {
+    log::debug!("{label} missed in {region:?}");
+    draw_rect(region, MISS, "", DEBUG_TTL);
+}
+
+fn add(shape: Shape, ttl: Duration) {
+    let mut overlay = OVERLAY.lock().unwrap();
+    overlay.add(shape, ttl, Instant::now());
+    overlay.send();
+}
+
+fn argb(color: &Color) -> u32 {
+    0xff00_0000 | (color.red as u32) << 16 | (color.green as u32) << 8 | color.blue as u32
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type")]
+enum Shape {
+    Rect {
+        left: i32,
+        top: i32,
+        width: u32,
+        height: u32,
+        color: u32,
+        label: String,
+    },
+    Point {
+        x: i32,
+        y: i32,
+        color: u32,
+        label: String,
+    },
+}
+
+struct Item {
+    shape: Shape,
+    // none until clear
+    until: Option<Instant>,
+}
+
+#[derive(Serialize)]
+struct Frame<'a> {
+    shapes: Vec<Sent<'a>>,
+    status: &'a str,
+}
+
+#[derive(Serialize)]
+struct Sent<'a> {
+    #[serde(flatten)]
+    shape: &'a Shape,
+    // millis left
+    ttl: Option<u64>,
+}
+
+struct Overlay {
+    items: Vec<Item>,
+    status: String,
+}
+
+impl Overlay {
+    const fn new() -> Self {
+        Overlay {
+            items: vec![],
+            status: String::new(),
+        }
+    }
+
+    // drawing what is already shown renews it, so a find evaluated on every
+    // frame of an appear doesn't stack up copies
+    fn add(&mut self, shape: Shape, ttl: Duration, now: Instant) {
+        self.items.retain(|x| x.shape != shape);
+        self.items.push(Item {
+            shape,
+            until: (!ttl.is_zero()).then(|| now + ttl),
+        });
+    }
+
+    fn frame(&mut self, now: Instant) -> Frame<'_> {
+        self.items
+            .retain(|x| x.until.is_none_or(|until| until > now));
+        Frame {
+            shapes: self
+                .items
+                .iter()
+                .map(|x| Sent {
+                    shape: &x.shape,
+                    ttl: x.until.map(|until| (until - now).as_millis() as u64),
+                })
+                .collect(),
+            status: &self.status,
+        }
+    }
+
+    fn send(&mut self) {
+        let frame = self.frame(Instant::now());
+        proxy().update_overlay(&frame);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn point(label: &str) -> Shape {
+        Shape::Point {
+            x: 1,
+            y: 2,
+            color: argb(&(255, 0, 0).into()),
+            label: label.into(),
+        }
+    }
+
+    #[test]
+    fn frame_json() {
+        let now = Instant::now();
+        let mut overlay = Overlay::new();
+        overlay.status = "looking".into();
+        overlay.add(point("a"), Duration::from_secs(2), now);
+        overlay.add(
+            Shape::Rect {
+                left: -1,
+                top: 0,
+                width: 3,
+                height: 4,
+                color: argb(&HIT),
+                label: String::new(),
+            },
+            Duration::ZERO,
+            now,
+        );
+        assert_eq!(
+            serde_json::to_value(overlay.frame(now)).unwrap(),
+            json!({
+                "shapes": [
+                    {"type": "Point", "x": 1, "y": 2, "color": 0xffff0000u32, "label": "a", "ttl": 2000},
+                    {"type": "Rect", "left": -1, "top": 0, "width": 3, "height": 4,
+                        "color": 0xff00c800u32, "label": "", "ttl": null},
+                ],
+                "status": "looking",
+            })
+        );
+    }
+
+    #[test]
+    fn expire_and_renew() {
+        let now = Instant::now();
+        let mut overlay = Overlay::new();
+        overlay.add(point("a"), Duration::from_secs(1), now);
+        overlay.add(point("b"), Duration::from_secs(1), now);
+        overlay.add(point("a"), Duration::from_secs(3), now);
+        assert_eq!(overlay.frame(now).shapes.len(), 2);
+
+        let later = now + Duration::from_secs(2);
+        let frame = overlay.frame(later);
+        assert_eq!(frame.shapes.len(), 1);
+        assert_eq!(frame.shapes[0].shape, &point("a"));
+        assert_eq!(frame.shapes[0].ttl, Some(1000));
+    }
+}
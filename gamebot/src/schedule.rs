@@ -0,0 +1,409 @@
+// named tasks on cron times, e.g. dailies after the 4:00 reset
+//
+// a task is due once its next cron time after the last run has passed, so a
+// run missed while the phone was off happens once when the guest is back,
+// not once per missed time. a task never run looks back one day. runs are
+// kept in data_dir/schedule.json to count against the daily limit
+//
+// run_forever shows the tasks in the host, which can start one at any time
+
+mod cron;
+
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+pub use cron::{Cron, Date, DateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{assert_running_status, proxy},
+    ui::Persist,
+};
+
+// runs kept per task
+const KEEP_RUNS: usize = 50;
+
+// longest sleep of run_forever, so a changed clock or time zone is noticed
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+pub struct Task {
+    name: String,
+    // as given, for the host
+    expr: String,
+    cron: Cron,
+    daily_limit: Option<u32>,
+    first_day: Option<Date>,
+    last_day: Option<Date>,
+    run: Box<dyn FnMut() -> anyhow::Result<()>>,
+}
+
+impl Task {
+    pub fn new(
+        name: &str,
+        cron: &str,
+        run: impl FnMut() -> anyhow::Result<()> + 'static,
+    ) -> anyhow::Result<Self> {
+        Ok(Task {
+            name: name.into(),
+            expr: cron.into(),
+            cron: cron.parse()?,
+            daily_limit: None,
+            first_day: None,
+            last_day: None,
+            run: Box::new(run),
+        })
+    }
+
+    // runs per calendar day, failed ones count too
+    pub fn daily_limit(mut self, limit: u32) -> Self {
+        self.daily_limit = Some(limit);
+        self
+    }
+
+    // not before this day
+    pub fn first_day(mut self, date: Date) -> Self {
+        self.first_day = Some(date);
+        self
+    }
+
+    // not after this day, e.g. the end of an event
+    pub fn last_day(mut self, date: Date) -> Self {
+        self.last_day = Some(date);
+        self
+    }
+
+    fn is_due(&self, runs: &[Run], now: DateTime) -> bool {
+        if !self.in_days(now.date) || self.limit_reached(runs, now.date) {
+            return false;
+        }
+        let since = runs
+            .last()
+            .map(|x| x.at)
+            .unwrap_or_else(|| now.add_minutes(-1440));
+        self.cron.next_after(since).is_some_and(|x| x <= now)
+    }
+
+    // now when due, none when it won't be due again
+    fn next_time(&self, runs: &[Run], now: DateTime) -> Option<DateTime> {
+        if self.is_due(runs, now) {
+            return Some(now);
+        }
+        let mut from = now;
+        if self.limit_reached(runs, now.date) {
+            from = from.max(DateTime::new(now.date, 23, 59));
+        }
+        if let Some(first) = self.first_day {
+            from = from.max(DateTime::new(first, 0, 0).add_minutes(-1));
+        }
+        let next = self.cron.next_after(from)?;
+        self.in_days(next.date).then_some(next)
+    }
+
+    fn in_days(&self, date: Date) -> bool {
+        self.first_day.is_none_or(|x| date >= x) && self.last_day.is_none_or(|x| date <= x)
+    }
+
+    fn limit_reached(&self, runs: &[Run], date: Date) -> bool {
+        self.daily_limit
+            .is_some_and(|limit| runs_on(runs, date) >= limit)
+    }
+
+    fn status(&self, runs: &[Run], now: DateTime) -> TaskStatus {
+        let last = runs.last();
+        TaskStatus {
+            name: self.name.clone(),
+            cron: self.expr.clone(),
+            due: self.is_due(runs, now),
+            next: self.next_time(runs, now).map(|x| x.to_string()),
+            runs_today: runs_on(runs, now.date),
+            daily_limit: self.daily_limit,
+            first_day: self.first_day.map(|x| x.to_string()),
+            last_day: self.last_day.map(|x| x.to_string()),
+            last_run: last.map(|x| x.at.to_string()),
+            last_error: last.and_then(|x| x.error.clone()),
+        }
+    }
+}
+
+fn runs_on(runs: &[Run], date: Date) -> u32 {
+    runs.iter().filter(|x| x.at.date == date).count() as u32
+}
+
+// what the host shows of a task, times as local "2026-10-19 04:00"
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TaskStatus {
+    pub name: String,
+    pub cron: String,
+    pub due: bool,
+    // none when it won't be due again
+    pub next: Option<String>,
+    pub runs_today: u32,
+    pub daily_limit: Option<u32>,
+    pub first_day: Option<String>,
+    pub last_day: Option<String>,
+    pub last_run: Option<String>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Run {
+    // local time it started
+    pub at: DateTime,
+    pub millis: u64,
+    // none when it went fine
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct History {
+    runs: BTreeMap<String, Vec<Run>>,
+}
+
+pub struct Schedule {
+    tasks: Vec<Task>,
+    history: History,
+    persist: Persist,
+}
+
+impl Schedule {
+    // history in <data_dir>/schedule.json
+    pub fn new() -> Self {
+        Self::with(Persist::new("schedule"))
+    }
+
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self::with(Persist::at(path))
+    }
+
+    fn with(persist: Persist) -> Self {
+        // a broken file shouldn't keep the tasks from running
+        let history = persist.load().unwrap_or_else(|e| {
+            log::warn!("schedule history: {e:#}");
+            None
+        });
+        Schedule {
+            tasks: vec![],
+            history: history.unwrap_or_default(),
+            persist,
+        }
+    }
+
+    pub fn task(mut self, task: Task) -> Self {
+        self.tasks.retain(|x| x.name != task.name);
+        self.tasks.push(task);
+        self
+    }
+
+    // in the order they were added
+    pub fn due(&self, now: DateTime) -> Vec<&str> {
+        self.tasks
+            .iter()
+            .filter(|x| x.is_due(self.runs(&x.name), now))
+            .map(|x| x.name.as_str())
+            .collect()
+    }
+
+    // earliest task to be due, with when
+    pub fn next_due(&self, now: DateTime) -> Option<(&str, DateTime)> {
+        self.tasks
+            .iter()
+            .filter_map(|x| Some((x.name.as_str(), x.next_time(self.runs(&x.name), now)?)))
+            .min_by_key(|(_, at)| *at)
+    }
+
+    // in the order they were added
+    pub fn status(&self, now: DateTime) -> Vec<TaskStatus> {
+        self.tasks
+            .iter()
+            .map(|x| x.status(self.runs(&x.name), now))
+            .collect()
+    }
+
+    pub fn runs(&self, name: &str) -> &[Run] {
+        self.history.runs.get(name).map_or(&[], |x| x.as_slice())
+    }
+
+    // right away, due or not. the run is recorded and its error returned
+    pub fn run(&mut self, name: &str) -> anyhow::Result<()> {
+        self.run_at(name, now())
+    }
+
+    fn run_at(&mut self, name: &str, at: DateTime) -> anyhow::Result<()> {
+        let Some(task) = self.tasks.iter_mut().find(|x| x.name == name) else {
+            anyhow::bail!("no task {name}");
+        };
+        log::info!("task {name} at {at}");
+        let start = Instant::now();
+        let result = (task.run)();
+        let runs = self.history.runs.entry(name.into()).or_default();
+        runs.push(Run {
+            at,
+            millis: start.elapsed().as_millis() as u64,
+            error: result.as_ref().err().map(|e| format!("{e:#}")),
+        });
+        if runs.len() > KEEP_RUNS {
+            runs.drain(..runs.len() - KEEP_RUNS);
+        }
+        if let Err(e) = self.persist.save(&self.history) {
+            log::warn!("save schedule history: {e:#}");
+        }
+        result
+    }
+
+    // every due task once, returns how many ran
+    pub fn run_due(&mut self) -> usize {
+        let now = now();
+        let due: Vec<String> = self.due(now).into_iter().map(String::from).collect();
+        for name in &due {
+            if let Err(e) = self.run_at(name, now) {
+                log::warn!("task {name}: {e:#}");
+            }
+        }
+        due.len()
+    }
+
+    // until the guest stops. the host shows the tasks and picks ones to run
+    // right away, due or not
+    pub fn run_forever(&mut self) -> ! {
+        loop {
+            self.run_due();
+            let now = now();
+            proxy().update_schedule(&self.status(now));
+            let sleep = match self.next_due(now) {
+                Some((_, at)) if at > now => {
+                    let offset = utc_offset();
+                    let secs = at.to_unix(offset) - unix_now();
+                    Duration::from_secs(secs.max(1) as u64).min(MAX_SLEEP)
+                }
+                Some(_) => Duration::from_secs(1),
+                None => MAX_SLEEP,
+            };
+            let name = proxy().wait_schedule_run(sleep.as_millis() as i64);
+            assert_running_status();
+            if !name.is_empty() {
+                if let Err(e) = self.run(&name) {
+                    log::warn!("task {name}: {e:#}");
+                }
+            }
+        }
+    }
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// local time by the device's time zone
+pub fn now() -> DateTime {
+    DateTime::from_unix(unix_now(), utc_offset())
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+fn utc_offset() -> i32 {
+    proxy().utc_offset(unix_now() * 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime {
+        DateTime::new(Date::new(2026, 10, day), hour, minute)
+    }
+
+    fn run(at: DateTime) -> Run {
+        Run {
+            at,
+            millis: 0,
+            error: None,
+        }
+    }
+
+    fn daily() -> Task {
+        Task::new("daily", "0 4 * * *", || Ok(())).unwrap()
+    }
+
+    #[test]
+    fn due_once_per_fire() {
+        let task = daily();
+        // never run, today's 4:00 is within the day looked back
+        assert!(task.is_due(&[], at(19, 10, 0)));
+        assert!(!task.is_due(&[run(at(19, 4, 0))], at(19, 10, 0)));
+        assert!(task.is_due(&[run(at(19, 4, 0))], at(20, 4, 0)));
+        // off for days, runs once
+        assert!(task.is_due(&[run(at(10, 4, 0))], at(19, 10, 0)));
+        assert_eq!(
+            task.next_time(&[run(at(19, 4, 0))], at(19, 10, 0)),
+            Some(at(20, 4, 0))
+        );
+    }
+
+    #[test]
+    fn limits_and_days() {
+        let task = Task::new("often", "0 * * * *", || Ok(()))
+            .unwrap()
+            .daily_limit(2)
+            .first_day(Date::new(2026, 10, 20))
+            .last_day(Date::new(2026, 10, 21));
+        assert!(!task.is_due(&[], at(19, 10, 0)));
+        assert_eq!(task.next_time(&[], at(19, 10, 0)), Some(at(20, 0, 0)));
+        assert!(task.is_due(&[], at(20, 10, 0)));
+
+        let two = [run(at(20, 1, 0)), run(at(20, 2, 0))];
+        assert!(!task.is_due(&two, at(20, 10, 0)));
+        assert_eq!(task.next_time(&two, at(20, 10, 0)), Some(at(21, 0, 0)));
+        assert_eq!(task.next_time(&[], at(22, 0, 0)), None);
+    }
+
+    #[test]
+    fn history_is_kept() {
+        let dir = TempDir::new("schedule");
+        let path = dir.join("schedule.json");
+        let mut schedule = Schedule::at(&path)
+            .task(daily())
+            .task(Task::new("broken", "@hourly", || anyhow::bail!("no stamina")).unwrap());
+        assert_eq!(schedule.due(at(19, 10, 0)), ["daily", "broken"]);
+        schedule.run_at("daily", at(19, 10, 0)).unwrap();
+        assert!(schedule.run_at("broken", at(19, 10, 0)).is_err());
+        assert!(schedule.run_at("missing", at(19, 10, 0)).is_err());
+        assert_eq!(
+            schedule.next_due(at(19, 10, 0)),
+            Some(("broken", at(19, 11, 0)))
+        );
+
+        let schedule = Schedule::at(&path).task(daily());
+        assert_eq!(schedule.runs("daily"), [run(at(19, 10, 0))]);
+        assert_eq!(
+            schedule.runs("broken")[0].error.as_deref(),
+            Some("no stamina")
+        );
+        assert!(schedule.due(at(19, 12, 0)).is_empty());
+        assert_eq!(
+            schedule.status(at(19, 12, 0)),
+            [TaskStatus {
+                name: "daily".into(),
+                cron: "0 4 * * *".into(),
+                due: false,
+                next: Some("2026-10-20 04:00".into()),
+                runs_today: 1,
+                daily_limit: None,
+                first_day: None,
+                last_day: None,
+                last_run: Some("2026-10-19 10:00".into()),
+                last_error: None,
+            }]
+        );
+    }
+}
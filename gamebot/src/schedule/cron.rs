@@ -0,0 +1,359 @@
+// five field cron, "minute hour day-of-month month day-of-week", on local
+// wall clock time at minute precision
+//
+// fields take *, lists, ranges, steps and names (jan, mon). day-of-week 0
+// and 7 are sunday. when both day fields are restricted either one matching
+// is enough, like every cron does
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+// impossible ones like "0 0 30 2 *" give up after this, a 29th of february
+// is at most 8 years away
+const SEARCH_DAYS: i64 = 366 * 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    pub fn new(year: i32, month: u32, day: u32) -> Self {
+        Date { year, month, day }
+    }
+
+    // days since 1970-01-01
+    pub fn days(self) -> i64 {
+        // http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+        let y = self.year as i64 - (self.month <= 2) as i64;
+        let era = y.div_euclid(400);
+        let yoe = y.rem_euclid(400);
+        let m = self.month as i64;
+        let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146097 + doe - 719468
+    }
+
+    pub fn from_days(days: i64) -> Self {
+        // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400 + (month <= 2) as i64) as i32;
+        Date { year, month, day }
+    }
+
+    // 0 is sunday
+    pub fn weekday(self) -> u32 {
+        // 1970-01-01 was a thursday
+        (self.days() + 4).rem_euclid(7) as u32
+    }
+
+    pub fn add_days(self, days: i64) -> Self {
+        Self::from_days(self.days() + days)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct DateTime {
+    pub date: Date,
+    pub hour: u32,
+    pub minute: u32,
+}
+
+impl DateTime {
+    pub fn new(date: Date, hour: u32, minute: u32) -> Self {
+        DateTime { date, hour, minute }
+    }
+
+    // offset is what local time is ahead of utc
+    pub fn from_unix(secs: i64, offset_secs: i32) -> Self {
+        Self::from_minutes((secs + offset_secs as i64).div_euclid(60))
+    }
+
+    pub fn to_unix(self, offset_secs: i32) -> i64 {
+        self.minutes() * 60 - offset_secs as i64
+    }
+
+    // since 1970-01-01 00:00
+    fn minutes(self) -> i64 {
+        self.date.days() * 1440 + self.hour as i64 * 60 + self.minute as i64
+    }
+
+    fn from_minutes(minutes: i64) -> Self {
+        let rest = minutes.rem_euclid(1440);
+        DateTime {
+            date: Date::from_days(minutes.div_euclid(1440)),
+            hour: (rest / 60) as u32,
+            minute: (rest % 60) as u32,
+        }
+    }
+
+    pub fn add_minutes(self, minutes: i64) -> Self {
+        Self::from_minutes(self.minutes() + minutes)
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {:02}:{:02}", self.date, self.hour, self.minute)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cron {
+    // bit n set when n is allowed
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    // not starting with *, decides how the two day fields combine
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl FromStr for Cron {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let expanded = match s.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            x => x,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            bail!("cron {s:?} needs 5 fields, has {}", fields.len());
+        };
+        let field = |text: &str, name: &str, min, max, names: &[&str]| {
+            parse_field(text, min, max, names)
+                .with_context(|| format!("{name} field {text:?} of cron {s:?}"))
+        };
+        let mut weekdays = field(weekday, "day-of-week", 0, 7, &WEEKDAYS)?;
+        // 7 is sunday as well
+        if weekdays & 1 << 7 != 0 {
+            weekdays = weekdays & !(1 << 7) | 1;
+        }
+        Ok(Cron {
+            minutes: field(minute, "minute", 0, 59, &[])?,
+            hours: field(hour, "hour", 0, 23, &[])?,
+            days: field(day, "day-of-month", 1, 31, &[])?,
+            months: field(month, "month", 1, 12, &MONTHS)?,
+            weekdays,
+            days_restricted: !day.starts_with('*'),
+            weekdays_restricted: !weekday.starts_with('*'),
+        })
+    }
+}
+
+impl Cron {
+    pub fn matches(&self, t: DateTime) -> bool {
+        has(self.months, t.date.month)
+            && self.matches_day(t.date)
+            && has(self.hours, t.hour)
+            && has(self.minutes, t.minute)
+    }
+
+    fn matches_day(&self, date: Date) -> bool {
+        let day = has(self.days, date.day);
+        let weekday = has(self.weekdays, date.weekday());
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            (true, false) => day,
+            (false, true) => weekday,
+            (false, false) => true,
+        }
+    }
+
+    // first match strictly later than t
+    pub fn next_after(&self, t: DateTime) -> Option<DateTime> {
+        let mut t = t.add_minutes(1);
+        let end = t.date.days() + SEARCH_DAYS;
+        while t.date.days() <= end {
+            let start_of_day = DateTime::new(t.date, 0, 0);
+            if !has(self.months, t.date.month) {
+                let next_month = Date::new(t.date.year, t.date.month, 1).add_days(31);
+                t = DateTime::new(Date::new(next_month.year, next_month.month, 1), 0, 0);
+            } else if !self.matches_day(t.date) {
+                t = start_of_day.add_minutes(1440);
+            } else if !has(self.hours, t.hour) {
+                t = DateTime::new(t.date, t.hour, 0).add_minutes(60);
+            } else if !has(self.minutes, t.minute) {
+                t = t.add_minutes(1);
+            } else {
+                return Some(t);
+            }
+        }
+        None
+    }
+}
+
+fn has(bits: u64, n: u32) -> bool {
+    bits & 1 << n != 0
+}
+
+// comma separated parts of *, n, a-b, each with an optional /step
+fn parse_field(text: &str, min: u32, max: u32, names: &[&str]) -> anyhow::Result<u64> {
+    let value = |x: &str| -> anyhow::Result<u32> {
+        let lower = x.to_ascii_lowercase();
+        let n = match names.iter().position(|name| *name == lower) {
+            // months are named from 1, weekdays from 0
+            Some(i) => i as u32 + if names.len() == 12 { 1 } else { 0 },
+            None => x
+                .parse()
+                .with_context(|| format!("{x:?} is not a number"))?,
+        };
+        if n < min || n > max {
+            bail!("{n} is outside {min}-{max}");
+        }
+        Ok(n)
+    };
+    let mut bits = 0;
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, Some(step)),
+            None => (part, None),
+        };
+        let step = match step {
+            Some(x) => x.parse().with_context(|| format!("step {x:?}"))?,
+            None => 1,
+        };
+        if step == 0 {
+            bail!("step 0");
+        }
+        let (from, to) = match range {
+            "*" => (min, max),
+            x => match x.split_once('-') {
+                Some((a, b)) => (value(a)?, value(b)?),
+                // n/step runs to the end
+                None if step > 1 => (value(x)?, max),
+                None => (value(x)?, value(x)?),
+            },
+        };
+        if from > to {
+            bail!("{from}-{to} is backwards");
+        }
+        for n in (from..=to).step_by(step as usize) {
+            bits |= 1 << n;
+        }
+    }
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime {
+        DateTime::new(Date::new(year, month, day), hour, minute)
+    }
+
+    fn cron(s: &str) -> Cron {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(Date::new(1970, 1, 1).days(), 0);
+        assert_eq!(Date::new(2000, 2, 29).days(), 11_016);
+        assert_eq!(Date::from_days(11_016), Date::new(2000, 2, 29));
+        assert_eq!(Date::from_days(-1), Date::new(1969, 12, 31));
+        // a monday
+        assert_eq!(Date::new(2026, 10, 19).weekday(), 1);
+        assert_eq!(
+            DateTime::from_unix(1_700_000_000, 8 * 3600),
+            at(2023, 11, 15, 6, 13)
+        );
+        assert_eq!(at(2023, 11, 15, 6, 13).to_unix(8 * 3600), 1_699_999_980);
+    }
+
+    #[test]
+    fn parse_fields() {
+        let x = cron("*/15 4,16 1-7 jan-MAR mon-fri");
+        assert_eq!(x.minutes, 1 << 0 | 1 << 15 | 1 << 30 | 1 << 45);
+        assert_eq!(x.hours, 1 << 4 | 1 << 16);
+        assert_eq!(x.days, 0b1111_1110);
+        assert_eq!(x.months, 0b1110);
+        assert_eq!(x.weekdays, 0b11_1110);
+        assert_eq!(cron("0 0 * * 7").weekdays, 1);
+        assert_eq!(cron("5/20 * * * *").minutes, 1 << 5 | 1 << 25 | 1 << 45);
+        assert_eq!(cron("@daily"), cron("0 0 * * *"));
+
+        for bad in [
+            "",
+            "* * * *",
+            "60 * * * *",
+            "* * 0 * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+        ] {
+            assert!(bad.parse::<Cron>().is_err(), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn next_fire() {
+        let daily = cron("0 4 * * *");
+        assert_eq!(
+            daily.next_after(at(2026, 10, 19, 3, 59)),
+            Some(at(2026, 10, 19, 4, 0))
+        );
+        // strictly after
+        assert_eq!(
+            daily.next_after(at(2026, 10, 19, 4, 0)),
+            Some(at(2026, 10, 20, 4, 0))
+        );
+        // over a year end
+        assert_eq!(
+            cron("30 12 1 * *").next_after(at(2026, 12, 2, 0, 0)),
+            Some(at(2027, 1, 1, 12, 30))
+        );
+        // leap day
+        assert_eq!(
+            cron("0 0 29 2 *").next_after(at(2026, 10, 19, 0, 0)),
+            Some(at(2028, 2, 29, 0, 0))
+        );
+        assert_eq!(cron("0 0 30 2 *").next_after(at(2026, 1, 1, 0, 0)), None);
+    }
+
+    #[test]
+    fn day_fields_combine() {
+        // the 13th or any friday
+        let x = cron("0 0 13 * fri");
+        assert_eq!(
+            x.next_after(at(2026, 10, 19, 0, 0)),
+            Some(at(2026, 10, 23, 0, 0))
+        );
+        assert!(x.matches(at(2026, 11, 13, 0, 0)));
+        // only mondays
+        let x = cron("0 9 * * 1");
+        assert_eq!(
+            x.next_after(at(2026, 10, 19, 9, 0)),
+            Some(at(2026, 10, 26, 9, 0))
+        );
+    }
+}
@@ -1,5 +1,8 @@
+use image::RgbImage;
+
 use crate::color::{
-    ColorPoint, ColorPointGroup, ColorPointGroupIn, ImageIn, Point, Region, Tolerance,
+    Blob, Color, ColorMatch, ColorPoint, ColorPointGroup, ColorPointGroupIn, Histogram, ImageIn,
+    Point, Rect, Region, Tolerance,
 };
 
 #[derive(Default, Debug)]
@@ -70,6 +73,21 @@ impl Screenshot {
         }
     }
 
+    pub fn crop_rgb(&self, region: &Region) -> Option<RgbImage> {
+        if !self.region().contains(region) {
+            return None;
+        }
+        let mut data = Vec::with_capacity((region.width * region.height * 3) as usize);
+        for y in region.top..region.bottom() {
+            let i = ((y * self.width + region.left) * 4) as usize;
+            let j = ((y * self.width + region.right()) * 4) as usize;
+            for pixel in self.data[i..j].chunks_exact(4) {
+                data.extend_from_slice(&pixel[..3]);
+            }
+        }
+        RgbImage::from_raw(region.width, region.height, data)
+    }
+
     pub fn find_all_color_point_group_in(
         &self,
         cpg: &ColorPointGroupIn,
@@ -202,4 +220,211 @@ impl Screenshot {
 
         ans
     }
+
+    pub fn average_color(&self, region: &Region) -> Option<Color> {
+        if !self.region().contains(region) || region.width == 0 || region.height == 0 {
+            return None;
+        }
+        let mut sum = [0u64; 3];
+        for y in region.top..region.bottom() {
+            for x in region.left..region.right() {
+                let i = ((y * self.width + x) * 4) as usize;
+                sum[0] += self.data[i] as u64;
+                sum[1] += self.data[i + 1] as u64;
+                sum[2] += self.data[i + 2] as u64;
+            }
+        }
+        let n = region.width as u64 * region.height as u64;
+        Some(Color {
+            red: (sum[0] / n) as u8,
+            green: (sum[1] / n) as u8,
+            blue: (sum[2] / n) as u8,
+        })
+    }
+
+    pub fn histogram(&self, region: &Region) -> Histogram {
+        let mut ans = Histogram::default();
+        if !self.region().contains(region) {
+            return ans;
+        }
+        for y in region.top..region.bottom() {
+            for x in region.left..region.right() {
+                let i = ((y * self.width + x) * 4) as usize;
+                ans.red[self.data[i] as usize] += 1;
+                ans.green[self.data[i + 1] as usize] += 1;
+                ans.blue[self.data[i + 2] as usize] += 1;
+            }
+        }
+        ans
+    }
+
+    pub fn count_pixels_matching(&self, region: &Region, color: &ColorMatch) -> u32 {
+        if !self.region().contains(region) {
+            return 0;
+        }
+        let mut ans = 0;
+        for y in region.top..region.bottom() {
+            for x in region.left..region.right() {
+                let i = ((y * self.width + x) * 4) as usize;
+                if color.matches(self.data[i], self.data[i + 1], self.data[i + 2]) {
+                    ans += 1;
+                }
+            }
+        }
+        ans
+    }
+
+    // blobs smaller than min_area are dropped
+    pub fn find_blobs(&self, region: &Region, color: &ColorMatch, min_area: u32) -> Vec<Blob> {
+        if !self.region().contains(region) {
+            return vec![];
+        }
+
+        let (w, h) = (region.width as usize, region.height as usize);
+        let mut mask = vec![false; w * h];
+        for y in 0..h {
+            for x in 0..w {
+                let i =
+                    (((y as u32 + region.top) * self.width + x as u32 + region.left) * 4) as usize;
+                mask[y * w + x] = color.matches(self.data[i], self.data[i + 1], self.data[i + 2]);
+            }
+        }
+
+        label_components(&mut mask, w, h)
+            .into_iter()
+            .filter(|blob| blob.area >= min_area)
+            .map(|mut blob| {
+                blob.rect.left += region.left as i32;
+                blob.rect.top += region.top as i32;
+                blob.centroid.x += region.left as i32;
+                blob.centroid.y += region.top as i32;
+                blob
+            })
+            .collect()
+    }
+}
+
+// 8-connected components of a row major mask, the mask is cleared while labeling
+fn label_components(mask: &mut [bool], w: usize, h: usize) -> Vec<Blob> {
+    let mut ans = vec![];
+    let mut stack = vec![];
+    for start in 0..mask.len() {
+        if !mask[start] {
+            continue;
+        }
+        mask[start] = false;
+        stack.push(start);
+
+        let (mut l, mut t, mut r, mut b) = (usize::MAX, usize::MAX, 0, 0);
+        let (mut sx, mut sy, mut area) = (0u64, 0u64, 0u32);
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % w, i / w);
+            l = l.min(x);
+            r = r.max(x);
+            t = t.min(y);
+            b = b.max(y);
+            sx += x as u64;
+            sy += y as u64;
+            area += 1;
+
+            for ny in y.saturating_sub(1)..(y + 2).min(h) {
+                for nx in x.saturating_sub(1)..(x + 2).min(w) {
+                    let j = ny * w + nx;
+                    if mask[j] {
+                        mask[j] = false;
+                        stack.push(j);
+                    }
+                }
+            }
+        }
+
+        ans.push(Blob {
+            rect: Rect {
+                left: l as i32,
+                top: t as i32,
+                width: (r - l + 1) as u32,
+                height: (b - t + 1) as u32,
+            },
+            centroid: Point {
+                x: (sx as f64 / area as f64).round() as i32,
+                y: (sy as f64 / area as f64).round() as i32,
+            },
+            area,
+        });
+    }
+    ans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // black with red pixels at the given spots
+    fn screenshot(width: u32, height: u32, red: &[(u32, u32)]) -> Screenshot {
+        let mut data = vec![0u8; (width * height * 4) as usize];
+        for &(x, y) in red {
+            data[((y * width + x) * 4) as usize] = 255;
+        }
+        Screenshot {
+            width,
+            height,
+            data: Vec::leak(data),
+            timestamp: 0,
+        }
+    }
+
+    fn summary(blobs: &[Blob]) -> Vec<(i32, i32, u32, u32, i32, i32, u32)> {
+        blobs
+            .iter()
+            .map(|x| {
+                let Rect {
+                    left,
+                    top,
+                    width,
+                    height,
+                } = x.rect;
+                (left, top, width, height, x.centroid.x, x.centroid.y, x.area)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn blobs() {
+        let shot = screenshot(
+            8,
+            6,
+            &[
+                // a square with a pixel only touching its corner
+                (1, 1),
+                (2, 1),
+                (1, 2),
+                (2, 2),
+                (3, 3),
+                // apart from it
+                (6, 0),
+                (7, 0),
+                // too small
+                (6, 5),
+            ],
+        );
+        let red = ColorMatch::new((255, 0, 0), 10);
+        assert_eq!(
+            summary(&shot.find_blobs(&shot.region(), &red, 2)),
+            // in row major order of their first pixel
+            [(6, 0, 2, 1, 7, 0, 2), (1, 1, 3, 3, 2, 2, 5)]
+        );
+
+        let right = Region {
+            left: 4,
+            top: 0,
+            width: 4,
+            height: 6,
+        };
+        assert_eq!(
+            summary(&shot.find_blobs(&right, &red, 1)),
+            [(6, 0, 2, 1, 7, 0, 2), (6, 5, 1, 1, 6, 5, 1)]
+        );
+        assert_eq!(shot.histogram(&right).red[255], 3);
+        assert_eq!(shot.histogram(&right).red[0], 21);
+    }
 }
@@ -0,0 +1,266 @@
+// commands like dumpsys, settings, pm or input, with their output
+//
+// the guest lives in the process the host started through root or shizuku,
+// so a command runs as root or shell already. RunAs::Root goes through su
+// for a shizuku guest on a rooted device. stopping the guest kills what is
+// still running
+
+use std::{
+    io::{BufRead, BufReader, Read},
+    os::unix::fs::MetadataExt,
+    process::{Child, Stdio},
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    time::{Duration, Instant},
+};
+
+use crate::api::{assert_running_status, is_stopped_status, Seconds};
+
+const POLL: Duration = Duration::from_millis(20);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum RunAs {
+    // uid of the guest process, root or shell depending on how it was started
+    #[default]
+    Guest,
+    Root,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Line {
+    Stdout(String),
+    Stderr(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Output {
+    // none when killed, by a signal or the timeout
+    pub status: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Output {
+    pub fn success(&self) -> bool {
+        self.status == Some(0)
+    }
+}
+
+pub fn run(cmd: &str, args: &[&str], timeout: impl Seconds) -> anyhow::Result<Output> {
+    Command::new(cmd).args(args).timeout(timeout).run()
+}
+
+pub fn run_root(cmd: &str, args: &[&str], timeout: impl Seconds) -> anyhow::Result<Output> {
+    Command::new(cmd)
+        .args(args)
+        .timeout(timeout)
+        .run_as(RunAs::Root)
+        .run()
+}
+
+pub struct Command {
+    cmd: String,
+    args: Vec<String>,
+    timeout: Option<Duration>,
+    run_as: RunAs,
+}
+
+impl Command {
+    // no timeout until one is set
+    pub fn new(cmd: &str) -> Self {
+        Command {
+            cmd: cmd.into(),
+            args: vec![],
+            timeout: None,
+            run_as: RunAs::Guest,
+        }
+    }
+
+    pub fn arg(mut self, arg: &str) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args(mut self, args: &[&str]) -> Self {
+        self.args.extend(args.iter().map(|x| x.to_string()));
+        self
+    }
+
+    pub fn timeout(mut self, timeout: impl Seconds) -> Self {
+        self.timeout = Some(timeout.into_duration());
+        self
+    }
+
+    pub fn run_as(mut self, run_as: RunAs) -> Self {
+        self.run_as = run_as;
+        self
+    }
+
+    pub fn run(self) -> anyhow::Result<Output> {
+        self.run_streaming(|_| {})
+    }
+
+    // on_line sees each line as it comes, on the calling thread. output is
+    // collected all the same
+    pub fn run_streaming(self, on_line: impl FnMut(Line)) -> anyhow::Result<Output> {
+        self.execute(on_line, is_stopped_status)
+    }
+
+    fn execute(
+        self,
+        mut on_line: impl FnMut(Line),
+        stopped: impl Fn() -> bool,
+    ) -> anyhow::Result<Output> {
+        let mut child = self
+            .command()
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("{}: {e}", self.describe()))?;
+
+        let (sender, lines) = mpsc::channel();
+        read_lines(child.stdout.take().unwrap(), sender.clone(), Line::Stdout);
+        read_lines(child.stderr.take().unwrap(), sender, Line::Stderr);
+
+        let deadline = self.timeout.and_then(|x| Instant::now().checked_add(x));
+        let mut output = Output::default();
+        let mut take = |(line, raw): (Line, String)| {
+            match &line {
+                Line::Stdout(_) => output.stdout.push_str(&raw),
+                Line::Stderr(_) => output.stderr.push_str(&raw),
+            }
+            on_line(line);
+        };
+        let status = loop {
+            match lines.recv_timeout(POLL) {
+                Ok(x) => {
+                    take(x);
+                    continue;
+                }
+                // both pipes closed
+                Err(RecvTimeoutError::Disconnected) => break child.wait()?.code(),
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+            if stopped() {
+                kill(&mut child);
+                assert_running_status();
+            }
+            if deadline.is_some_and(|x| Instant::now() >= x) {
+                log::warn!("{} timed out", self.describe());
+                kill(&mut child);
+                break None;
+            }
+            // exited, something it left behind may hold the pipes
+            if let Some(status) = child.try_wait()? {
+                break status.code();
+            }
+        };
+        // what the readers still have in hand
+        while let Ok(x) = lines.recv_timeout(POLL) {
+            take(x);
+        }
+        output.status = status;
+        Ok(output)
+    }
+
+    fn command(&self) -> std::process::Command {
+        if self.run_as == RunAs::Root && !is_root() {
+            let mut command = std::process::Command::new("su");
+            command.arg("-c").arg(self.describe());
+            command
+        } else {
+            let mut command = std::process::Command::new(&self.cmd);
+            command.args(&self.args);
+            command
+        }
+    }
+
+    fn describe(&self) -> String {
+        std::iter::once(&self.cmd)
+            .chain(&self.args)
+            .map(|x| quote(x))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+fn kill(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+fn is_root() -> bool {
+    std::fs::metadata("/proc/self").is_ok_and(|x| x.uid() == 0)
+}
+
+// each line is sent with its text as read, newline included, for Output
+fn read_lines(
+    pipe: impl Read + Send + 'static,
+    sender: Sender<(Line, String)>,
+    line: fn(String) -> Line,
+) {
+    std::thread::spawn(move || {
+        let mut pipe = BufReader::new(pipe);
+        let mut buf = vec![];
+        loop {
+            buf.clear();
+            match pipe.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {
+                    let raw = String::from_utf8_lossy(&buf).into_owned();
+                    let text = raw.trim_end_matches(['\n', '\r']).to_string();
+                    if sender.send((line(text), raw)).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    });
+}
+
+// for sh -c, left alone when nothing in it needs quoting
+fn quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        arg.into()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_for_su() {
+        let cmd = Command::new("settings").args(&["put", "system", "x", "it's a b", ""]);
+        assert_eq!(cmd.describe(), r"settings put system x 'it'\''s a b' ''");
+    }
+
+    #[test]
+    fn output_and_lines() {
+        let mut lines = vec![];
+        let output = Command::new("sh")
+            .args(&["-c", "echo a; echo b >&2; printf c; exit 3"])
+            .execute(|x| lines.push(x), || false)
+            .unwrap();
+        assert_eq!(output.status, Some(3));
+        assert_eq!(output.stdout, "a\nc");
+        assert_eq!(output.stderr, "b\n");
+        assert!(lines.contains(&Line::Stdout("c".into())));
+        assert!(lines.contains(&Line::Stderr("b".into())));
+    }
+
+    #[test]
+    fn timeout_kills() {
+        let start = Instant::now();
+        let output = Command::new("sleep")
+            .arg("5")
+            .timeout(Duration::from_millis(100))
+            .execute(|_| {}, || false)
+            .unwrap();
+        assert_eq!(output.status, None);
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
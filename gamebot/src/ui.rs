@@ -3,14 +3,32 @@ use std::{
     collections::HashMap,
     default,
     marker::PhantomData,
-    sync::mpsc::{Receiver, Sender},
+    ops::RangeInclusive,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{Receiver, Sender},
+        Arc,
+    },
     thread::JoinHandle,
 };
 
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 use crate::api::proxy;
 
+mod config;
+mod diff;
+mod persist;
+mod tester;
+#[cfg(feature = "web")]
+mod web;
+
+pub use config::{config_form, ConfigUI, Field, Lens};
+pub(crate) use diff::Patch;
+pub use gamebot_derive::ConfigUI;
+pub use persist::Persist;
+pub use tester::UiTester;
+
 #[typetag::serialize(tag = "type")]
 trait View<State> {
     fn take_callback(&mut self) -> Option<CallbackFunc<State>> {
@@ -28,6 +46,7 @@ trait View<State> {
     {
         Element {
             item: Box::new(self),
+            key: None,
         }
     }
 }
@@ -57,6 +76,10 @@ impl CallbackValue for String {}
 pub struct Element<State> {
     #[serde(flatten)]
     item: Box<dyn View<State>>,
+    // identity among siblings, so a list keeps diffing and callback ids per
+    // item when items are inserted, removed or reordered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
     // #[serde(skip)]
     // ty: PhantomData<State>,
 }
@@ -77,19 +100,83 @@ pub enum UIEvent<State> {
 type CallbackFunc<State> = Box<dyn Fn(&mut State, Box<dyn CallbackValue>, UIContext<State>) + Send>;
 
 impl<State> Element<State> {
+    pub fn key(mut self, key: impl ToString) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    // callbacks by id, numbered from 0 in tree order
     pub fn collect_callback(&mut self) -> Vec<CallbackFunc<State>> {
-        let mut ans: Vec<CallbackFunc<State>> = vec![];
-        let mut stack = vec![self];
-        while !stack.is_empty() {
-            for Element { item } in std::mem::take(&mut stack) {
-                if let Some(callback) = item.take_callback() {
-                    item.set_callback_id(ans.len());
-                    ans.push(callback);
-                }
-                stack.extend(item.children_mut());
+        let mut callback = self.collect_callback_with(&mut CallbackIds::default());
+        (0..callback.len())
+            .map(|id| callback.remove(&id).unwrap())
+            .collect()
+    }
+
+    fn collect_callback_with(
+        &mut self,
+        ids: &mut CallbackIds,
+    ) -> HashMap<usize, CallbackFunc<State>> {
+        let mut ans = HashMap::new();
+        self.walk_callback(String::new(), ids, &mut ans);
+        ids.finish();
+        ans
+    }
+
+    // path is made of keys where siblings have unique ones, else of indices
+    fn walk_callback(
+        &mut self,
+        path: String,
+        ids: &mut CallbackIds,
+        ans: &mut HashMap<usize, CallbackFunc<State>>,
+    ) {
+        let path = format!("{path}/{}", self.item.typetag_name());
+        if let Some(callback) = self.item.take_callback() {
+            let id = ids.get(&path);
+            self.item.set_callback_id(id);
+            ans.insert(id, callback);
+        }
+        let children = self.item.children_mut();
+        let mut count = HashMap::new();
+        for child in &children {
+            if let Some(key) = &child.key {
+                *count.entry(key.clone()).or_insert(0) += 1;
             }
         }
-        ans
+        for (i, child) in children.into_iter().enumerate() {
+            let seg = match &child.key {
+                Some(key) if count[key] == 1 => format!("{key:?}"),
+                _ => i.to_string(),
+            };
+            child.walk_callback(format!("{path}/{seg}"), ids, ans);
+        }
+    }
+}
+
+// ids by element path, kept while the path is rendered and never reused after,
+// so an event sent before a rerender can't reach another element's callback
+#[derive(Default)]
+struct CallbackIds {
+    last: HashMap<String, usize>,
+    current: HashMap<String, usize>,
+    next: usize,
+}
+
+impl CallbackIds {
+    fn get(&mut self, path: &str) -> usize {
+        if let Some(&id) = self.current.get(path) {
+            return id;
+        }
+        let id = self.last.get(path).copied().unwrap_or_else(|| {
+            self.next += 1;
+            self.next - 1
+        });
+        self.current.insert(path.to_string(), id);
+        id
+    }
+
+    fn finish(&mut self) {
+        self.last = std::mem::take(&mut self.current);
     }
 }
 
@@ -234,22 +321,382 @@ where
     .into_element()
 }
 
+#[derive(Serialize)]
+pub struct Switch<State: Serialize> {
+    label: String,
+    value: bool,
+    #[serde(skip)]
+    callback: Option<CallbackFunc<State>>,
+    #[serde(rename = "callbackId")]
+    callback_id: usize,
+}
+
+#[typetag::serialize]
+impl<State: Serialize> View<State> for Switch<State> {
+    fn take_callback(&mut self) -> Option<CallbackFunc<State>> {
+        self.callback.take()
+    }
+    fn set_callback_id(&mut self, id: usize) {
+        self.callback_id = id
+    }
+}
+
+pub fn switch<State, Callback>(
+    label: impl ToString,
+    value: bool,
+    callback: Callback,
+) -> Element<State>
+where
+    State: 'static + Serialize,
+    Callback: Fn(&mut State, bool, UIContext<State>) + Send + 'static,
+{
+    Switch {
+        label: label.to_string(),
+        value,
+        callback: Some(Box::new(
+            move |state: &mut State, new: Box<dyn CallbackValue>, ui: UIContext<State>| {
+                if let Ok(new) = (new as Box<dyn Any>).downcast::<bool>() {
+                    callback(state, *new, ui)
+                }
+            },
+        )),
+        callback_id: 0,
+    }
+    .into_element()
+}
+
+#[derive(Serialize)]
+pub struct Select<State: Serialize> {
+    label: String,
+    selected: usize,
+    options: Vec<String>,
+    #[serde(skip)]
+    callback: Option<CallbackFunc<State>>,
+    #[serde(rename = "callbackId")]
+    callback_id: usize,
+}
+
+#[typetag::serialize]
+impl<State: Serialize> View<State> for Select<State> {
+    fn take_callback(&mut self) -> Option<CallbackFunc<State>> {
+        self.callback.take()
+    }
+    fn set_callback_id(&mut self, id: usize) {
+        self.callback_id = id
+    }
+}
+
+// callback gets index of the picked option
+pub fn select<State, Callback>(
+    label: impl ToString,
+    options: impl IntoIterator<Item = impl ToString>,
+    selected: usize,
+    callback: Callback,
+) -> Element<State>
+where
+    State: 'static + Serialize,
+    Callback: Fn(&mut State, usize, UIContext<State>) + Send + 'static,
+{
+    Select {
+        label: label.to_string(),
+        selected,
+        options: options.into_iter().map(|x| x.to_string()).collect(),
+        callback: Some(Box::new(
+            move |state: &mut State, new: Box<dyn CallbackValue>, ui: UIContext<State>| {
+                if let Ok(new) = (new as Box<dyn Any>).downcast::<usize>() {
+                    callback(state, *new, ui)
+                }
+            },
+        )),
+        callback_id: 0,
+    }
+    .into_element()
+}
+
+#[derive(Serialize)]
+pub struct Section<State> {
+    title: String,
+    content: Vec<Element<State>>,
+}
+
+#[typetag::serialize]
+impl<State: Serialize> View<State> for Section<State> {
+    fn children_mut(&mut self) -> Vec<&mut Element<State>> {
+        self.content.iter_mut().collect()
+    }
+}
+
+// titled group of elements, empty title draws the card only
+pub fn section<State>(
+    title: impl ToString,
+    content: impl IntoIterator<Item = Element<State>>,
+) -> Element<State>
+where
+    State: 'static + Serialize,
+{
+    Section {
+        title: title.to_string(),
+        content: content.into_iter().collect(),
+    }
+    .into_element()
+}
+
+#[derive(Serialize)]
+pub struct Slider<State: Serialize> {
+    label: String,
+    value: f64,
+    min: f64,
+    max: f64,
+    // 0 is continuous
+    step: f64,
+    #[serde(skip)]
+    callback: Option<CallbackFunc<State>>,
+    #[serde(rename = "callbackId")]
+    callback_id: usize,
+}
+
+#[typetag::serialize]
+impl<State: Serialize> View<State> for Slider<State> {
+    fn take_callback(&mut self) -> Option<CallbackFunc<State>> {
+        self.callback.take()
+    }
+    fn set_callback_id(&mut self, id: usize) {
+        self.callback_id = id
+    }
+}
+
+// value sent back is clamped to range and snapped to step
+pub fn slider<State, Callback>(
+    label: impl ToString,
+    value: f64,
+    range: RangeInclusive<f64>,
+    step: f64,
+    callback: Callback,
+) -> Element<State>
+where
+    State: 'static + Serialize,
+    Callback: Fn(&mut State, f64, UIContext<State>) + Send + 'static,
+{
+    let (min, max) = (*range.start(), *range.end());
+    Slider {
+        label: label.to_string(),
+        value,
+        min,
+        max,
+        step,
+        callback: Some(Box::new(
+            move |state: &mut State, new: Box<dyn CallbackValue>, ui: UIContext<State>| {
+                if let Ok(new) = (new as Box<dyn Any>).downcast::<f64>() {
+                    let mut new = new.clamp(min, max);
+                    if step > 0.0 {
+                        new = (min + ((new - min) / step).round() * step).min(max);
+                    }
+                    callback(state, new, ui)
+                }
+            },
+        )),
+        callback_id: 0,
+    }
+    .into_element()
+}
+
+#[derive(Serialize)]
+pub struct Checkbox<State: Serialize> {
+    label: String,
+    value: bool,
+    #[serde(skip)]
+    callback: Option<CallbackFunc<State>>,
+    #[serde(rename = "callbackId")]
+    callback_id: usize,
+}
+
+#[typetag::serialize]
+impl<State: Serialize> View<State> for Checkbox<State> {
+    fn take_callback(&mut self) -> Option<CallbackFunc<State>> {
+        self.callback.take()
+    }
+    fn set_callback_id(&mut self, id: usize) {
+        self.callback_id = id
+    }
+}
+
+pub fn checkbox<State, Callback>(
+    label: impl ToString,
+    value: bool,
+    callback: Callback,
+) -> Element<State>
+where
+    State: 'static + Serialize,
+    Callback: Fn(&mut State, bool, UIContext<State>) + Send + 'static,
+{
+    Checkbox {
+        label: label.to_string(),
+        value,
+        callback: Some(Box::new(
+            move |state: &mut State, new: Box<dyn CallbackValue>, ui: UIContext<State>| {
+                if let Ok(new) = (new as Box<dyn Any>).downcast::<bool>() {
+                    callback(state, *new, ui)
+                }
+            },
+        )),
+        callback_id: 0,
+    }
+    .into_element()
+}
+
+#[derive(Serialize)]
+pub struct Row<State> {
+    content: Vec<Element<State>>,
+}
+
+#[typetag::serialize]
+impl<State: Serialize> View<State> for Row<State> {
+    fn children_mut(&mut self) -> Vec<&mut Element<State>> {
+        self.content.iter_mut().collect()
+    }
+}
+
+pub fn row<State>(content: impl IntoIterator<Item = Element<State>>) -> Element<State>
+where
+    State: 'static + Serialize,
+{
+    Row {
+        content: content.into_iter().collect(),
+    }
+    .into_element()
+}
+
+#[derive(Serialize)]
+pub struct LazyList<State> {
+    content: Vec<Element<State>>,
+}
+
+#[typetag::serialize]
+impl<State: Serialize> View<State> for LazyList<State> {
+    fn children_mut(&mut self) -> Vec<&mut Element<State>> {
+        self.content.iter_mut().collect()
+    }
+}
+
+// scrollable column, host only composes items on screen
+pub fn lazy_list<State>(content: impl IntoIterator<Item = Element<State>>) -> Element<State>
+where
+    State: 'static + Serialize,
+{
+    LazyList {
+        content: content.into_iter().collect(),
+    }
+    .into_element()
+}
+
+#[derive(Serialize)]
+struct Divider<State> {
+    #[serde(skip)]
+    ty: PhantomData<State>,
+}
+
+#[typetag::serialize]
+impl<State: Serialize> View<State> for Divider<State> {}
+
+pub fn divider<State: 'static + Serialize>() -> Element<State> {
+    Divider { ty: PhantomData }.into_element()
+}
+
+#[derive(Serialize)]
+struct Image<State> {
+    // encoded png / jpeg, base64 in json
+    data: String,
+    #[serde(skip)]
+    ty: PhantomData<State>,
+}
+
+#[typetag::serialize]
+impl<State: Serialize> View<State> for Image<State> {}
+
+pub fn image<State: 'static + Serialize>(bytes: &[u8]) -> Element<State> {
+    Image {
+        data: base64(bytes),
+        ty: PhantomData,
+    }
+    .into_element()
+}
+
+#[derive(Serialize)]
+struct Progress<State> {
+    // 0.0 to 1.0, none spins
+    value: Option<f32>,
+    #[serde(skip)]
+    ty: PhantomData<State>,
+}
+
+#[typetag::serialize]
+impl<State: Serialize> View<State> for Progress<State> {}
+
+pub fn progress<State: 'static + Serialize>(value: Option<f32>) -> Element<State> {
+    Progress {
+        value: value.map(|x| x.clamp(0.0, 1.0)),
+        ty: PhantomData,
+    }
+    .into_element()
+}
+
+pub(crate) fn base64(bytes: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut ans = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                ans.push(TABLE[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                ans.push('=');
+            }
+        }
+    }
+    ans
+}
+
 pub struct UI<State> {
     state: State,
     view: Box<dyn Fn(&mut State, UIContext<State>) -> Element<State>>,
-    callback: Vec<CallbackFunc<State>>,
+    callback: HashMap<usize, CallbackFunc<State>>,
+    callback_ids: CallbackIds,
+    // last tree sent to the host
+    tree: Option<serde_json::Value>,
     event_receiver: Receiver<UIEvent<State>>,
     event_sender: Sender<UIEvent<State>>,
+    persist: Option<Persist>,
+    saver: Option<persist::Saver>,
+    frontend: Frontend,
+}
+
+// where the tree is shown and events come from
+#[derive(Clone)]
+enum Frontend {
+    // compose in the host app
+    Host,
+    // browsers connected to an embedded server, see UI::serve
+    #[cfg(feature = "web")]
+    Web(Arc<web::Server>),
+    // UiTester, counts spawned threads still running
+    Headless(Arc<AtomicUsize>),
 }
 
 // #[derive(Clone)]
 pub struct UIContext<State> {
     event_sender: Sender<UIEvent<State>>,
+    frontend: Frontend,
 }
 
 impl<State: Send + 'static> UIContext<State> {
+    // wake the render loop, only the host waits elsewhere than on event_sender
     pub fn rerender(&self) {
-        proxy().send_empty_config_ui_event()
+        if let Frontend::Host = self.frontend {
+            proxy().send_empty_config_ui_event()
+        }
     }
     pub fn exit(&self) {
         self.event_sender.send(UIEvent::Exit).unwrap();
@@ -264,8 +711,32 @@ impl<State: Send + 'static> UIContext<State> {
     pub fn spawn(&self, f: impl FnOnce(UIContext<State>) + Send + 'static) -> JoinHandle<()> {
         let ctx = UIContext {
             event_sender: self.event_sender.clone(),
+            frontend: self.frontend.clone(),
+        };
+        let running = match &self.frontend {
+            Frontend::Headless(count) => Some(Running::new(count.clone())),
+            _ => None,
         };
-        std::thread::spawn(move || f(ctx))
+        std::thread::spawn(move || {
+            let _running = running;
+            f(ctx)
+        })
+    }
+}
+
+// counts a spawned thread until it returns or panics
+struct Running(Arc<AtomicUsize>);
+
+impl Running {
+    fn new(count: Arc<AtomicUsize>) -> Self {
+        count.fetch_add(1, Ordering::SeqCst);
+        Running(count)
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
     }
 }
 
@@ -278,51 +749,165 @@ impl<State: Serialize> UI<State> {
         UI {
             state,
             view: Box::new(view),
-            callback: vec![],
+            callback: HashMap::new(),
+            callback_ids: CallbackIds::default(),
+            tree: None,
             event_receiver,
             event_sender,
+            persist: None,
+            saver: None,
+            frontend: Frontend::Host,
+        }
+    }
+
+    fn context(&self) -> UIContext<State> {
+        UIContext {
+            event_sender: self.event_sender.clone(),
+            frontend: self.frontend.clone(),
         }
     }
 
     fn render(&mut self) {
-        let mut view = (self.view)(
-            &mut self.state,
-            UIContext {
-                event_sender: self.event_sender.clone(),
-            },
-        );
-        self.callback = view.collect_callback();
-        proxy().set_config_ui(view);
+        let ui = self.context();
+        let mut view = (self.view)(&mut self.state, ui);
+        self.callback = view.collect_callback_with(&mut self.callback_ids);
+        let tree = serde_json::to_value(&view).unwrap();
+        let patch = self.tree.as_ref().map(|old| diff::diff(old, &tree));
+        match (&self.frontend, patch) {
+            (Frontend::Headless(_), _) => {}
+            #[cfg(feature = "web")]
+            (Frontend::Web(server), patch) => server.publish(&tree, patch),
+            (Frontend::Host, Some(patch)) => {
+                if !patch.is_empty() {
+                    proxy().patch_config_ui(&patch);
+                }
+            }
+            (Frontend::Host, None) => proxy().set_config_ui(&tree),
+        }
+        self.tree = Some(tree);
     }
 
     pub fn into_state(self) -> State {
         self.state
     }
 
+    // versioned json of the current state, see Persist::export
+    pub fn export(&self) -> anyhow::Result<String> {
+        self.codec().export(&self.state)
+    }
+
+    fn codec(&self) -> Persist {
+        self.persist.clone().unwrap_or_else(|| Persist::at(""))
+    }
+
+    // queue a save if persisted, the saver thread debounces
+    fn changed(&self) {
+        let Some(saver) = &self.saver else {
+            return;
+        };
+        match self.codec().export(&self.state) {
+            Ok(json) => saver.send(json),
+            Err(e) => log::warn!("export ui state: {e:#}"),
+        }
+    }
+
     pub fn enter_render_loop(&mut self) {
-        'outer: loop {
+        loop {
             self.render();
 
-            let event = proxy()
-                .wait_config_ui_event()
-                .into_iter()
-                .chain(self.event_receiver.try_iter());
-
-            for event in event {
-                match event {
-                    UIEvent::Empty => continue,
-                    UIEvent::Exit => break 'outer,
-                    UIEvent::Callback { id, value } => (self.callback[id])(
-                        &mut self.state,
-                        value,
-                        UIContext {
-                            event_sender: self.event_sender.clone(),
-                        },
-                    ),
-                    UIEvent::Update(f) => f(&mut self.state),
+            let mut event = match self.frontend {
+                Frontend::Host => proxy().wait_config_ui_event(),
+                // anything else sends to event_sender, which self holds so recv can't fail
+                _ => self.event_receiver.recv().into_iter().collect(),
+            };
+            event.extend(self.event_receiver.try_iter());
+            if self.handle(event) {
+                break;
+            }
+        }
+    }
+
+    // apply a batch of events, true on exit
+    fn handle(&mut self, event: Vec<UIEvent<State>>) -> bool {
+        let (mut changed, mut exit) = (false, false);
+        for event in event {
+            match event {
+                UIEvent::Empty => continue,
+                UIEvent::Exit => {
+                    exit = true;
+                    break;
+                }
+                UIEvent::Callback { id, value } => {
+                    let Some(callback) = self.callback.get(&id) else {
+                        // element went away before the event arrived
+                        continue;
+                    };
+                    let ui = self.context();
+                    callback(&mut self.state, value, ui)
                 }
+                UIEvent::Update(f) => f(&mut self.state),
             }
+            changed = true;
         }
+        if changed {
+            self.changed();
+        }
+        exit
+    }
+}
+
+#[cfg(feature = "web")]
+impl<State: Serialize + 'static> UI<State> {
+    // render to browsers at addr instead of the host app. "0.0.0.0:8080"
+    // makes it reachable from a pc, so anyone on that network can edit state
+    pub fn serve(mut self, addr: impl std::net::ToSocketAddrs) -> anyhow::Result<Self> {
+        let sender = self.event_sender.clone();
+        let server = web::Server::start(addr, move |text| {
+            match serde_json::from_str::<UIEvent<State>>(text)? {
+                event @ UIEvent::Callback { .. } => sender
+                    .send(event)
+                    .map_err(|_| anyhow::anyhow!("ui is gone")),
+                _ => anyhow::bail!("only callbacks are taken from browsers"),
+            }
+        })?;
+        self.frontend = Frontend::Web(Arc::new(server));
+        // first render publishes the whole tree
+        self.tree = None;
+        Ok(self)
+    }
+
+    pub fn web_addr(&self) -> Option<std::net::SocketAddr> {
+        match &self.frontend {
+            Frontend::Web(server) => Some(server.addr()),
+            _ => None,
+        }
+    }
+}
+
+impl<State: Serialize + DeserializeOwned> UI<State> {
+    // start from what was saved last time if any, and save every change from now on.
+    // a file that fails to load is moved aside to <path>.broken rather than overwritten
+    pub fn persist(mut self, persist: Persist) -> Self {
+        match persist.load() {
+            Ok(Some(state)) => self.state = state,
+            Ok(None) => {}
+            Err(e) => {
+                log::warn!("{e:#}, starting from default");
+                let mut broken = persist.path().to_path_buf().into_os_string();
+                broken.push(".broken");
+                let _ = std::fs::rename(persist.path(), broken);
+            }
+        }
+        self.saver = Some(persist::Saver::new(persist.clone()));
+        self.persist = Some(persist);
+        self
+    }
+
+    // replace state by an export, migrated to the current version
+    pub fn import(&mut self, json: &str) -> anyhow::Result<()> {
+        self.state = self.codec().import(json)?;
+        self.changed();
+        Ok(())
     }
 }
 
@@ -378,13 +963,19 @@ impl<Key: Serialize + Default> NavController<Key> {
 }
 
 pub fn nav_host<Key: Default, State>(
-    children: HashMap<Key, Element<State>>,
+    mut children: HashMap<Key, Element<State>>,
     controller: NavController<Key>,
 ) -> Element<State>
 where
     Key: 'static + Serialize,
     State: 'static + Serialize,
 {
+    // map order changes between renders, callback ids go by destination
+    for (key, child) in children.iter_mut() {
+        if child.key.is_none() {
+            child.key = serde_json::to_string(key).ok();
+        }
+    }
     NavHost {
         children,
         controller,
@@ -409,3 +1000,120 @@ pub fn web_view<State: 'static + Serialize>(url: impl ToString) -> Element<State
     }
     .into_element()
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn widget_json() {
+        let mut view: Element<()> = col([
+            slider("volume", 0.5, 0.0..=1.0, 0.1, |_, _, _| {}),
+            row([checkbox("mute", true, |_, _, _| {}), divider()]),
+            lazy_list([progress(Some(2.0)), progress(None)]),
+            image(b"Man"),
+        ]);
+        view.collect_callback();
+        assert_eq!(
+            serde_json::to_value(&view).unwrap(),
+            json!({
+                "type": "Column",
+                "content": [
+                    {
+                        "type": "Slider",
+                        "label": "volume",
+                        "value": 0.5,
+                        "min": 0.0,
+                        "max": 1.0,
+                        "step": 0.1,
+                        "callbackId": 0
+                    },
+                    {
+                        "type": "Row",
+                        "content": [
+                            {"type": "Checkbox", "label": "mute", "value": true, "callbackId": 1},
+                            {"type": "Divider"}
+                        ]
+                    },
+                    {
+                        "type": "LazyList",
+                        "content": [
+                            {"type": "Progress", "value": 1.0},
+                            {"type": "Progress", "value": null}
+                        ]
+                    },
+                    {"type": "Image", "data": "TWFu"}
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn slider_snaps_into_range() {
+        let mut value = 0.0;
+        for (input, expect) in [(0.34, 0.25), (-1.0, 0.0), (0.9, 0.75)] {
+            let mut view = slider("x", value, 0.0..=0.8, 0.25, |state: &mut f64, new, _| {
+                *state = new
+            });
+            let callback = view.collect_callback();
+            let (event_sender, _) = std::sync::mpsc::channel();
+            callback[0](
+                &mut value,
+                Box::new(input),
+                UIContext {
+                    event_sender,
+                    frontend: Frontend::Headless(Default::default()),
+                },
+            );
+            assert_eq!(value, expect);
+        }
+    }
+
+    #[test]
+    fn callback_ids_follow_keys() {
+        fn view(keys: &[&str]) -> Element<()> {
+            col(keys
+                .iter()
+                .map(|&k| button(k, |_, _| {}).key(k))
+                .chain([switch("s", false, |_, _, _| {})]))
+        }
+        fn ids(view: &Element<()>) -> HashMap<String, u64> {
+            let json = serde_json::to_value(view).unwrap();
+            json["content"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|x| {
+                    let name = x["key"].as_str().unwrap_or("switch").to_string();
+                    (name, x["callbackId"].as_u64().unwrap())
+                })
+                .collect()
+        }
+
+        let mut callback_ids = CallbackIds::default();
+        let mut first = view(&["a", "b", "c"]);
+        first.collect_callback_with(&mut callback_ids);
+        let first = ids(&first);
+
+        let mut second = view(&["c", "d", "a"]);
+        second.collect_callback_with(&mut callback_ids);
+        let second = ids(&second);
+        assert_eq!(second["a"], first["a"]);
+        assert_eq!(second["c"], first["c"]);
+        // b is retired, d gets a fresh id
+        assert!(first.values().all(|&id| id != second["d"]));
+        // unkeyed, so its index is its path
+        assert_eq!(second["switch"], first["switch"]);
+    }
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"M"), "TQ==");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(&[0xff, 0xfe, 0xfd, 0x00]), "//79AA==");
+    }
+}
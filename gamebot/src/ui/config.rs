@@ -0,0 +1,358 @@
+// config structs rendered as forms, usually through #[derive(ConfigUI)]
+//
+// every element gets a Lens from the root state down to the value it edits,
+// so callbacks can write back without knowing where the value lives
+
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use super::{button, col, section, slider, switch, text, text_field, Element, UIContext};
+
+// path from State to a T inside it, None once the target is gone,
+// e.g. an item removed from a list by an earlier event of the same batch
+pub struct Lens<State, T> {
+    get: Getter<State, T>,
+}
+
+type Getter<State, T> = Arc<dyn Fn(&mut State) -> Option<&mut T> + Send + Sync>;
+
+impl<State, T> Clone for Lens<State, T> {
+    fn clone(&self) -> Self {
+        Lens {
+            get: self.get.clone(),
+        }
+    }
+}
+
+impl<State: 'static> Lens<State, State> {
+    pub fn root() -> Self {
+        Lens::new(|state| Some(state))
+    }
+}
+
+impl<State: 'static, T: 'static> Lens<State, T> {
+    pub fn new(get: impl Fn(&mut State) -> Option<&mut T> + Send + Sync + 'static) -> Self {
+        Lens { get: Arc::new(get) }
+    }
+
+    pub fn get<'a>(&self, state: &'a mut State) -> Option<&'a mut T> {
+        (self.get)(state)
+    }
+
+    pub fn field<U: 'static>(
+        &self,
+        field: impl Fn(&mut T) -> &mut U + Send + Sync + 'static,
+    ) -> Lens<State, U> {
+        let parent = self.clone();
+        Lens::new(move |state| parent.get(state).map(&field))
+    }
+
+    // write new value if the target still exists
+    pub fn set(&self, state: &mut State, value: T) {
+        if let Some(x) = self.get(state) {
+            *x = value;
+        }
+    }
+}
+
+impl<State: 'static, T: 'static> Lens<State, Vec<T>> {
+    pub fn index(&self, i: usize) -> Lens<State, T> {
+        let parent = self.clone();
+        Lens::new(move |state| parent.get(state).and_then(|x| x.get_mut(i)))
+    }
+}
+
+// from field attributes, #[config(label = "..", range = 1..=10)]
+#[derive(Debug, Clone, Default)]
+pub struct Field {
+    pub label: String,
+    pub range: Option<(f64, f64)>,
+}
+
+impl Field {
+    pub fn new(label: impl ToString) -> Self {
+        Field {
+            label: label.to_string(),
+            range: None,
+        }
+    }
+
+    pub fn range(mut self, min: f64, max: f64) -> Self {
+        self.range = Some((min, max));
+        self
+    }
+}
+
+pub trait ConfigUI {
+    fn config_ui<State: Serialize + 'static>(
+        &self,
+        field: &Field,
+        lens: Lens<State, Self>,
+    ) -> Element<State>
+    where
+        Self: Sized;
+}
+
+// view for UI::new when the whole state is the config
+pub fn config_form<State: ConfigUI + Serialize + 'static>(
+    state: &mut State,
+    _: UIContext<State>,
+) -> Element<State> {
+    state.config_ui(&Field::default(), Lens::root())
+}
+
+impl ConfigUI for bool {
+    fn config_ui<State: Serialize + 'static>(
+        &self,
+        field: &Field,
+        lens: Lens<State, Self>,
+    ) -> Element<State> {
+        switch(&field.label, *self, move |state, new, _| {
+            lens.set(state, new)
+        })
+    }
+}
+
+impl ConfigUI for String {
+    fn config_ui<State: Serialize + 'static>(
+        &self,
+        field: &Field,
+        lens: Lens<State, Self>,
+    ) -> Element<State> {
+        col([
+            text(&field.label),
+            text_field(self, move |state, new, _| lens.set(state, new)),
+        ])
+    }
+}
+
+// slider when range is bounded, else typed as text where input that doesn't
+// parse is ignored and range is clamped to
+macro_rules! number_config_ui {
+    ($($ty:ty => $step:expr),*) => {$(
+        impl ConfigUI for $ty {
+            fn config_ui<State: Serialize + 'static>(
+                &self,
+                field: &Field,
+                lens: Lens<State, Self>,
+            ) -> Element<State> {
+                match field.range {
+                    Some((min, max)) if min.is_finite() && max.is_finite() => {
+                        slider(&field.label, *self as f64, min..=max, $step, move |state, new, _| {
+                            lens.set(state, new as $ty)
+                        })
+                    }
+                    range => col([
+                        text(&field.label),
+                        text_field(self, move |state, new: String, _| {
+                            let Ok(mut new) = new.trim().parse::<$ty>() else {
+                                return;
+                            };
+                            if let Some((min, max)) = range {
+                                new = (new as f64).clamp(min, max) as $ty;
+                            }
+                            lens.set(state, new);
+                        }),
+                    ]),
+                }
+            }
+        }
+    )*};
+}
+
+number_config_ui!(
+    u8 => 1.0, u16 => 1.0, u32 => 1.0, u64 => 1.0, usize => 1.0,
+    i8 => 1.0, i16 => 1.0, i32 => 1.0, i64 => 1.0, isize => 1.0,
+    f32 => 0.0, f64 => 0.0
+);
+
+// one section per item with a remove button, and a button appending T::default()
+impl<T: ConfigUI + Default + 'static> ConfigUI for Vec<T> {
+    fn config_ui<State: Serialize + 'static>(
+        &self,
+        field: &Field,
+        lens: Lens<State, Self>,
+    ) -> Element<State> {
+        let mut content: Vec<_> = self
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let remove = lens.clone();
+                section(
+                    "",
+                    [
+                        item.config_ui(
+                            &Field::new(format!("{} {}", field.label, i + 1)),
+                            lens.index(i),
+                        ),
+                        button("remove", move |state: &mut State, _| {
+                            if let Some(list) = remove.get(state) {
+                                if i < list.len() {
+                                    list.remove(i);
+                                }
+                            }
+                        }),
+                    ],
+                )
+            })
+            .collect();
+        content.push(button("add", move |state: &mut State, _| {
+            if let Some(list) = lens.get(state) {
+                list.push(T::default());
+            }
+        }));
+        section(&field.label, content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::*;
+    use crate::ui::{CallbackValue, ConfigUI};
+
+    #[derive(Default, Clone, Copy, PartialEq, Debug, Serialize, ConfigUI)]
+    enum Server {
+        #[default]
+        Official,
+        #[config(label = "bilibili")]
+        Bilibili,
+    }
+
+    #[derive(Default, Clone, Serialize, ConfigUI)]
+    struct Account {
+        username: String,
+        server: Server,
+    }
+
+    #[derive(Default, Clone, Serialize, ConfigUI)]
+    struct Config {
+        #[config(label = "Name")]
+        name: String,
+        enable: bool,
+        #[config(range = 1..=10, visible = self.enable)]
+        times: u32,
+        #[serde(rename = "accounts", default)]
+        account: Vec<Account>,
+        #[config(skip)]
+        secret: String,
+        #[serde(skip)]
+        launched: bool,
+    }
+
+    fn render(config: &Config) -> (serde_json::Value, Vec<super::super::CallbackFunc<Config>>) {
+        let mut view = config.config_ui(&Field::default(), Lens::root());
+        let callback = view.collect_callback();
+        (serde_json::to_value(&view).unwrap(), callback)
+    }
+
+    fn labels(value: &serde_json::Value, out: &mut Vec<String>) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for key in ["label", "title"] {
+                    if let Some(serde_json::Value::String(x)) = map.get(key) {
+                        if !x.is_empty() {
+                            out.push(x.clone());
+                        }
+                    }
+                }
+                map.values().for_each(|x| labels(x, out));
+            }
+            serde_json::Value::Array(list) => list.iter().for_each(|x| labels(x, out)),
+            _ => {}
+        }
+    }
+
+    // callback by id with a dummy context, as the render loop does
+    fn call(config: &mut Config, pointer: &str, value: Box<dyn CallbackValue>) {
+        let (json, callback) = render(config);
+        let id = json
+            .pointer(&format!("{pointer}/callbackId"))
+            .and_then(|x| x.as_u64())
+            .unwrap_or_else(|| panic!("no callback at {pointer}"));
+        let (event_sender, _) = std::sync::mpsc::channel();
+        let ui = UIContext {
+            event_sender,
+            frontend: crate::ui::Frontend::Headless(Default::default()),
+        };
+        callback[id as usize](config, value, ui);
+    }
+
+    #[test]
+    fn layout_follows_attributes() {
+        let mut config = Config {
+            account: vec![Account::default()],
+            ..Default::default()
+        };
+        let (json, _) = render(&config);
+        let mut found = vec![];
+        labels(&json, &mut found);
+        assert_eq!(found, ["enable", "account", "account 1", "server"]);
+        assert_eq!(json["type"], "Section");
+        assert_eq!(
+            json.pointer("/content/0/content/0/content").unwrap(),
+            "Name"
+        );
+        assert_eq!(
+            json.pointer("/content/0/content/1/type").unwrap(),
+            "TextField"
+        );
+
+        config.enable = true;
+        let (json, _) = render(&config);
+        let times = json.pointer("/content/2").unwrap();
+        assert_eq!(times["type"], "Slider");
+        assert_eq!(times["label"], "times");
+        assert_eq!(times["min"], 1.0);
+        assert_eq!(times["max"], 10.0);
+        let select = json
+            .pointer("/content/3/content/0/content/0/content/1")
+            .unwrap();
+        assert_eq!(select["type"], "Select");
+        assert_eq!(
+            select["options"],
+            serde_json::json!(["Official", "bilibili"])
+        );
+    }
+
+    #[test]
+    fn callbacks_write_through_lens() {
+        let mut config = Config {
+            enable: true,
+            ..Default::default()
+        };
+        call(
+            &mut config,
+            "/content/0/content/1",
+            Box::new("bob".to_string()),
+        );
+        assert_eq!(config.name, "bob");
+
+        call(&mut config, "/content/2", Box::new(42.0f64));
+        assert_eq!(config.times, 10);
+        call(&mut config, "/content/2", Box::new(3.4f64));
+        assert_eq!(config.times, 3);
+
+        call(&mut config, "/content/1", Box::new(false));
+        assert!(!config.enable);
+
+        // times is hidden now, account moves up
+        call(&mut config, "/content/2/content/0", Box::new(()));
+        call(&mut config, "/content/2/content/1", Box::new(()));
+        assert_eq!(config.account.len(), 2);
+
+        call(
+            &mut config,
+            "/content/2/content/1/content/0/content/1",
+            Box::new(1usize),
+        );
+        assert_eq!(config.account[1].server, Server::Bilibili);
+        assert_eq!(config.account[0].server, Server::Official);
+
+        call(&mut config, "/content/2/content/0/content/1", Box::new(()));
+        assert_eq!(config.account.len(), 1);
+        assert_eq!(config.account[0].server, Server::Bilibili);
+    }
+}
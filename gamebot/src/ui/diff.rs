@@ -0,0 +1,316 @@
+// patches turning the last rendered tree into the new one, so the host gets
+// what changed instead of the whole layout on every event
+//
+// works on the serialized json: objects with the same "type" and "key" are
+// patched field by field, lists of elements are matched by key, falling back
+// to position for elements without one
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+// field of an object or index into a list
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Seg {
+    Field(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "op")]
+pub enum Patch {
+    // node at path
+    Replace {
+        path: Vec<Seg>,
+        node: Value,
+    },
+    // set and drop fields of the object at path
+    Update {
+        path: Vec<Seg>,
+        props: Map<String, Value>,
+        removed: Vec<String>,
+    },
+    // list at path
+    Insert {
+        path: Vec<Seg>,
+        index: usize,
+        node: Value,
+    },
+    Remove {
+        path: Vec<Seg>,
+        index: usize,
+    },
+    Move {
+        path: Vec<Seg>,
+        from: usize,
+        to: usize,
+    },
+}
+
+pub fn diff(old: &Value, new: &Value) -> Vec<Patch> {
+    let mut out = vec![];
+    diff_node(old, new, &mut vec![], &mut out);
+    out
+}
+
+fn diff_node(old: &Value, new: &Value, path: &mut Vec<Seg>, out: &mut Vec<Patch>) {
+    if old == new {
+        return;
+    }
+    match (old, new) {
+        (Value::Object(o), Value::Object(n)) if same_node(o, n) => diff_object(o, n, path, out),
+        _ => out.push(Patch::Replace {
+            path: path.clone(),
+            node: new.clone(),
+        }),
+    }
+}
+
+fn same_node(old: &Map<String, Value>, new: &Map<String, Value>) -> bool {
+    old.get("type") == new.get("type") && old.get("key") == new.get("key")
+}
+
+fn diff_object(
+    old: &Map<String, Value>,
+    new: &Map<String, Value>,
+    path: &mut Vec<Seg>,
+    out: &mut Vec<Patch>,
+) {
+    let mut props = Map::new();
+    for (k, n) in new {
+        let Some(o) = old.get(k) else {
+            props.insert(k.clone(), n.clone());
+            continue;
+        };
+        if o == n {
+            continue;
+        }
+        path.push(Seg::Field(k.clone()));
+        match (o, n) {
+            (Value::Object(o), Value::Object(n)) if same_node(o, n) => diff_object(o, n, path, out),
+            (Value::Array(o), Value::Array(n)) if is_list(o) && is_list(n) => {
+                diff_list(o, n, path, out)
+            }
+            _ => {
+                props.insert(k.clone(), n.clone());
+            }
+        }
+        path.pop();
+    }
+    let removed: Vec<_> = old
+        .keys()
+        .filter(|k| !new.contains_key(*k))
+        .cloned()
+        .collect();
+    if !props.is_empty() || !removed.is_empty() {
+        out.push(Patch::Update {
+            path: path.clone(),
+            props,
+            removed,
+        });
+    }
+}
+
+// children rather than a plain value such as the options of a select
+fn is_list(x: &[Value]) -> bool {
+    x.iter().all(Value::is_object)
+}
+
+fn key(x: &Value) -> Option<&Value> {
+    x.get("key")
+}
+
+fn diff_list(old: &[Value], new: &[Value], path: &mut Vec<Seg>, out: &mut Vec<Patch>) {
+    let new_keys: HashSet<_> = new.iter().filter_map(key).map(Value::to_string).collect();
+    let mut working: Vec<&Value> = old.iter().collect();
+
+    // keyed children that are gone, from the back so indices hold
+    for i in (0..working.len()).rev() {
+        if let Some(k) = key(working[i]) {
+            if !new_keys.contains(&k.to_string()) {
+                working.remove(i);
+                out.push(Patch::Remove {
+                    path: path.clone(),
+                    index: i,
+                });
+            }
+        }
+    }
+
+    for (i, n) in new.iter().enumerate() {
+        let found = match key(n) {
+            Some(k) => (i..working.len()).find(|&j| key(working[j]) == Some(k)),
+            None => (i < working.len() && key(working[i]).is_none()).then_some(i),
+        };
+        match found {
+            Some(j) => {
+                if j != i {
+                    let x = working.remove(j);
+                    working.insert(i, x);
+                    out.push(Patch::Move {
+                        path: path.clone(),
+                        from: j,
+                        to: i,
+                    });
+                }
+                path.push(Seg::Index(i));
+                diff_node(working[i], n, path, out);
+                path.pop();
+            }
+            None => {
+                working.insert(i, n);
+                out.push(Patch::Insert {
+                    path: path.clone(),
+                    index: i,
+                    node: n.clone(),
+                });
+            }
+        }
+    }
+
+    while working.len() > new.len() {
+        working.pop();
+        out.push(Patch::Remove {
+            path: path.clone(),
+            index: working.len(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    // what the host does with a patch
+    fn apply(root: &mut Value, patch: &Patch) {
+        let (Patch::Replace { path, .. }
+        | Patch::Update { path, .. }
+        | Patch::Insert { path, .. }
+        | Patch::Remove { path, .. }
+        | Patch::Move { path, .. }) = patch;
+        let mut target = root;
+        for seg in path {
+            target = match seg {
+                Seg::Field(k) => &mut target[k.as_str()],
+                Seg::Index(i) => &mut target[*i],
+            };
+        }
+        match patch.clone() {
+            Patch::Replace { node, .. } => *target = node,
+            Patch::Update { props, removed, .. } => {
+                let target = target.as_object_mut().unwrap();
+                target.extend(props);
+                removed.iter().for_each(|k| {
+                    target.remove(k);
+                });
+            }
+            Patch::Insert { index, node, .. } => target.as_array_mut().unwrap().insert(index, node),
+            Patch::Remove { index, .. } => {
+                target.as_array_mut().unwrap().remove(index);
+            }
+            Patch::Move { from, to, .. } => {
+                let list = target.as_array_mut().unwrap();
+                let x = list.remove(from);
+                list.insert(to, x);
+            }
+        }
+    }
+
+    fn check(old: Value, new: Value) -> Vec<Patch> {
+        let patch = diff(&old, &new);
+        let mut applied = old;
+        patch.iter().for_each(|x| apply(&mut applied, x));
+        assert_eq!(applied, new, "{patch:#?}");
+        patch
+    }
+
+    fn text(content: &str) -> Value {
+        json!({"type": "Text", "content": content})
+    }
+
+    fn keyed(key: &str) -> Value {
+        json!({"type": "Text", "content": key, "key": key})
+    }
+
+    #[test]
+    fn unchanged_is_empty() {
+        let tree = json!({"type": "Column", "content": [text("a"), text("b")]});
+        assert!(check(tree.clone(), tree).is_empty());
+    }
+
+    #[test]
+    fn update_props_in_place() {
+        let patch = check(
+            json!({"type": "Column", "content": [text("a"), {"type": "Progress", "value": 0.1}]}),
+            json!({"type": "Column", "content": [text("a"), {"type": "Progress", "value": 0.2}]}),
+        );
+        assert_eq!(
+            patch,
+            [Patch::Update {
+                path: vec![Seg::Field("content".into()), Seg::Index(1)],
+                props: json!({"value": 0.2}).as_object().unwrap().clone(),
+                removed: vec![],
+            }]
+        );
+    }
+
+    #[test]
+    fn type_change_replaces() {
+        let patch = check(
+            json!({"type": "Column", "content": [text("a")]}),
+            json!({"type": "Column", "content": [{"type": "Divider"}]}),
+        );
+        assert!(matches!(patch[..], [Patch::Replace { .. }]));
+        check(text("a"), json!({"type": "Row", "content": []}));
+    }
+
+    #[test]
+    fn keyed_list() {
+        let patch = check(
+            json!({"type": "LazyList", "content": [keyed("a"), keyed("b"), keyed("c")]}),
+            json!({"type": "LazyList", "content": [keyed("c"), keyed("a"), keyed("d")]}),
+        );
+        // b removed, c moved, d inserted, a untouched
+        assert_eq!(patch.len(), 3, "{patch:#?}");
+        assert!(patch
+            .iter()
+            .all(|x| !matches!(x, Patch::Replace { .. } | Patch::Update { .. })));
+    }
+
+    #[test]
+    fn unkeyed_list() {
+        check(
+            json!({"type": "Column", "content": [text("a"), text("b"), text("c")]}),
+            json!({"type": "Column", "content": [text("a"), text("x")]}),
+        );
+        check(
+            json!({"type": "Column", "content": []}),
+            json!({"type": "Column", "content": [keyed("a"), text("b"), keyed("c")]}),
+        );
+        check(
+            json!({"type": "Column", "content": [keyed("a"), text("b"), keyed("c")]}),
+            json!({"type": "Column", "content": [text("b"), keyed("c"), text("d"), keyed("a")]}),
+        );
+    }
+
+    #[test]
+    fn plain_values_are_props() {
+        let patch = check(
+            json!({"type": "Select", "options": [], "selected": 0}),
+            json!({"type": "Select", "options": ["a", "b"], "selected": 1, "label": "x"}),
+        );
+        assert_eq!(patch.len(), 1);
+        check(
+            json!({"type": "NavHost", "children": {"a": text("a")}, "start": "a"}),
+            json!({"type": "NavHost", "children": {"a": text("b"), "c": text("c")}, "start": "a"}),
+        );
+        check(
+            json!({"type": "Text", "content": "a", "extra": 1}),
+            text("a"),
+        );
+    }
+}
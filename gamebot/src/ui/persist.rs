@@ -0,0 +1,262 @@
+// UI state saved as json so config survives a restart of the guest
+//
+// the file holds {"version": n, "state": ..}. bumping the version with a
+// migration for the old one lets a renamed or retyped field keep its value
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use anyhow::{bail, Context};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::api::data_dir;
+
+type Migration = Arc<dyn Fn(&mut Value) -> anyhow::Result<()> + Send + Sync>;
+
+#[derive(Clone)]
+pub struct Persist {
+    path: PathBuf,
+    version: u32,
+    // from version n to n + 1
+    migrations: BTreeMap<u32, Migration>,
+    debounce: Duration,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Saved<T> {
+    version: u32,
+    state: T,
+}
+
+impl Persist {
+    // <data_dir>/<name>.json
+    pub fn new(name: &str) -> Self {
+        Self::at(data_dir().join(format!("{name}.json")))
+    }
+
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Persist {
+            path: path.into(),
+            version: 0,
+            migrations: BTreeMap::new(),
+            debounce: Duration::from_millis(500),
+        }
+    }
+
+    // version of the State written now
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    // turn state saved at version from into from + 1
+    pub fn migrate(
+        mut self,
+        from: u32,
+        f: impl Fn(&mut Value) -> anyhow::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.migrations.insert(from, Arc::new(f));
+        self
+    }
+
+    // quiet time after the last change before writing
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // versioned json, what import takes, also on another device
+    pub fn export<State: Serialize>(&self, state: &State) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(&Saved {
+            version: self.version,
+            state,
+        })?)
+    }
+
+    pub fn import<State: DeserializeOwned>(&self, json: &str) -> anyhow::Result<State> {
+        let Saved { version, mut state } = serde_json::from_str::<Saved<Value>>(json)?;
+        if version > self.version {
+            bail!(
+                "config version {version} is newer than {}, update the script",
+                self.version
+            );
+        }
+        for from in version..self.version {
+            let Some(migration) = self.migrations.get(&from) else {
+                bail!("no migration from config version {from}");
+            };
+            migration(&mut state).with_context(|| format!("migrate from version {from}"))?;
+        }
+        Ok(serde_json::from_value(state)?)
+    }
+
+    // none if nothing was saved yet
+    pub fn load<State: DeserializeOwned>(&self) -> anyhow::Result<Option<State>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(json) => self
+                .import(&json)
+                .map(Some)
+                .with_context(|| format!("load {}", self.path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save<State: Serialize>(&self, state: &State) -> anyhow::Result<()> {
+        self.write(&self.export(state)?)
+    }
+
+    // written aside first so a kill in between leaves the old file intact
+    fn write(&self, json: &str) -> anyhow::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+// background writer, keeps the latest export until changes stop for the
+// debounce time, flushes on drop
+pub(super) struct Saver {
+    sender: Option<Sender<String>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Saver {
+    pub(super) fn new(persist: Persist) -> Self {
+        let (sender, receiver) = channel::<String>();
+        let handle = std::thread::spawn(move || {
+            while let Ok(mut json) = receiver.recv() {
+                let quit = loop {
+                    match receiver.recv_timeout(persist.debounce) {
+                        Ok(newer) => json = newer,
+                        Err(RecvTimeoutError::Timeout) => break false,
+                        Err(RecvTimeoutError::Disconnected) => break true,
+                    }
+                };
+                if let Err(e) = persist.write(&json) {
+                    log::warn!("save {}: {e:#}", persist.path.display());
+                }
+                if quit {
+                    break;
+                }
+            }
+        });
+        Saver {
+            sender: Some(sender),
+            handle: Some(handle),
+        }
+    }
+
+    pub(super) fn send(&self, json: String) {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(json);
+        }
+    }
+}
+
+impl Drop for Saver {
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        times: u32,
+    }
+
+    // v0 had count, v1 renamed it to times, v2 added name
+    fn persist(path: PathBuf) -> Persist {
+        Persist::at(path)
+            .version(2)
+            .migrate(0, |state| {
+                state["times"] = state["count"].take();
+                Ok(())
+            })
+            .migrate(1, |state| {
+                state["name"] = json!("default");
+                Ok(())
+            })
+    }
+
+    #[test]
+    fn migrate_old_versions() {
+        let persist = persist(PathBuf::new());
+        let old = json!({"version": 0, "state": {"count": 3}}).to_string();
+        assert_eq!(
+            persist.import::<Config>(&old).unwrap(),
+            Config {
+                name: "default".into(),
+                times: 3
+            }
+        );
+
+        let newer = json!({"version": 3, "state": {}}).to_string();
+        assert!(persist.import::<Config>(&newer).is_err());
+
+        let gap = Persist::at("").version(2).migrate(1, |_| Ok(()));
+        assert!(gap.import::<Value>(&old).is_err());
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = TempDir::new("persist-save");
+        let persist = persist(dir.join("config.json"));
+        assert_eq!(persist.load::<Config>().unwrap(), None);
+
+        let config = Config {
+            name: "bob".into(),
+            times: 5,
+        };
+        persist.save(&config).unwrap();
+        assert_eq!(persist.load::<Config>().unwrap(), Some(config));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn saver_writes_last_change() {
+        let dir = TempDir::new("persist-saver");
+        let persist = persist(dir.join("config.json")).debounce(Duration::from_secs(60));
+        let saver = Saver::new(persist.clone());
+        for times in 0..5 {
+            let config = Config {
+                name: "bob".into(),
+                times,
+            };
+            saver.send(persist.export(&config).unwrap());
+        }
+        // still waiting for quiet, drop flushes
+        assert!(!persist.path().exists());
+        drop(saver);
+        assert_eq!(persist.load::<Config>().unwrap().unwrap().times, 4);
+    }
+}
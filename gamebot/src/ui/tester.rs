@@ -0,0 +1,258 @@
+// runs a UI without the host, for tests of view and callback logic
+//
+// elements are addressed by json pointer into the rendered tree, as found by
+// find_text or find_key. events apply in the order sent, each followed by a
+// rerender like the render loop does
+
+use std::{
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail};
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{CallbackValue, Element, Frontend, UIContext, UIEvent, UI};
+
+pub struct UiTester<State> {
+    ui: UI<State>,
+    exited: bool,
+}
+
+impl<State: Serialize> UiTester<State> {
+    pub fn new(
+        state: State,
+        view: impl Fn(&mut State, UIContext<State>) -> Element<State> + 'static,
+    ) -> Self {
+        let mut ui = UI::new(state, view);
+        ui.frontend = Frontend::Headless(Arc::default());
+        ui.render();
+        UiTester { ui, exited: false }
+    }
+
+    pub fn state(&self) -> &State {
+        &self.ui.state
+    }
+
+    // change state from outside, rerenders
+    pub fn update(&mut self, f: impl FnOnce(&mut State)) {
+        f(&mut self.ui.state);
+        self.ui.render();
+    }
+
+    pub fn into_state(self) -> State {
+        self.ui.state
+    }
+
+    // rendered tree as sent to the host
+    pub fn json(&self) -> &Value {
+        self.ui.tree.as_ref().unwrap()
+    }
+
+    pub fn get(&self, pointer: &str) -> anyhow::Result<&Value> {
+        self.json()
+            .pointer(pointer)
+            .ok_or_else(|| anyhow!("nothing at {pointer:?}"))
+    }
+
+    // first element in tree order showing text as content, label or title
+    pub fn find_text(&self, text: &str) -> anyhow::Result<String> {
+        self.find(|x| {
+            ["content", "label", "title"]
+                .iter()
+                .any(|k| x.get(*k).and_then(Value::as_str) == Some(text))
+        })
+        .ok_or_else(|| anyhow!("no element shows {text:?}"))
+    }
+
+    pub fn find_key(&self, key: &str) -> anyhow::Result<String> {
+        self.find(|x| x.get("key").and_then(Value::as_str) == Some(key))
+            .ok_or_else(|| anyhow!("no element has key {key:?}"))
+    }
+
+    fn find(&self, f: impl Fn(&Value) -> bool) -> Option<String> {
+        fn walk(x: &Value, pointer: String, f: &dyn Fn(&Value) -> bool) -> Option<String> {
+            match x {
+                Value::Object(map) => {
+                    if map.contains_key("type") && f(x) {
+                        return Some(pointer);
+                    }
+                    map.iter()
+                        .find_map(|(k, v)| walk(v, format!("{pointer}/{}", escape(k)), f))
+                }
+                Value::Array(list) => list
+                    .iter()
+                    .enumerate()
+                    .find_map(|(i, v)| walk(v, format!("{pointer}/{i}"), f)),
+                _ => None,
+            }
+        }
+        walk(self.json(), String::new(), &f)
+    }
+
+    pub fn click(&mut self, pointer: &str) -> anyhow::Result<()> {
+        self.send(pointer, ())
+    }
+
+    pub fn input(&mut self, pointer: &str, text: &str) -> anyhow::Result<()> {
+        self.send(pointer, text.to_string())
+    }
+
+    // callback of the element at pointer, or of the closest parent having one,
+    // so clicking the text inside a button clicks the button
+    pub fn send(&mut self, pointer: &str, value: impl CallbackValue) -> anyhow::Result<()> {
+        self.get(pointer)?;
+        let mut at = pointer;
+        let id = loop {
+            if let Some(id) = self.get(at)?.get("callbackId").and_then(Value::as_u64) {
+                break id as usize;
+            }
+            match at.rfind('/') {
+                Some(i) => at = &at[..i],
+                None => bail!("no callback at or above {pointer:?}"),
+            }
+        };
+        let event = UIEvent::Callback {
+            id,
+            value: Box::new(value),
+        };
+        self.apply(vec![event]);
+        self.pump();
+        Ok(())
+    }
+
+    // apply what update and spawned threads sent so far, including what
+    // those events send in turn, number of events
+    pub fn pump(&mut self) -> usize {
+        let mut n = 0;
+        loop {
+            let event: Vec<_> = self.ui.event_receiver.try_iter().collect();
+            if event.is_empty() {
+                return n;
+            }
+            n += event.len();
+            self.apply(event);
+        }
+    }
+
+    // pump until spawned threads are done, err if they run past timeout
+    pub fn settle(&mut self, timeout: Duration) -> anyhow::Result<()> {
+        let start = Instant::now();
+        loop {
+            let running = self.running();
+            self.pump();
+            if running == 0 {
+                return Ok(());
+            }
+            if start.elapsed() > timeout {
+                bail!("{running} spawned threads still running after {timeout:?}");
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    pub fn running(&self) -> usize {
+        match &self.ui.frontend {
+            Frontend::Headless(count) => count.load(Ordering::SeqCst),
+            _ => unreachable!(),
+        }
+    }
+
+    // UIContext::exit was called
+    pub fn exited(&self) -> bool {
+        self.exited
+    }
+
+    fn apply(&mut self, event: Vec<UIEvent<State>>) {
+        if self.exited {
+            return;
+        }
+        self.exited = self.ui.handle(event);
+        self.ui.render();
+    }
+}
+
+// json pointer escaping of a field name
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+    use serde_json::json;
+
+    use super::*;
+    use crate::ui::{button, col, row, text, text_field};
+
+    #[derive(Default, Serialize)]
+    struct State {
+        name: String,
+        count: usize,
+        log: Vec<String>,
+    }
+
+    fn view(state: &mut State, _: UIContext<State>) -> Element<State> {
+        col([
+            text_field(&state.name, |state: &mut State, new, _| state.name = new),
+            row([
+                button("add", |state: &mut State, _| state.count += 1),
+                button("later", |_: &mut State, ui| {
+                    ui.update(|state| state.log.push("updated".into()))
+                }),
+                button("work", |_: &mut State, ui| {
+                    ui.spawn(|ui| {
+                        std::thread::sleep(Duration::from_millis(20));
+                        ui.update(|state| state.log.push("spawned".into()));
+                    });
+                }),
+                button("quit", |_: &mut State, ui| ui.exit()),
+            ]),
+            col(state.log.iter().enumerate().map(|(i, x)| text(x).key(i))),
+        ])
+    }
+
+    #[test]
+    fn click_and_input() {
+        let mut tester = UiTester::new(State::default(), view);
+
+        let add = tester.find_text("add").unwrap();
+        assert_eq!(add, "/content/1/content/0/content");
+        tester.click(&add).unwrap();
+        tester.click(&add).unwrap();
+        assert_eq!(tester.state().count, 2);
+
+        tester.input("/content/0", "bob").unwrap();
+        assert_eq!(tester.state().name, "bob");
+        assert_eq!(tester.get("/content/0/content").unwrap(), "bob");
+
+        assert!(tester.click("/content/2").is_err());
+        assert!(tester.find_text("missing").is_err());
+    }
+
+    #[test]
+    fn update_and_spawn() {
+        let mut tester = UiTester::new(State::default(), view);
+        let later = tester.find_text("later").unwrap();
+        tester.click(&later).unwrap();
+        // update from a callback is applied before click returns
+        assert_eq!(tester.state().log, ["updated"]);
+        assert_eq!(
+            tester.get(&tester.find_key("0").unwrap()).unwrap(),
+            &json!({"type": "Text", "content": "updated", "key": "0"})
+        );
+
+        let work = tester.find_text("work").unwrap();
+        tester.click(&work).unwrap();
+        assert_eq!(tester.running(), 1);
+        tester.settle(Duration::from_secs(5)).unwrap();
+        assert_eq!(tester.state().log, ["updated", "spawned"]);
+
+        let quit = tester.find_text("quit").unwrap();
+        tester.click(&quit).unwrap();
+        assert!(tester.exited());
+        tester.click(&later).unwrap();
+        assert_eq!(tester.state().log.len(), 2);
+    }
+}
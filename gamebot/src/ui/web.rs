@@ -0,0 +1,182 @@
+// config UI in a browser, for headless emulators or driving a device from a pc
+//
+// serves a page rendering the same Element json as the host does. each
+// browser gets the whole tree on connect, then numbered patches, and sends
+// UIEvent::Callback back over the websocket
+
+use std::{
+    net::{SocketAddr, ToSocketAddrs},
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::{Html, IntoResponse},
+    routing::get,
+    Router,
+};
+use serde_json::{json, Value};
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    watch,
+};
+
+use super::Patch;
+
+const INDEX: &str = include_str!("web/index.html");
+
+// numbered message for browsers, a tree or patches to the one before
+#[derive(Clone)]
+struct Update {
+    version: u64,
+    text: String,
+}
+
+struct Shared {
+    // what a new browser starts from
+    snapshot: Mutex<Update>,
+    updates: broadcast::Sender<Update>,
+    on_event: Box<dyn Fn(&str) -> anyhow::Result<()> + Send + Sync>,
+    stop: watch::Sender<bool>,
+}
+
+pub(super) struct Server {
+    shared: Arc<Shared>,
+    addr: SocketAddr,
+}
+
+impl Server {
+    // binds before returning so a taken port is reported to the caller,
+    // serving runs on its own thread until the server is dropped
+    pub(super) fn start(
+        addr: impl ToSocketAddrs,
+        on_event: impl Fn(&str) -> anyhow::Result<()> + Send + Sync + 'static,
+    ) -> anyhow::Result<Self> {
+        let listener = std::net::TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+
+        let (updates, _) = broadcast::channel(64);
+        let (stop, _) = watch::channel(false);
+        let shared = Arc::new(Shared {
+            snapshot: Mutex::new(Update {
+                version: 0,
+                text: json!({"version": 0, "tree": {"type": "Empty"}}).to_string(),
+            }),
+            updates,
+            on_event: Box::new(on_event),
+            stop,
+        });
+
+        let app = Router::new()
+            .route("/", get(index))
+            .route("/ws", get(ws))
+            .with_state(shared.clone());
+        let mut stop = shared.stop.subscribe();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        std::thread::spawn(move || {
+            runtime.block_on(async move {
+                let served = async move {
+                    let listener = tokio::net::TcpListener::from_std(listener)?;
+                    axum::serve(listener, app)
+                        .with_graceful_shutdown(async move {
+                            // set once on drop, an error means it's gone as well
+                            let _ = stop.changed().await;
+                        })
+                        .await
+                };
+                if let Err(e) = served.await {
+                    log::warn!("web ui on {addr}: {e}");
+                }
+            })
+        });
+        log::info!("web ui on http://{addr}");
+        Ok(Server { shared, addr })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    // patch is none for the first tree
+    pub(super) fn publish(&self, tree: &Value, patch: Option<Vec<Patch>>) {
+        if patch.as_ref().is_some_and(|x| x.is_empty()) {
+            return;
+        }
+        // held while broadcasting so a browser reading the snapshot can tell
+        // which broadcasts it already has
+        let mut snapshot = self.shared.snapshot.lock().unwrap();
+        let version = snapshot.version + 1;
+        *snapshot = Update {
+            version,
+            text: json!({"version": version, "tree": tree}).to_string(),
+        };
+        let update = match patch {
+            Some(patch) => Update {
+                version,
+                text: json!({"version": version, "patch": patch}).to_string(),
+            },
+            None => snapshot.clone(),
+        };
+        // no browser connected is fine
+        let _ = self.shared.updates.send(update);
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.shared.stop.send_replace(true);
+    }
+}
+
+async fn index() -> Html<&'static str> {
+    Html(INDEX)
+}
+
+async fn ws(ws: WebSocketUpgrade, State(shared): State<Arc<Shared>>) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| client(socket, shared))
+}
+
+async fn client(mut socket: WebSocket, shared: Arc<Shared>) {
+    let mut stop = shared.stop.subscribe();
+    let mut updates = shared.updates.subscribe();
+    let mut sent = 0;
+    let mut next = Some(shared.snapshot.lock().unwrap().clone());
+    loop {
+        if let Some(update) = next.take() {
+            if update.version <= sent {
+                continue;
+            }
+            sent = update.version;
+            if socket.send(Message::Text(update.text)).await.is_err() {
+                return;
+            }
+        }
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(update) => next = Some(update),
+                // too slow to keep up, start over from the whole tree
+                Err(RecvError::Lagged(_)) => {
+                    sent = 0;
+                    next = Some(shared.snapshot.lock().unwrap().clone());
+                }
+                Err(RecvError::Closed) => return,
+            },
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Text(text))) => {
+                    if let Err(e) = (shared.on_event)(&text) {
+                        log::warn!("web ui event {text}: {e:#}");
+                    }
+                }
+                Some(Ok(_)) => {}
+                _ => return,
+            },
+            _ = stop.changed() => return,
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use crate::datareader::DataReader;
 use crate::Extractor;
 use ncnn_sys::*;
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 
 pub struct Net {
     ptr: ncnn_net_t,
@@ -40,6 +40,28 @@ impl Net {
         }
     }
 
+    pub fn load_param_memory(&mut self, mem: &[u8]) -> anyhow::Result<()> {
+        let c_str = CString::new(mem)?;
+        if unsafe { ncnn_net_load_param_memory(self.ptr, c_str.as_ptr()) } != 0 {
+            anyhow::bail!("Error loading params from memory");
+        } else {
+            Ok(())
+        }
+    }
+
+    // ncnn keeps reading mem after load, so 'static and 4-byte aligned
+    pub fn load_model_memory(&mut self, mem: &'static [u8]) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            mem.as_ptr().align_offset(4) == 0,
+            "model memory must be 4-byte aligned"
+        );
+        if unsafe { ncnn_net_load_model_memory(self.ptr, mem.as_ptr()) } <= 0 {
+            anyhow::bail!("Error loading model from memory");
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn load_model_datareader(&mut self, dr: &DataReader) -> anyhow::Result<()> {
         if unsafe { ncnn_net_load_model_datareader(self.ptr, dr.ptr()) } != 0 {
             anyhow::bail!("Error loading model from datareader");
@@ -48,6 +70,24 @@ impl Net {
         }
     }
 
+    // in param file order
+    pub fn input_names(&self) -> Vec<String> {
+        let count = unsafe { ncnn_net_get_input_count(self.ptr) };
+        (0..count)
+            .map(|i| unsafe { CStr::from_ptr(ncnn_net_get_input_name(self.ptr, i)) })
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    // in param file order
+    pub fn output_names(&self) -> Vec<String> {
+        let count = unsafe { ncnn_net_get_output_count(self.ptr) };
+        (0..count)
+            .map(|i| unsafe { CStr::from_ptr(ncnn_net_get_output_name(self.ptr, i)) })
+            .map(|name| name.to_string_lossy().into_owned())
+            .collect()
+    }
+
     pub fn create_extractor(&mut self) -> Extractor<'_> {
         let ptr;
         unsafe {
@@ -3,8 +3,17 @@ use std::{env, path::PathBuf};
 
 fn download_and_link() {
     let version = "1.19.2";
-    let name = format!("onnxruntime-android-{version}");
-    let url = &format!("https://repo1.maven.org/maven2/com/microsoft/onnxruntime/onnxruntime-android/{version}/{name}.aar");
+    // training package is a superset of the inference one
+    let training = env::var("CARGO_FEATURE_TRAINING").is_ok();
+    let package = if training {
+        "onnxruntime-training-android"
+    } else {
+        "onnxruntime-android"
+    };
+    let name = format!("{package}-{version}");
+    let url = &format!(
+        "https://repo1.maven.org/maven2/com/microsoft/onnxruntime/{package}/{version}/{name}.aar"
+    );
     let cache_dir = dirs::cache_dir().unwrap().join("rust_cached_path");
     std::fs::create_dir_all(&cache_dir).unwrap();
     let cache = cached_path::CacheBuilder::new()
@@ -26,9 +35,14 @@ fn download_and_link() {
         }
     };
 
+    let header = if training {
+        "onnxruntime_training_c_api.h"
+    } else {
+        "onnxruntime_c_api.h"
+    };
     let header_path = path
         .join("headers")
-        .join("onnxruntime_c_api.h")
+        .join(header)
         .into_os_string()
         .into_string()
         .unwrap();